@@ -1,3 +1,98 @@
+use phire::{
+    config::Mods,
+    scene::{GameMode, DEFAULT_REMOTE_PORT},
+};
+use phire_ui::LaunchOptions;
+
+const HELP: &str = "
+Usage: phire-main [options] [chart]
+
+Plays the chart file or folder directly if given, otherwise opens the main menu.
+
+Options:
+    -h, --help              Display this message
+    -m, --mode <mode>       One of normal, exercise, view and tweak-offset
+        --mods <mods>       Comma-separated mods, e.g. autoplay,flip-x
+        --speed <speed>     Playback speed, between 0.1 and 2
+        --start <time>      Chart time in seconds to start playing from
+        --seed <seed>       Seed of the random mod, picked anew for every play if not given
+        --respack <path>    Resource pack file or folder
+        --data-dir <path>   Alternate data directory
+        --remote [port]     Start a remote-controlled previewer (port defaults to 14514)
+";
+
+fn parse_args() -> Result<Option<LaunchOptions>, String> {
+    let mut options = LaunchOptions::default();
+    let mut iter = std::env::args().skip(1).peekable();
+    while let Some(arg) = iter.next() {
+        let mut value = |name: &str| iter.next().ok_or_else(|| format!("Missing value for {name}"));
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{}", HELP.trim());
+                return Ok(None);
+            }
+            "-m" | "--mode" => {
+                options.mode = Some(match value(&arg)?.as_str() {
+                    "normal" => GameMode::Normal,
+                    "exercise" => GameMode::Exercise,
+                    "view" => GameMode::View,
+                    "tweak-offset" => GameMode::TweakOffset,
+                    mode => return Err(format!("Unknown mode: {mode}")),
+                });
+            }
+            "--mods" => {
+                let mut mods = Mods::default();
+                for name in value(&arg)?.split(',').filter(|it| !it.is_empty()) {
+                    mods |= Mods::from_name(&name.to_ascii_uppercase().replace('-', "_")).ok_or_else(|| format!("Unknown mod: {name}"))?;
+                }
+                options.mods = Some(mods);
+            }
+            "--speed" => {
+                // the same range as the speed slider in settings
+                let speed = value(&arg)?.parse::<f32>().ok().filter(|it| (0.1..=2.).contains(it));
+                options.speed = Some(speed.ok_or_else(|| "Invalid speed, expected a number between 0.1 and 2".to_owned())?);
+            }
+            "--start" => {
+                let time = value(&arg)?.parse::<f32>().ok().filter(|it| *it >= 0. && it.is_finite());
+                options.start_time = time.ok_or_else(|| "Invalid start time, expected a non-negative number of seconds".to_owned())?;
+            }
+            "--seed" => {
+                options.seed = Some(value(&arg)?.parse().map_err(|_| "Invalid seed".to_owned())?);
+            }
+            "--respack" => {
+                options.res_pack = Some(value(&arg)?);
+            }
+            "--data-dir" => {
+                options.data_dir = Some(value(&arg)?);
+            }
+            "--remote" => {
+                options.remote = Some(match iter.next_if(|it| !it.starts_with('-')) {
+                    Some(port) => port.parse().map_err(|_| format!("Invalid port: {port}"))?,
+                    None => DEFAULT_REMOTE_PORT,
+                });
+            }
+            _ if arg.starts_with('-') => return Err(format!("Unknown option: {arg}")),
+            _ => {
+                if options.chart.is_some() {
+                    return Err("Too many arguments".to_owned());
+                }
+                options.chart = Some(arg);
+            }
+        }
+    }
+    Ok(Some(options))
+}
+
 fn main() {
-    phire_ui::quad_main();
+    match parse_args() {
+        Ok(Some(options)) => {
+            phire_ui::set_launch_options(options);
+            phire_ui::quad_main();
+        }
+        Ok(None) => {}
+        Err(err) => {
+            eprintln!("{err}\n{}", HELP.trim_end());
+            std::process::exit(2);
+        }
+    }
 }
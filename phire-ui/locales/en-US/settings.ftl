@@ -11,6 +11,10 @@ about = Info
 item-lang = Language
 item-offline = Offline mode
 item-offline-sub = You can't upload playing record in offline mode
+item-rks = Offline RKS
+item-rks-sub = Computed from local records, tap for the breakdown
+rks-breakdown = RKS { $rks }
+rks-breakdown-empty = No local records yet
 item-mp = Multiplayer
 item-mp-sub = Enable multiplayer mode
 item-mp-addr = Multiplayer server
@@ -32,6 +36,11 @@ item-cali = Adjust offset
 item-exclusive-audio = Exclusive Audio (Only Android)
 item-exclusive-audio-sub = Use exclusive access to reduce latency, but may prevent the sound from being recorded
 item-audio-compatibility = Audio Compatibility Mode (Only Android)
+item-nightcore = Nightcore speed
+item-nightcore-sub = Changing the speed also changes the pitch of the music
+item-loudness = Loudness normalization
+item-loudness-sub = Plays every chart's music at a similar volume
+item-target-loudness = Target loudness
 
 item-show-acc = Show real-time accuracy
 item-dc-pause = Double tap to pause
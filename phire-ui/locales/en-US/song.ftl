@@ -28,6 +28,14 @@ delete = Delete
 rate = Rate
 exercise = Exercise
 offset = Adjust offset
+course-add = Add to course
+course-remove = Remove from course
+course-start = Start course
+course-added = Added to course ({ $count } charts)
+course-removed = Removed from course ({ $count } charts)
+course-import = Import course
+course-imported = Imported course { $name } ({ $count } charts)
+course-import-failed = Failed to import course
 
 edit-cancel = Cancel
 edit-save = Save
@@ -100,6 +108,20 @@ mods-flip-x-sub = Flip on the X-axis
 mods-fade-out = Fade out
 mods-fade-out-sub = Notes disappear when they approach the line
 mods-full-screen-judge = Full screen judge
+mods-hidden = Hidden
+mods-hidden-sub = Notes gradually fade out as they approach the line
+mods-sudden = Sudden
+mods-sudden-sub = Notes only appear when they are close to the line
+mods-random = Random
+mods-random-sub = Mirror or shift the notes of each line
+mods-no-line = No line
+mods-no-line-sub = Hide the judge lines
+mods-sudden-death = Sudden death
+mods-sudden-death-sub = Fail on the first Bad or Miss
+mods-perfect-only = Perfect only
+mods-perfect-only-sub = Fail on anything other than Perfect
+mods-life-gauge = Life gauge
+mods-life-gauge-sub = Fail when the life gauge runs out
 
 rate-failed = Rate failed
 rate-done = Rated successfully
@@ -11,6 +11,10 @@ about = 关于
 item-lang = 语言
 item-offline = 离线模式
 item-offline-sub = 在离线模式下将不能上传成绩
+item-rks = 离线 RKS
+item-rks-sub = 根据本地成绩计算，点击查看详情
+rks-breakdown = RKS { $rks }
+rks-breakdown-empty = 暂无本地成绩
 item-mp = 多人游戏
 item-mp-sub = 启用多人游戏
 item-mp-addr = 多人游戏服务器
@@ -32,6 +36,11 @@ item-cali = 调整延迟
 item-exclusive-audio = 独占音频 (仅 Android)
 item-exclusive-audio-sub = 尝试独占输出设备，可以降低音频延时，但会导致声音无法被录制
 item-audio-compatibility = 音频兼容模式 (仅 Android)
+item-nightcore = Nightcore 变速
+item-nightcore-sub = 调整速度时音乐音调随之改变
+item-loudness = 响度均衡
+item-loudness-sub = 使不同谱面的音乐音量保持一致
+item-target-loudness = 目标响度
 
 item-show-acc = 显示实时准度
 item-dc-pause = 双击暂停
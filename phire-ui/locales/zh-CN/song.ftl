@@ -28,6 +28,14 @@ delete = 删除
 rate = 评分
 exercise = 练习
 offset = 调整延迟
+course-add = 加入课题
+course-remove = 移出课题
+course-start = 开始课题
+course-added = 已加入课题（共 { $count } 首）
+course-removed = 已移出课题（共 { $count } 首）
+course-import = 导入课题
+course-imported = 已导入课题 { $name }（共 { $count } 首）
+course-import-failed = 导入课题失败
 
 edit-cancel = 取消
 edit-save = 保存
@@ -99,6 +107,20 @@ mods-flip-x-sub = 在 X 轴上反转谱面
 mods-fade-out = 下隐
 mods-fade-out-sub = 音符在靠近判定线时会隐藏
 mods-full-screen-judge = 全屏判定
+mods-hidden = 渐隐
+mods-hidden-sub = 音符在靠近判定线时逐渐消失
+mods-sudden = 骤现
+mods-sudden-sub = 音符在靠近判定线时才会出现
+mods-random = 随机
+mods-random-sub = 随机镜像或平移每条判定线上的音符
+mods-no-line = 无判定线
+mods-no-line-sub = 隐藏判定线
+mods-sudden-death = 猝死
+mods-sudden-death-sub = 出现 Bad 或 Miss 时立即失败
+mods-perfect-only = 完美模式
+mods-perfect-only-sub = 出现非 Perfect 判定时立即失败
+mods-life-gauge = 血条
+mods-life-gauge-sub = 血量耗尽时失败
 
 rate-failed = 评分失败
 rate-done = 评分成功
@@ -4,7 +4,7 @@ use crate::{
     icons::Icons,
     page::{ChartItem, Fader, Illustration},
     save_data,
-    scene::{SongScene, MP_PANEL},
+    scene::{split_local_path, SongScene, MP_PANEL},
 };
 use anyhow::Result;
 use macroquad::prelude::*;
@@ -249,8 +249,12 @@ impl ChartsView {
                         } else {
                             format!("download/{}", item.chart.info.id.unwrap())
                         };
-                        std::fs::remove_dir_all(format!("{}/{path}", dir::charts()?))?;
-                        data.charts.remove(data.find_chart_by_path(path.as_str()).unwrap());
+                        std::fs::remove_dir_all(format!("{}/{}", dir::charts()?, split_local_path(&path).0))?;
+                        // all difficulties share the same package
+                        for index in data.find_difficulties(&path).into_iter().rev() {
+                            data.charts.remove(index);
+                        }
+                        data.update_offline_rks();
                         save_data()?;
                         NEED_UPDATE.store(true, Ordering::SeqCst);
                     } else {
@@ -1,16 +1,18 @@
 use crate::{
     client::{Ptr, User},
     dir,
+    scene::{local_path_of, split_local_path},
 };
 use anyhow::Result;
 use chrono::{DateTime, Utc};
 use phire::{
     config::{Config, Mods},
     info::ChartInfo,
-    scene::SimpleRecord,
+    rks::{RksBreakdown, RksContext, RksRecord},
+    scene::{CourseInfo, SimpleRecord},
 };
 use serde::{Deserialize, Serialize};
-use std::{collections::HashSet, ops::DerefMut, path::Path};
+use std::{collections::HashSet, ops::DerefMut, path::Path, sync::Mutex};
 
 fn default_score_total() -> u32 {
     1_000_000
@@ -64,6 +66,18 @@ pub struct LocalChart {
     pub record: Option<SimpleRecord>,
     #[serde(default)]
     pub mods: Mods,
+    #[serde(default)]
+    pub loudness: Option<f32>,
+}
+
+impl LocalChart {
+    pub fn rks_record(&self) -> Option<RksRecord> {
+        self.record.as_ref().map(|rec| RksRecord {
+            difficulty: self.info.difficulty,
+            accuracy: rec.accuracy,
+            all_perfect: rec.score >= 1_000_000,
+        })
+    }
 }
 
 #[derive(Default, Serialize, Deserialize)]
@@ -79,33 +93,39 @@ pub struct Data {
     pub respacks: Vec<String>,
     pub respack_id: usize,
     pub accept_invalid_cert: bool,
+    pub course: CourseInfo,
+    /// RKS of [`Data::rks_breakdown`], until records change. Tasks updating records read it as well, hence the lock.
+    #[serde(skip)]
+    rks_cache: Mutex<Option<f32>>,
 }
 
 impl Data {
     pub async fn init(&mut self) -> Result<()> {
         let charts = dir::charts()?;
-        self.charts.retain(|it| Path::new(&format!("{}/{}", charts, it.local_path)).exists());
+        self.charts.retain(|it| Path::new(&format!("{}/{}", charts, split_local_path(&it.local_path).0)).exists());
+        // packages already listed may have gained difficulties since
         let occurred: HashSet<_> = self.charts.iter().map(|it| it.local_path.clone()).collect();
         for entry in std::fs::read_dir(dir::custom_charts()?)? {
             let entry = entry?;
             let filename = entry.file_name();
             let filename = filename.to_str().unwrap();
             let filename = format!("custom/{filename}");
-            if occurred.contains(&filename) {
-                continue;
-            }
             let path = entry.path();
             let Ok(mut fs) = phire::fs::fs_from_file(&path) else {
                 continue;
             };
-            let result = phire::fs::load_info(fs.deref_mut()).await;
-            if let Ok(info) = result {
-                self.charts.push(LocalChart {
-                    info: BriefChartInfo { id: None, ..info.into() },
-                    local_path: filename,
-                    record: None,
-                    mods: Mods::default(),
-                });
+            let result = phire::fs::load_infos(fs.deref_mut()).await;
+            if let Ok(infos) = result {
+                self.charts.extend(infos.into_iter().enumerate().filter_map(|(index, info)| {
+                    let local_path = local_path_of(&filename, index);
+                    (!occurred.contains(&local_path)).then(|| LocalChart {
+                        info: BriefChartInfo { id: None, ..info.into() },
+                        local_path,
+                        record: None,
+                        mods: Mods::default(),
+                        loudness: None,
+                    })
+                }));
             }
         }
         for entry in std::fs::read_dir(dir::downloaded_charts()?)? {
@@ -114,21 +134,22 @@ impl Data {
             let filename = filename.to_str().unwrap();
             let filename = format!("download/{filename}");
             let Ok(id): Result<i32, _> = filename.parse() else { continue };
-            if occurred.contains(&filename) {
-                continue;
-            }
             let path = entry.path();
             let Ok(mut fs) = phire::fs::fs_from_file(&path) else {
                 continue;
             };
-            let result = phire::fs::load_info(fs.deref_mut()).await;
-            if let Ok(info) = result {
-                self.charts.push(LocalChart {
-                    info: BriefChartInfo { id: Some(id), ..info.into() },
-                    local_path: filename,
-                    record: None,
-                    mods: Mods::default(),
-                });
+            let result = phire::fs::load_infos(fs.deref_mut()).await;
+            if let Ok(infos) = result {
+                self.charts.extend(infos.into_iter().enumerate().filter_map(|(index, info)| {
+                    let local_path = local_path_of(&filename, index);
+                    (!occurred.contains(&local_path)).then(|| LocalChart {
+                        info: BriefChartInfo { id: Some(id), ..info.into() },
+                        local_path,
+                        record: None,
+                        mods: Mods::default(),
+                        loudness: None,
+                    })
+                }));
             }
         }
         if let Some(res_pack_path) = &mut self.config.res_pack_path {
@@ -138,10 +159,60 @@ impl Data {
             }
         }
         self.config.init();
+        self.update_offline_rks();
         Ok(())
     }
 
     pub fn find_chart_by_path(&self, local_path: &str) -> Option<usize> {
         self.charts.iter().position(|local| local.local_path == local_path)
     }
+
+    /// Indices of all difficulties in the package of the given chart, ordered as in the package.
+    pub fn find_difficulties(&self, local_path: &str) -> Vec<usize> {
+        let package = split_local_path(local_path).0;
+        let mut result: Vec<_> = (0..self.charts.len())
+            .filter(|index| split_local_path(&self.charts[*index].local_path).0 == package)
+            .collect();
+        result.sort_by_key(|index| split_local_path(&self.charts[*index].local_path).1);
+        result
+    }
+
+    /// Local charts with a record, paired with their RKS records.
+    pub fn rks_records(&self) -> (Vec<usize>, Vec<RksRecord>) {
+        self.charts.iter().enumerate().filter_map(|(index, chart)| Some((index, chart.rks_record()?))).unzip()
+    }
+
+    /// Offline RKS breakdown; indices refer to [`Data::charts`].
+    pub fn rks_breakdown(&self) -> RksBreakdown {
+        let (indices, records) = self.rks_records();
+        let mut breakdown = RksBreakdown::new(&records);
+        breakdown.phi = breakdown.phi.map(|(index, rating)| (indices[index], rating));
+        breakdown.best.iter_mut().for_each(|it| it.0 = indices[it.0]);
+        breakdown
+    }
+
+    pub fn rks_context(&self, local_path: &str, difficulty: f32) -> RksContext {
+        let current = self.find_chart_by_path(local_path);
+        RksContext {
+            others: (self.charts.iter().enumerate())
+                .filter(|(index, _)| Some(*index) != current)
+                .filter_map(|(_, chart)| chart.rks_record())
+                .collect(),
+            current: current.and_then(|index| self.charts[index].rks_record()),
+            difficulty,
+        }
+    }
+
+    /// Offline RKS, computed again only after [`Data::update_offline_rks`].
+    pub fn offline_rks(&self) -> f32 {
+        *self.rks_cache.lock().unwrap().get_or_insert_with(|| self.rks_breakdown().rks)
+    }
+
+    /// Must be called whenever records or charts change.
+    pub fn update_offline_rks(&mut self) {
+        *self.rks_cache.get_mut().unwrap() = None;
+        if self.config.offline_mode {
+            self.config.player_rks = self.offline_rks();
+        }
+    }
 }
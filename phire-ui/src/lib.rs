@@ -25,13 +25,14 @@ use phire::{
     core::init_assets,
     l10n::{set_prefered_locale, GLOBAL, LANGS},
     log,
-    scene::{show_error, show_message},
+    config::Mods,
+    scene::{show_error, show_message, GameMode, RemoteScene, Scene},
     time::TimeManager,
     ui::{FontArc, TextPainter},
     gyro::{GYRO, GYROSCOPE_DATA},
     Main,
 };
-use scene::MainScene;
+use scene::{LaunchScene, MainScene};
 use std::sync::{mpsc, Mutex};
 use std::time::Instant;
 use nalgebra::{UnitQuaternion, Vector3};
@@ -43,6 +44,7 @@ static MESSAGES_TX_FOUCUS_PAUSE: Mutex<Option<mpsc::Sender<bool>>> = Mutex::new(
 static AA_TX: Mutex<Option<mpsc::Sender<i32>>> = Mutex::new(None);
 static DATA_PATH: Mutex<Option<String>> = Mutex::new(None);
 static CACHE_DIR: Mutex<Option<String>> = Mutex::new(None);
+static LAUNCH_OPTIONS: Mutex<Option<LaunchOptions>> = Mutex::new(None);
 pub static mut DATA: Option<Data> = None;
 
 #[cfg(feature = "closed")]
@@ -155,6 +157,11 @@ async fn the_main() -> Result<()> {
         *CACHE_DIR.lock().unwrap() = Some("Caches".to_owned());
     }
 
+    let options = LAUNCH_OPTIONS.lock().unwrap().take().unwrap_or_default();
+    if let Some(dir) = options.data_dir {
+        *DATA_PATH.lock().unwrap() = Some(dir);
+    }
+
     let dir = dir::root()?;
     let mut data: Data = std::fs::read_to_string(format!("{dir}/data.json"))
         .map_err(anyhow::Error::new)
@@ -194,7 +201,30 @@ async fn the_main() -> Result<()> {
     let font = FontArc::try_from_vec(load_file("font.ttf").await?)?;
     let mut painter = TextPainter::new(font);
 
-    let mut main = Main::new(Box::new(MainScene::new().await?), TimeManager::default(), None).await?;
+    let scene: Box<dyn Scene> = if let Some(port) = options.remote {
+        Box::new(RemoteScene::new(port, get_data().config.clone())?)
+    } else if let Some(chart) = options.chart {
+        let mut config = get_data().config.clone();
+        config.res_pack_path = options.res_pack.or_else(|| {
+            let id = get_data().respack_id;
+            let dir = dir::respacks().ok()?;
+            (id != 0).then(|| format!("{dir}/{}", get_data().respacks[id - 1]))
+        });
+        if let Some(mods) = options.mods {
+            config.mods = mods;
+        }
+        if let Some(speed) = options.speed {
+            config.speed = speed;
+        }
+        config.start_time = options.start_time;
+        if let Some(seed) = options.seed {
+            config.random_seed = seed;
+        }
+        Box::new(LaunchScene::new(chart, options.mode.unwrap_or(GameMode::Normal), config)?)
+    } else {
+        Box::new(MainScene::new().await?)
+    };
+    let mut main = Main::new(scene, TimeManager::default(), None).await?;
 
     let tm = TimeManager::default();
     let mut fps_time = -1;
@@ -291,6 +321,28 @@ fn show_and_exit(msg: &str) {
         .show();
 }
 
+/// Options given on the command line.
+#[derive(Default)]
+pub struct LaunchOptions {
+    /// Chart file or folder to play directly, skipping the menus
+    pub chart: Option<String>,
+    pub mode: Option<GameMode>,
+    pub mods: Option<Mods>,
+    pub speed: Option<f32>,
+    pub start_time: f32,
+    /// Seed of the `RANDOM` mod
+    pub seed: Option<u64>,
+    pub res_pack: Option<String>,
+    pub data_dir: Option<String>,
+    /// Starts a remote-controlled previewer listening on the given port instead
+    pub remote: Option<u16>,
+}
+
+/// Must be called before [`quad_main`].
+pub fn set_launch_options(options: LaunchOptions) {
+    *LAUNCH_OPTIONS.lock().unwrap() = Some(options);
+}
+
 #[no_mangle]
 pub extern "C" fn quad_main() {
     macroquad::Window::from_config(build_conf(), async {
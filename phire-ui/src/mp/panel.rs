@@ -3,7 +3,7 @@ use crate::{
     client::{Chart, Ptr, UserManager},
     dir, get_data,
     mp::L10N_LOCAL,
-    scene::{Downloading, SongScene, RECORD_ID},
+    scene::{read_local_info, Downloading, SongScene, RECORD_ID},
 };
 use anyhow::{anyhow, Context, Result};
 use macroquad::prelude::*;
@@ -13,7 +13,6 @@ use phire::{
     config::Mods,
     core::{Smooth, Tweenable},
     ext::{poll_future, semi_black, semi_white, LocalTask, RectExt, SafeTexture},
-    info::ChartInfo,
     scene::{request_input, return_input, show_error, show_message, take_input, GameMode, NextScene},
     task::Task,
     time::TimeManager,
@@ -22,7 +21,6 @@ use phire::{
 };
 use smallvec::SmallVec;
 use std::{
-    fs::File,
     path::Path,
     sync::{atomic::Ordering, Arc},
 };
@@ -512,7 +510,7 @@ impl MPPanel {
                         let path = format!("download/{}", entity.id);
                         let info_path = format!("{}/{path}/info.yml", dir::charts()?);
                         let should_download = if Path::new(&info_path).exists() {
-                            let local_info: ChartInfo = serde_yaml::from_reader(File::open(info_path)?)?;
+                            let local_info = read_local_info(&path)?;
                             local_info
                                 .updated
                                 .map_or(entity.updated != entity.created, |local_updated| local_updated != entity.updated)
@@ -25,7 +25,7 @@ use crate::{
     data::BriefChartInfo,
     dir, get_data,
     images::Images,
-    scene::{fs_from_path, ChartOrder},
+    scene::{fs_from_path, load_local_info, split_local_path, ChartOrder},
 };
 use anyhow::Result;
 use image::DynamicImage;
@@ -33,7 +33,6 @@ use macroquad::prelude::*;
 use phire::{
     core::Resource,
     ext::{semi_black, semi_white, SafeTexture, ScaleType, BLACK_TEXTURE},
-    fs,
     scene::{NextScene, Scene},
     task::Task,
     time::TimeManager,
@@ -52,7 +51,7 @@ pub fn illustration_task(notify: Arc<Notify>, path: String) -> Task<Result<(Dyna
     Task::new(async move {
         notify.notified().await;
         let mut fs = fs_from_path(&path)?;
-        let info = fs::load_info(fs.deref_mut()).await?;
+        let info = load_local_info(fs.deref_mut(), &path).await?;
         let image = image::load_from_memory(&fs.load_file(&info.illustration).await?)?;
         let thumbnail = Images::local_or_else(thumbnail_path(&path)?, async { Ok(Images::thumbnail(&image)) }).await?;
         Ok((thumbnail, Some(image)))
@@ -64,6 +63,8 @@ pub fn load_local(order: &(ChartOrder, bool)) -> Vec<ChartItem> {
     let mut res: Vec<_> = get_data()
         .charts
         .iter()
+        // difficulties of the same package are switched in the song scene
+        .filter(|it| split_local_path(&it.local_path).1 == 0)
         .map(|it| ChartItem {
             info: it.info.clone(),
             local_path: Some(it.local_path.clone()),
@@ -9,7 +9,7 @@ use crate::{
     icons::Icons,
     login::Login,
     save_data,
-    scene::ProfileScene,
+    scene::{read_local_info, split_local_path, ProfileScene},
     sync_data,
 };
 use ::rand::{random, thread_rng, Rng};
@@ -18,7 +18,6 @@ use image::DynamicImage;
 use macroquad::prelude::*;
 use phire::{
     ext::{semi_black, semi_white, RectExt, SafeTexture, ScaleType},
-    info::ChartInfo,
     scene::{show_error, NextScene},
     task::Task,
     ui::{button_hit_large, rounded_rect, DRectButton, Ui},
@@ -224,10 +223,10 @@ impl Page for HomePage {
                     index += 1;
                 }
                 let path = charts[index].local_path.clone();
-                let dir = phire::dir::Dir::new(format!("{}/{}", dir::charts()?, path))?;
-                self.board_last = Some(path);
+                let dir = phire::dir::Dir::new(format!("{}/{}", dir::charts()?, split_local_path(&path).0))?;
+                self.board_last = Some(path.clone());
                 self.board_task = Some(Task::new(async move {
-                    let info: ChartInfo = serde_yaml::from_reader(dir.open("info.yml")?)?;
+                    let info = read_local_info(&path)?;
                     let bytes = dir.read(info.illustration)?;
                     Ok(Some(image::load_from_memory(&bytes)?))
                 }));
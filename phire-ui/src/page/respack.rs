@@ -236,24 +236,27 @@ impl Page for ResPackPage {
             if let Some(res_pack) = &item.loaded {
                 let width = 0.16;
                 let mut r = Rect::new(cr.x + 0.07, cr.y + 0.1, width, 0.);
-                let mut draw = |mut r: Rect, tex: Texture2D, mh: Texture2D| {
+                let animation = res_pack.info.note_animation;
+                let mut draw = |mut r: Rect, tex: Texture2D, mh: Texture2D, frames: u32| {
+                    let source = animation.frame(frames, t);
+                    let (w, mh_w) = (tex.width() * source.w, mh.width() * source.w);
                     let y = r.y;
-                    r.h = tex.height() / tex.width() * r.w;
+                    r.h = tex.height() / w * r.w;
                     r.y = y - r.h / 2.;
-                    ui.fill_rect(r, (tex, r, ScaleType::Fit, c));
+                    ui.fill_rect(r, (tex, source, r, c));
                     r.x += r.w * 1.8;
-                    r.w *= mh.width() / tex.width();
+                    r.w *= mh_w / w;
                     r.x -= r.w / 2.;
-                    r.h = mh.height() / mh.width() * r.w;
+                    r.h = mh.height() / mh_w * r.w;
                     r.y = y - r.h / 2.;
-                    ui.fill_rect(r, (mh, r, ScaleType::Fit, c));
+                    ui.fill_rect(r, (mh, source, r, c));
                 };
                 let sp = (cr.h - 0.4) / 2.;
-                draw(r, *res_pack.note_style.click, *res_pack.note_style_mh.click);
+                draw(r, *res_pack.note_style.click, *res_pack.note_style_mh.click, animation.click);
                 r.y += sp;
-                draw(r, *res_pack.note_style.drag, *res_pack.note_style_mh.drag);
+                draw(r, *res_pack.note_style.drag, *res_pack.note_style_mh.drag, animation.drag);
                 r.y += sp;
-                draw(r, *res_pack.note_style.flick, *res_pack.note_style_mh.flick);
+                draw(r, *res_pack.note_style.flick, *res_pack.note_style_mh.flick, animation.flick);
                 r.y += sp;
                 let mut r = Rect::new(0.1, cr.y + 0.1, width, cr.h - 0.38);
                 let draw = |mut r: Rect, style: &NoteStyle, width: f32| {
@@ -326,10 +329,10 @@ impl Page for ResPackPage {
                 let inter = 1.5;
                 let rnd = t.div_euclid(inter);
                 let irnd = rnd as u32;
-                let tex = match irnd % 3 {
-                    0 => *res_pack.note_style.click,
-                    1 => *res_pack.note_style.drag,
-                    2 => *res_pack.note_style.flick,
+                let (tex, frames) = match irnd % 3 {
+                    0 => (*res_pack.note_style.click, animation.click),
+                    1 => (*res_pack.note_style.drag, animation.drag),
+                    2 => (*res_pack.note_style.flick, animation.flick),
                     _ => unreachable!(),
                 };
                 let st = r.y + 0.06;
@@ -343,12 +346,13 @@ impl Page for ResPackPage {
                 ui.fill_rect(Rect::new(cx - 0.2, line - 0.00375, 0.4, 0.0075),line_color);
                 if p <= 1. {
                     let y = st + (line - st) * p;
-                    let h = tex.height() / tex.width() * width;
+                    let source = animation.frame(frames, t);
+                    let h = tex.height() / (tex.width() * source.w) * width;
                     let r = Rect::new(cx - width / 2., y - h / 2., width, h);
-                    ui.fill_rect(r, (tex, r, ScaleType::Fit, c));
+                    ui.fill_rect(r, (tex, source, r, c));
                 } else if irnd != self.last_round {
                     if let Some(emitter) = &mut self.emitter {
-                        emitter.emit_at(vec2(cx, line), 0., fx_color);
+                        emitter.emit_at(vec2(cx, line), 0., fx_color, irnd % 2 != 0);
                     }
                     if let Some(sfxs) = &mut self.sfxs {
                         let _ = sfxs[(irnd % 3) as usize].play(PlaySfxParams::default());
@@ -8,7 +8,7 @@ use phire::{
     ext::{poll_future, semi_black, validate_combo, LocalTask, RectExt, SafeTexture, ScaleType},
     l10n::{LanguageIdentifier, LANG_IDENTS, LANG_NAMES},
     scene::{request_input, return_input, show_error, show_message, take_input},
-    ui::{DRectButton, Scroll, Slider, Ui},
+    ui::{DRectButton, Dialog, Scroll, Slider, Ui},
 };
 use std::{borrow::Cow, net::ToSocketAddrs, sync::atomic::Ordering};
 
@@ -276,6 +276,7 @@ struct GeneralList {
 
     lang_btn: ChooseButton,
     offline_btn: DRectButton,
+    rks_btn: DRectButton,
     mp_btn: DRectButton,
     mp_addr_btn: DRectButton,
     lowq_btn: DRectButton,
@@ -298,6 +299,7 @@ impl GeneralList {
                         .unwrap_or_default(),
                 ),
             offline_btn: DRectButton::new(),
+            rks_btn: DRectButton::new(),
             mp_btn: DRectButton::new(),
             mp_addr_btn: DRectButton::new(),
             lowq_btn: DRectButton::new(),
@@ -320,8 +322,13 @@ impl GeneralList {
         }
         if self.offline_btn.touch(touch, t) {
             config.offline_mode ^= true;
+            data.update_offline_rks();
             return Ok(Some(true));
         }
+        if self.rks_btn.touch(touch, t) {
+            show_rks_breakdown();
+            return Ok(Some(false));
+        }
         if self.mp_btn.touch(touch, t) {
             config.mp_enabled ^= true;
             return Ok(Some(true));
@@ -390,6 +397,10 @@ impl GeneralList {
             render_title(ui, c, tl!("item-offline"), Some(tl!("item-offline-sub")));
             render_switch(ui, rr, t, c, &mut self.offline_btn, config.offline_mode);
         }
+        item! {
+            render_title(ui, c, tl!("item-rks"), Some(tl!("item-rks-sub")));
+            self.rks_btn.render_text(ui, rr, t, c.a, format!("{:.2}", data.offline_rks()), 0.5, true);
+        }
         item! {
             render_title(ui, c, tl!("item-mp"), Some(tl!("item-mp-sub")));
             render_switch(ui, rr, t, c, &mut self.mp_btn, config.mp_enabled);
@@ -411,6 +422,25 @@ impl GeneralList {
     }
 }
 
+fn show_rks_breakdown() {
+    let data = get_data();
+    let breakdown = data.rks_breakdown();
+    let line = |prefix: String, index: usize, rating: f32| {
+        let chart = &data.charts[index];
+        let acc = chart.record.as_ref().map_or(0., |it| it.accuracy);
+        format!("{prefix}  {} ({})  {:.2}%  {rating:.2}", chart.info.name, chart.info.level, acc * 100.)
+    };
+    let mut lines = Vec::new();
+    if let Some((index, rating)) = breakdown.phi {
+        lines.push(line("φ".to_owned(), index, rating));
+    }
+    lines.extend(breakdown.best.iter().enumerate().map(|(rank, (index, rating))| line(format!("#{}", rank + 1), *index, *rating)));
+    if lines.is_empty() {
+        lines.push(tl!("rks-breakdown-empty").into_owned());
+    }
+    Dialog::plain(tl!("rks-breakdown", "rks" => format!("{:.2}", breakdown.rks)), lines.join("\n")).show();
+}
+
 struct AudioList {
     adjust_btn: DRectButton,
     music_slider: Slider,
@@ -418,6 +448,9 @@ struct AudioList {
     bgm_slider: Slider,
     cali_btn: DRectButton,
     audio_compatibility_btn: DRectButton,
+    nightcore_btn: DRectButton,
+    loudness_btn: DRectButton,
+    loudness_slider: Slider,
 
     cali_task: LocalTask<Result<OffsetPage>>,
     next_page: Option<NextPage>,
@@ -432,6 +465,9 @@ impl AudioList {
             bgm_slider: Slider::new(0.0..2.0, 0.05),
             cali_btn: DRectButton::new(),
             audio_compatibility_btn: DRectButton::new(),
+            nightcore_btn: DRectButton::new(),
+            loudness_btn: DRectButton::new(),
+            loudness_slider: Slider::new(-30.0..-5.0, 1.),
 
             cali_task: None,
             next_page: None,
@@ -470,6 +506,17 @@ impl AudioList {
             config.audio_compatibility ^= true;
             return Ok(Some(true));
         }
+        if self.nightcore_btn.touch(touch, t) {
+            config.nightcore ^= true;
+            return Ok(Some(true));
+        }
+        if self.loudness_btn.touch(touch, t) {
+            config.loudness_normalization ^= true;
+            return Ok(Some(true));
+        }
+        if let wt @ Some(_) = self.loudness_slider.touch(touch, t, &mut config.target_loudness) {
+            return Ok(wt);
+        }
         Ok(None)
     }
 
@@ -526,6 +573,18 @@ impl AudioList {
             render_title(ui, c, tl!("item-audio-compatibility"), None);
             render_switch(ui, rr, t, c, &mut self.audio_compatibility_btn, config.audio_compatibility);
         }
+        item! {
+            render_title(ui, c, tl!("item-nightcore"), Some(tl!("item-nightcore-sub")));
+            render_switch(ui, rr, t, c, &mut self.nightcore_btn, config.nightcore);
+        }
+        item! {
+            render_title(ui, c, tl!("item-loudness"), Some(tl!("item-loudness-sub")));
+            render_switch(ui, rr, t, c, &mut self.loudness_btn, config.loudness_normalization);
+        }
+        item! {
+            render_title(ui, c, tl!("item-target-loudness"), None);
+            self.loudness_slider.render(ui, rr, t, c, config.target_loudness, format!("{:.0} LUFS", config.target_loudness));
+        }
         (w, h)
     }
 
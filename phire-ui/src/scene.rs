@@ -9,6 +9,9 @@ pub use event::EventScene;
 mod main;
 pub use main::{MainScene, BGM_VOLUME_UPDATED, MP_PANEL};
 
+mod launch;
+pub use launch::LaunchScene;
+
 mod song;
 pub use song::{Downloading, SongScene, RECORD_ID};
 
@@ -16,11 +19,12 @@ mod profile;
 pub use profile::ProfileScene;
 
 use crate::{client::UserManager, data::LocalChart, dir, get_data, page::Fader};
-use anyhow::{bail, Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use phire::{
     config::Mods,
     ext::{semi_white, unzip_into, RectExt, SafeTexture},
     fs::{self, FileSystem},
+    info::ChartInfo,
     ui::{Dialog, RectButton, Scroll, Ui},
 };
 use std::{
@@ -41,7 +45,66 @@ thread_local! {
     pub static TEX_ICON_BACK: RefCell<Option<SafeTexture>> = RefCell::new(None);
 }
 
+/// Splits a local chart path into the path of its package and the index of the difficulty in it.
+///
+/// Difficulties other than the first one are addressed as `package#index`.
+pub fn split_local_path(path: &str) -> (&str, usize) {
+    match path.rsplit_once('#') {
+        Some((package, index)) => match index.parse() {
+            Ok(index) => (package, index),
+            Err(_) => (path, 0),
+        },
+        None => (path, 0),
+    }
+}
+
+pub fn local_path_of(package: &str, index: usize) -> String {
+    if index == 0 {
+        package.to_owned()
+    } else {
+        format!("{package}#{index}")
+    }
+}
+
+/// Loads the info of the given local chart from the file system of its package.
+pub async fn load_local_info(fs: &mut dyn FileSystem, local_path: &str) -> Result<ChartInfo> {
+    let index = split_local_path(local_path).1;
+    fs::load_infos(fs)
+        .await?
+        .into_iter()
+        .nth(index)
+        .ok_or_else(|| anyhow!("difficulty #{index} not found in {local_path}"))
+}
+
+/// Reads infos of all difficulties from the `info.yml` of the package.
+pub fn read_local_infos(package: &str) -> Result<Vec<ChartInfo>> {
+    fs::infos_from_yaml(&std::fs::read_to_string(format!("{}/{package}/info.yml", dir::charts()?))?)
+}
+
+pub fn read_local_info(local_path: &str) -> Result<ChartInfo> {
+    let (package, index) = split_local_path(local_path);
+    let mut infos = read_local_infos(package)?;
+    if index >= infos.len() {
+        bail!("difficulty #{index} not found in {package}");
+    }
+    Ok(infos.swap_remove(index))
+}
+
+/// Applies `f` to the info of the given local chart and writes it back, leaving other difficulties of the package untouched.
+pub fn update_local_info(local_path: &str, f: impl FnOnce(&mut ChartInfo)) -> Result<ChartInfo> {
+    let (package, index) = split_local_path(local_path);
+    let mut infos = read_local_infos(package)?;
+    let Some(info) = infos.get_mut(index) else {
+        bail!("difficulty #{index} not found in {package}");
+    };
+    f(info);
+    let info = info.clone();
+    std::fs::write(format!("{}/{package}/info.yml", dir::charts()?), fs::infos_to_yaml(&infos)?)?;
+    Ok(info)
+}
+
 pub fn fs_from_path(path: &str) -> Result<Box<dyn FileSystem + Send + Sync + 'static>> {
+    let (path, _) = split_local_path(path);
     if let Some(name) = path.strip_prefix(':') {
         fs::fs_from_assets(format!("charts/{name}/"))
     } else {
@@ -65,8 +128,8 @@ pub fn confirm_delete(res: Arc<AtomicBool>) {
     confirm_dialog(ttl!("del-confirm"), ttl!("del-confirm-content"), res)
 }
 
-pub async fn import_chart(path: String) -> Result<LocalChart> {
-    async fn inner(dir: &Path, id: Uuid, path: String) -> Result<LocalChart> {
+pub async fn import_chart(path: String) -> Result<Vec<LocalChart>> {
+    async fn inner(dir: &Path, id: Uuid, path: String) -> Result<Vec<LocalChart>> {
         let path = Path::new(&path);
         if !path.exists() || !path.is_file() {
             bail!("not a file");
@@ -75,15 +138,22 @@ pub async fn import_chart(path: String) -> Result<LocalChart> {
         unzip_into(BufReader::new(File::open(path)?), &dir, true)?;
         let local_path = format!("custom/{id}");
         let mut fs = fs_from_path(&local_path)?;
-        let mut info = fs::load_info(fs.as_mut()).await.with_context(|| itl!("info-fail"))?;
-        fs::fix_info(fs.as_mut(), &mut info).await.with_context(|| itl!("invalid-chart"))?;
-        dir.create("info.yml")?.write_all(serde_yaml::to_string(&info)?.as_bytes())?;
-        Ok(LocalChart {
-            info: info.into(),
-            local_path,
-            record: None,
-            mods: Mods::default(),
-        })
+        let mut infos = fs::load_infos(fs.as_mut()).await.with_context(|| itl!("info-fail"))?;
+        for info in &mut infos {
+            fs::fix_info(fs.as_mut(), info).await.with_context(|| itl!("invalid-chart"))?;
+        }
+        dir.create("info.yml")?.write_all(fs::infos_to_yaml(&infos)?.as_bytes())?;
+        Ok(infos
+            .into_iter()
+            .enumerate()
+            .map(|(index, info)| LocalChart {
+                info: info.into(),
+                local_path: local_path_of(&local_path, index),
+                record: None,
+                mods: Mods::default(),
+                loudness: None,
+            })
+            .collect())
     }
     let dir = dir::custom_charts()?;
     let dir = Path::new(&dir);
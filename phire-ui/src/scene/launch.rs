@@ -0,0 +1,76 @@
+use anyhow::Result;
+use macroquad::prelude::*;
+use phire::{
+    config::Config,
+    ext::{poll_future, LocalTask},
+    fs,
+    scene::{GameMode, LoadingScene, NextScene, Scene},
+    time::TimeManager,
+    ui::Ui,
+};
+use std::{any::Any, path::Path};
+use tracing::error;
+
+/// Plays a single chart given on the command line, and exits once it's over.
+pub struct LaunchScene {
+    load_task: LocalTask<Result<NextScene>>,
+    next_scene: Option<NextScene>,
+    started: bool,
+}
+
+impl LaunchScene {
+    pub fn new(path: String, mode: GameMode, config: Config) -> Result<Self> {
+        let mut fs = fs::fs_from_file(Path::new(&path))?;
+        Ok(Self {
+            load_task: Some(Box::pin(async move {
+                let info = fs::load_info(fs.as_mut()).await?;
+                Ok(NextScene::Overlay(Box::new(LoadingScene::new(mode, info, &config, fs, None, None, None).await?)))
+            })),
+            next_scene: None,
+            started: false,
+        })
+    }
+}
+
+impl Scene for LaunchScene {
+    fn enter(&mut self, _tm: &mut TimeManager, _target: Option<RenderTarget>) -> Result<()> {
+        if self.started {
+            self.next_scene = Some(NextScene::Exit);
+        }
+        Ok(())
+    }
+
+    fn on_result(&mut self, _tm: &mut TimeManager, result: Box<dyn Any>) -> Result<()> {
+        if let Ok(err) = result.downcast::<anyhow::Error>() {
+            error!("failed to load chart: {err:?}");
+        }
+        Ok(())
+    }
+
+    fn update(&mut self, _tm: &mut TimeManager) -> Result<()> {
+        if let Some(task) = &mut self.load_task {
+            if let Some(res) = poll_future(task.as_mut()) {
+                self.load_task = None;
+                self.started = true;
+                self.next_scene = Some(match res {
+                    Ok(scene) => scene,
+                    Err(err) => {
+                        error!("failed to load chart: {err:?}");
+                        NextScene::Exit
+                    }
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn render(&mut self, _tm: &mut TimeManager, ui: &mut Ui) -> Result<()> {
+        set_camera(&ui.camera());
+        clear_background(BLACK);
+        Ok(())
+    }
+
+    fn next_scene(&mut self, _tm: &mut TimeManager) -> NextScene {
+        self.next_scene.take().unwrap_or_default()
+    }
+}
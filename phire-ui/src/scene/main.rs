@@ -12,7 +12,7 @@ use anyhow::{anyhow, Context, Result};
 use macroquad::prelude::*;
 use phire::{
     core::ResPackInfo,
-    ext::{blur_image, unzip_into, RectExt, SafeTexture, ScaleType},
+    ext::{unzip_into, BlurredTexture, RectExt, SafeTexture, ScaleType},
     scene::{return_file, show_error, show_message, take_file, NextScene, Scene},
     task::Task,
     time::TimeManager,
@@ -51,7 +51,7 @@ pub struct MainScene {
 
     pages: Vec<Box<dyn Page>>,
 
-    import_task: Option<Task<Result<LocalChart>>>,
+    import_task: Option<Task<Result<Vec<LocalChart>>>>,
 
     mp_btn: RectButton,
     mp_icon: SafeTexture,
@@ -101,8 +101,7 @@ impl MainScene {
 
         let background: SafeTexture = load_texture("background.png").await?.into();
 
-        let image = image::load_from_memory(&load_file("background.png").await?).context("Failed to decode image")?;
-        let background_blur = blur_image(image, 80.)?;
+        let background_blur = BlurredTexture::new(background.clone()).into_texture(80., 0.);
 
         let icon_back: SafeTexture = load_texture("back.png").await?.into();
 
@@ -317,9 +316,9 @@ impl Scene for MainScene {
                     Err(err) => {
                         show_error(err.context(itl!("import-failed")));
                     }
-                    Ok(chart) => {
+                    Ok(charts) => {
                         show_message(itl!("import-success")).ok();
-                        get_data_mut().charts.push(chart);
+                        get_data_mut().charts.extend(charts);
                         save_data()?;
                         self.state.reload_local_charts();
                         NEED_UPDATE.store(true, Ordering::Relaxed);
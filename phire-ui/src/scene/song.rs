@@ -1,6 +1,9 @@
 phire::tl_file!("song");
 
-use super::{confirm_delete, confirm_dialog, fs_from_path, render_ldb, LdbDisplayItem, ProfileScene};
+use super::{
+    confirm_delete, confirm_dialog, fs_from_path, load_local_info, read_local_info, render_ldb, split_local_path, update_local_info, LdbDisplayItem,
+    ProfileScene,
+};
 use crate::{
     charts_view::NEED_UPDATE,
     client::{basic_client_builder, recv_raw, Chart, Client, Permissions, Ptr, Record, UserManager, CLIENT_TOKEN},
@@ -20,15 +23,16 @@ use futures_util::StreamExt;
 use macroquad::prelude::*;
 use phira_mp_common::{ClientCommand, CompactPos, JudgeEvent, TouchFrame};
 use phire::{
+    audio::integrated_loudness,
     config::Mods,
     core::Tweenable,
-    ext::{poll_future, semi_black, semi_white, unzip_into, JoinToString, LocalTask, RectExt, SafeTexture, ScaleType},
+    ext::{poll_future, semi_black, semi_white, unzip_into, BlurredTexture, JoinToString, LocalTask, RectExt, SafeTexture, ScaleType},
     fs,
     info::ChartInfo,
     judge::{icon_index, Judge},
     scene::{
-        request_input, return_input, show_error, show_message, take_input, BasicPlayer, GameMode, LoadingScene, LocalSceneTask, NextScene,
-        RecordUpdateState, Scene, SimpleRecord, UpdateFn,
+        request_file, request_input, return_file, return_input, show_error, show_message, take_file, take_input, BasicPlayer, CourseChart, CourseInfo,
+        CourseResult, CourseScene, GameMode, LoadingScene, LocalSceneTask, NextScene, RecordUpdateState, Scene, SimpleRecord, StageLoader, UpdateFn,
     },
     task::Task,
     time::TimeManager,
@@ -59,16 +63,19 @@ use zip::{write::FileOptions, CompressionMethod, ZipWriter};
 
 const FADE_IN_TIME: f32 = 0.3;
 const EDIT_TRANSIT: f32 = 0.32;
+/// Blur of the background while the side panel is open
+const SIDE_BLUR: f32 = 30.;
 
 static CONFIRM_UPLOAD: AtomicBool = AtomicBool::new(false);
 pub static RECORD_ID: AtomicI32 = AtomicI32::new(-1);
 
-fn create_music(clip: AudioClip) -> Result<Music> {
+fn create_music(clip: AudioClip, loudness: Option<f32>) -> Result<Music> {
+    let gain = get_data().config.music_gain(loudness);
     let mut music = UI_AUDIO.with(|it| {
         it.borrow_mut().create_music(
             clip,
             MusicParams {
-                amplifier: 0.7,
+                amplifier: 0.7 * gain,
                 loop_mix_time: 0.,
                 ..Default::default()
             },
@@ -194,6 +201,7 @@ struct LdbItem {
 
 pub struct SongScene {
     illu: Illustration,
+    background: BlurredTexture,
 
     first_in: bool,
 
@@ -205,12 +213,14 @@ pub struct SongScene {
     next_scene: Option<NextScene>,
 
     preview: Option<Music>,
-    preview_task: Option<Task<Result<AudioClip>>>,
+    preview_task: Option<Task<Result<(AudioClip, Option<f32>)>>>,
 
     load_task: Option<Task<Result<Option<Arc<Chart>>>>>,
     entity: Option<Chart>,
     info: BriefChartInfo,
     local_path: Option<String>,
+    /// Other difficulties in the same package, as `(button, local path, level)`
+    diff_btns: Vec<(DRectButton, String, String)>,
 
     downloading: Option<Downloading>,
     loading_last: f32,
@@ -238,7 +248,7 @@ pub struct SongScene {
     side_content: SideContent,
     side_enter_time: f32,
 
-    save_task: Option<Task<Result<(ChartInfo, AudioClip)>>>,
+    save_task: Option<Task<Result<(ChartInfo, AudioClip, Option<f32>)>>>,
     upload_task: Option<Task<Result<BriefChartInfo>>>,
 
     ldb: Option<(Option<u32>, Vec<LdbItem>)>,
@@ -286,7 +296,7 @@ impl SongScene {
         mods: Mods,
     ) -> Self {
         if let Some(path) = &local_path {
-            if let Some(id) = path.strip_prefix("download/") {
+            if let Some(id) = split_local_path(path).0.strip_prefix("download/") {
                 chart.info.id = Some(id.parse().unwrap());
             }
         }
@@ -321,8 +331,24 @@ impl SongScene {
         };
         let id = chart.info.id;
         let offline_mode = get_data().config.offline_mode;
+        let loudness = local_path
+            .as_ref()
+            .and_then(|path| get_data().find_chart_by_path(path))
+            .and_then(|index| get_data().charts[index].loudness);
         let icon_star = icons.star.clone();
+        let diff_btns = local_path
+            .as_ref()
+            .map(|path| get_data().find_difficulties(path))
+            .filter(|it| it.len() > 1)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|index| {
+                let chart = &get_data().charts[index];
+                (DRectButton::new(), chart.local_path.clone(), chart.info.level.clone())
+            })
+            .collect();
         Self {
+            background: BlurredTexture::new(illu.texture.1.clone()),
             illu,
 
             first_in: true,
@@ -340,14 +366,19 @@ impl SongScene {
                 async move {
                     if let Some(path) = local_path {
                         let mut fs = fs_from_path(&path)?;
-                        let info = fs::load_info(fs.as_mut()).await?;
-                        with_effects(
-                            AudioClip::decode(fs.load_file(&info.music).await?)?,
+                        let info = load_local_info(fs.as_mut(), &path).await?;
+                        let (frames, sample_rate) = AudioClip::decode(fs.load_file(&info.music).await?)?;
+                        let loudness = loudness.or_else(|| integrated_loudness(&frames, sample_rate));
+                        let preview = with_effects(
+                            (frames, sample_rate),
                             Some((info.preview_start, info.preview_end.unwrap_or(info.preview_start + 15.))),
-                        )
+                        )?;
+                        Ok((preview, loudness))
                     } else {
                         let chart = Ptr::<Chart>::new(id.unwrap()).fetch().await?;
-                        with_effects(AudioClip::decode(chart.preview.fetch().await?.to_vec())?, None)
+                        let (frames, sample_rate) = AudioClip::decode(chart.preview.fetch().await?.to_vec())?;
+                        // the preview clip alone would measure differently from the whole song, so it's left as is until downloaded
+                        Ok((with_effects((frames, sample_rate), None)?, None))
                     }
                 }
             })),
@@ -360,6 +391,7 @@ impl SongScene {
             entity: None,
             info: chart.info,
             local_path,
+            diff_btns,
 
             downloading: None,
             loading_last: 0.,
@@ -509,13 +541,15 @@ impl SongScene {
                     if let Some(prog) = prog_wk.upgrade() {
                         *prog.lock().unwrap() = None;
                     }
-                    let mut info: ChartInfo = serde_yaml::from_reader(dir.open("info.yml")?)?;
-                    info.id = Some(entity.id);
-                    info.created = Some(entity.created);
-                    info.updated = Some(entity.updated);
-                    info.chart_updated = Some(entity.chart_updated);
-                    info.uploader = Some(entity.uploader.id);
-                    serde_yaml::to_writer(dir.create("info.yml")?, &info)?;
+                    let mut infos = fs::infos_from_yaml(&std::io::read_to_string(dir.open("info.yml")?)?)?;
+                    for info in &mut infos {
+                        info.id = Some(entity.id);
+                        info.created = Some(entity.created);
+                        info.updated = Some(entity.updated);
+                        info.chart_updated = Some(entity.chart_updated);
+                        info.uploader = Some(entity.uploader.id);
+                    }
+                    dir.create("info.yml")?.write_all(fs::infos_to_yaml(&infos)?.as_bytes())?;
 
                     if prog_wk.strong_count() == 0 {
                         // cancelled
@@ -540,6 +574,7 @@ impl SongScene {
                         local_path,
                         record: None,
                         mods: Mods::default(),
+                        loudness: None,
                     })
                 }
             }),
@@ -576,16 +611,29 @@ impl SongScene {
         };
         if let Some(rec) = &mut chart.record {
             if rec.update(&new_rec) {
+                get_data_mut().update_offline_rks();
                 save_data()?;
             }
         } else {
             chart.record = Some(new_rec);
+            get_data_mut().update_offline_rks();
             save_data()?;
         }
         self.record = chart.record.clone();
         Ok(())
     }
 
+    fn update_loudness(&mut self, loudness: Option<f32>) -> Result<()> {
+        let Some(path) = &self.local_path else { return Ok(()) };
+        let Some(index) = get_data().find_chart_by_path(path) else { return Ok(()) };
+        let chart = &mut get_data_mut().charts[index];
+        if chart.loudness != loudness {
+            chart.loudness = loudness;
+            save_data()?;
+        }
+        Ok(())
+    }
+
     fn update_menu(&mut self) {
         self.menu_options.clear();
         if self.local_path.is_some() {
@@ -594,10 +642,15 @@ impl SongScene {
         if self.info.id.is_some() {
             self.menu_options.push("rate");
         }
-        if self.local_path.is_some() {
+        if let Some(local_path) = &self.local_path {
             self.menu_options.push("exercise");
             self.menu_options.push("offset");
+            self.menu_options.push(if get_data().course.charts.contains(local_path) { "course-remove" } else { "course-add" });
         }
+        if !get_data().course.charts.is_empty() {
+            self.menu_options.push("course-start");
+        }
+        self.menu_options.push("course-import");
         let perms = get_data().me.as_ref().map(|it| it.perms()).unwrap_or_default();
         let is_uploader = get_data()
             .me
@@ -755,9 +808,20 @@ impl SongScene {
             };
             update_fn
         });
+        let local_path = local_path.to_owned();
         Ok(Some(Box::pin(async move {
-            let mut info = fs::load_info(fs.as_mut()).await?;
+            let mut info = load_local_info(fs.as_mut(), &local_path).await?;
             info.id = id;
+            info.loudness = get_data().find_chart_by_path(&local_path).and_then(|index| get_data().charts[index].loudness);
+            if info.loudness.is_none() && get_data().config.loudness_normalization {
+                // measured once and cached, rather than by `Resource::new` on every play
+                let (frames, sample_rate) = AudioClip::decode(fs.load_file(&info.music).await?)?;
+                info.loudness = integrated_loudness(&frames, sample_rate);
+                if let Some(index) = get_data().find_chart_by_path(&local_path) {
+                    get_data_mut().charts[index].loudness = info.loudness;
+                    save_data()?;
+                }
+            }
             let mut config = get_data().config.clone();
             config.player_name = get_data()
                 .me
@@ -774,6 +838,7 @@ impl SongScene {
             };
             let chart_updated = info.chart_updated;
             config.mods = mods;
+            config.rks_context = Some(get_data().rks_context(&local_path, info.difficulty));
             LoadingScene::new(
                 mode,
                 info,
@@ -829,6 +894,43 @@ impl SongScene {
         })))
     }
 
+    /// Loads a course file (see [`CourseInfo::from_yaml`]), whose charts must all be in the library.
+    fn import_course(file: &str) -> Result<CourseInfo> {
+        let course = CourseInfo::from_yaml(&std::fs::read_to_string(file)?)?;
+        if let Some(path) = course.charts.iter().find(|it| get_data().find_chart_by_path(it).is_none()) {
+            bail!("chart not found: {path}");
+        }
+        Ok(course)
+    }
+
+    /// Plays the charts of `course` back to back, see [`CourseScene`].
+    #[must_use]
+    pub fn launch_course(course: CourseInfo, mods: Mods, illustration: SafeTexture) -> Result<LocalSceneTask> {
+        let charts = course
+            .charts
+            .iter()
+            .map(|path| {
+                let index = get_data().find_chart_by_path(path).ok_or_else(|| anyhow!("chart not found: {path}"))?;
+                let info = &get_data().charts[index].info;
+                Ok(CourseChart {
+                    name: info.name.clone(),
+                    level: info.level.clone(),
+                    difficulty: info.difficulty,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let loader: StageLoader = Box::new(move |index| {
+            let path = &course.charts[index];
+            let id = get_data().find_chart_by_path(path).and_then(|index| get_data().charts[index].info.id);
+            Self::global_launch(id, path, mods, GameMode::Course, None)
+        });
+        let config = get_data().config.clone();
+        let name = course.name.clone();
+        Ok(Some(Box::pin(async move {
+            Ok(NextScene::Overlay(Box::new(CourseScene::new(name, charts, &config, illustration, loader).await?)))
+        })))
+    }
+
     fn is_owner(&self) -> bool {
         self.info.id.is_none()
             || (self.info.created.is_some() && self.info.uploader.as_ref().map(|it| it.id) == get_data().me.as_ref().map(|it| it.id))
@@ -1083,6 +1185,13 @@ impl SongScene {
             item(tl!("mods-flip-x"), Some(tl!("mods-flip-x-sub")), Mods::FLIP_X);
             item(tl!("mods-fade-out"), Some(tl!("mods-fade-out-sub")), Mods::FADE_OUT);
             item(tl!("mods-full-screen-judge"), None, Mods::FULL_SCREEN_JUDGE);
+            item(tl!("mods-hidden"), Some(tl!("mods-hidden-sub")), Mods::HIDDEN);
+            item(tl!("mods-sudden"), Some(tl!("mods-sudden-sub")), Mods::SUDDEN);
+            item(tl!("mods-random"), Some(tl!("mods-random-sub")), Mods::RANDOM);
+            item(tl!("mods-no-line"), Some(tl!("mods-no-line-sub")), Mods::NO_LINE);
+            item(tl!("mods-sudden-death"), Some(tl!("mods-sudden-death-sub")), Mods::SUDDEN_DEATH);
+            item(tl!("mods-perfect-only"), Some(tl!("mods-perfect-only-sub")), Mods::PERFECT_ONLY);
+            item(tl!("mods-life-gauge"), Some(tl!("mods-life-gauge-sub")), Mods::LIFE_GAUGE);
             (width, h)
         });
     }
@@ -1094,8 +1203,8 @@ impl SongScene {
         let edit = edit.clone();
         let is_owner = self.is_owner();
         self.save_task = Some(Task::new(async move {
-            let dir = phire::dir::Dir::new(format!("{}/{path}", dir::charts()?))?;
-            let patches = edit.to_patches().await.with_context(|| tl!("edit-load-file-failed"))?;
+            let dir = phire::dir::Dir::new(format!("{}/{}", dir::charts()?, split_local_path(&path).0))?;
+            let mut patches = edit.to_patches().await.with_context(|| tl!("edit-load-file-failed"))?;
             if !is_owner && patches.contains_key(&info.chart) {
                 bail!(tl!("edit-downloaded"));
             }
@@ -1105,18 +1214,50 @@ impl SongScene {
                 dir.read(&info.music)?
             };
             let (frames, sample_rate) = AudioClip::decode(bytes)?;
+            let loudness = integrated_loudness(&frames, sample_rate);
             let length = frames.len() as f32 / sample_rate as f32;
             if info.preview_end.unwrap_or(info.preview_start + 1.) > length {
                 tl!(bail "edit-preview-invalid");
             }
             let preview = with_effects((frames, sample_rate), Some((info.preview_start, info.preview_end.unwrap_or(info.preview_start + 15.))))?;
+            // other difficulties in the package are kept
+            patches.remove("info.yml");
             for (name, bytes) in patches.into_iter() {
                 dir.create(name)?.write_all(&bytes)?;
             }
-            Ok((info, preview))
+            update_local_info(&path, |it| *it = info.clone())?;
+            Ok((info, preview, loudness))
         }));
     }
 
+    /// Replaces this scene with the one of another difficulty in the same package.
+    fn switch_difficulty(&mut self, local_path: String) {
+        let Some(chart) = get_data().find_chart_by_path(&local_path).map(|index| &get_data().charts[index]) else {
+            return;
+        };
+        let item = ChartItem {
+            info: chart.info.clone(),
+            local_path: Some(local_path.clone()),
+            illu: self.illu.clone(),
+        };
+        let scene = SongScene::new(item, Some(self.illu.clone()), Some(local_path), Arc::clone(&self.icons), self.rank_icons.clone(), chart.mods);
+        self.next_scene = Some(NextScene::Replace(Box::new(scene)));
+    }
+
+    // how far the side panel has slid in, from 0 to 1
+    fn side_progress(&self, rt: f32) -> f32 {
+        if !self.side_enter_time.is_finite() {
+            return 0.;
+        }
+        let p = ((rt - self.side_enter_time.abs()) / EDIT_TRANSIT).min(1.);
+        let p = 1. - (1. - p).powi(3);
+        if self.side_enter_time < 0. {
+            1. - p
+        } else {
+            p
+        }
+    }
+
     fn update_chart_info(&self) -> Result<()> {
         Self::global_update_chart_info(self.local_path.as_ref().unwrap(), self.info.clone())
     }
@@ -1131,6 +1272,18 @@ impl SongScene {
 
 impl Scene for SongScene {
     fn on_result(&mut self, tm: &mut TimeManager, res: Box<dyn Any>) -> Result<()> {
+        let res = match res.downcast::<CourseResult>() {
+            Ok(result) => {
+                if result.cleared {
+                    let config = &mut get_data_mut().config;
+                    config.challenge_rank = result.rank;
+                    config.challenge_color = result.color;
+                    save_data()?;
+                }
+                return Ok(());
+            }
+            Err(res) => res,
+        };
         let res = match res.downcast::<SimpleRecord>() {
             Err(res) => res,
             Ok(rec) => {
@@ -1152,10 +1305,7 @@ impl Scene for SongScene {
         let _res = match res.downcast::<Option<f32>>() {
             Ok(offset) => {
                 if let Some(offset) = *offset {
-                    let dir = phire::dir::Dir::new(format!("{}/{}", dir::charts()?, self.local_path.as_ref().unwrap()))?;
-                    let mut info: ChartInfo = serde_yaml::from_reader(&dir.open("info.yml")?)?;
-                    info.offset = offset;
-                    dir.create("info.yml")?.write_all(serde_yaml::to_string(&info)?.as_bytes())?;
+                    update_local_info(self.local_path.as_ref().unwrap(), |info| info.offset = offset)?;
                     let path = thumbnail_path(self.local_path.as_ref().unwrap())?;
                     if path.exists() {
                         std::fs::remove_file(path)?;
@@ -1302,6 +1452,13 @@ impl Scene for SongScene {
             self.next_scene = Some(NextScene::PopWithResult(Box::new(false)));
             return Ok(true);
         }
+        if let Some((_, path, _)) = self.diff_btns.iter_mut().find(|(btn, ..)| btn.touch(touch, t)) {
+            if Some(&*path) != self.local_path.as_ref() {
+                let path = path.clone();
+                self.switch_difficulty(path);
+            }
+            return Ok(true);
+        }
         if self.play_btn.touch(touch, t) {
             if self.local_path.is_some() {
                 self.launch(GameMode::Normal)?;
@@ -1318,7 +1475,7 @@ impl Scene for SongScene {
         if let Some(path) = &self.local_path {
             if self.edit_btn.touch(touch) {
                 button_hit();
-                let mut info: ChartInfo = serde_yaml::from_str(&std::fs::read_to_string(format!("{}/{path}/info.yml", dir::charts()?))?)?;
+                let mut info = read_local_info(path)?;
                 info.id = self.info.id;
                 self.info_edit = Some(ChartInfoEdit::new(info));
                 self.side_content = SideContent::Edit;
@@ -1353,6 +1510,21 @@ impl Scene for SongScene {
     fn update(&mut self, tm: &mut TimeManager) -> Result<()> {
         let t = tm.now() as f32;
         self.menu.update(t);
+        if let Some((id, file)) = take_file() {
+            if id == "course" {
+                match Self::import_course(&file) {
+                    Ok(course) => {
+                        show_message(tl!("course-imported", "name" => course.name.clone(), "count" => course.charts.len())).ok();
+                        get_data_mut().course = course;
+                        save_data()?;
+                        self.update_menu();
+                    }
+                    Err(err) => show_error(err.context(tl!("course-import-failed"))),
+                }
+            } else {
+                return_file(id, file);
+            }
+        }
         self.illu.settle(t);
         let rt = tm.real_time() as f32;
         self.tags.update(rt);
@@ -1425,14 +1597,13 @@ impl Scene for SongScene {
                                 );
                             }
                         } else if let Some(local) = &self.local_path {
-                            let conf = format!("{}/{}/info.yml", dir::charts()?, local);
-                            let mut info: ChartInfo = serde_yaml::from_reader(File::open(&conf)?)?;
-                            info.id = None;
-                            info.uploader = None;
-                            info.created = None;
-                            info.updated = None;
-                            info.chart_updated = None;
-                            serde_yaml::to_writer(File::create(conf)?, &info)?;
+                            let info = update_local_info(local, |info| {
+                                info.id = None;
+                                info.uploader = None;
+                                info.created = None;
+                                info.updated = None;
+                                info.chart_updated = None;
+                            })?;
                             self.info = info.into();
                             self.update_chart_info()?;
                         }
@@ -1448,8 +1619,9 @@ impl Scene for SongScene {
                     Err(err) => {
                         show_error(err.context(tl!("load-preview-failed")));
                     }
-                    Ok(clip) => {
-                        self.preview = Some(create_music(clip)?);
+                    Ok((clip, loudness)) => {
+                        self.preview = Some(create_music(clip, loudness)?);
+                        self.update_loudness(loudness)?;
                     }
                 }
                 self.preview_task = None;
@@ -1511,6 +1683,24 @@ impl Scene for SongScene {
                 "offset" => {
                     self.launch(GameMode::TweakOffset)?;
                 }
+                "course-add" | "course-remove" => {
+                    let local_path = self.local_path.clone().unwrap();
+                    let charts = &mut get_data_mut().course.charts;
+                    if option == "course-add" {
+                        charts.push(local_path);
+                    } else {
+                        charts.retain(|it| *it != local_path);
+                    }
+                    save_data()?;
+                    show_message(tl!(if option == "course-add" { "course-added" } else { "course-removed" }, "count" => charts.len())).ok();
+                    self.update_menu();
+                }
+                "course-start" => {
+                    self.scene_task = Self::launch_course(get_data().course.clone(), self.mods, self.illu.texture.1.clone())?;
+                }
+                "course-import" => {
+                    request_file("course");
+                }
                 "review-approve" => {
                     let id = self.info.id.unwrap();
                     self.review_task = Some(Task::new(async move {
@@ -1600,11 +1790,12 @@ impl Scene for SongScene {
                     Err(err) => {
                         show_error(err.context(tl!("edit-save-failed")));
                     }
-                    Ok((info, preview)) => {
+                    Ok((info, preview, loudness)) => {
                         if let Some(preview) = &mut self.preview {
                             preview.pause()?;
                         }
-                        self.preview = Some(create_music(preview)?);
+                        self.preview = Some(create_music(preview, loudness)?);
+                        self.update_loudness(loudness)?;
                         self.info = info.into();
                         self.update_chart_info()?;
                         show_message(tl!("edit-saved")).duration(1.).ok();
@@ -1651,8 +1842,9 @@ impl Scene for SongScene {
             let path = self.local_path.clone().unwrap();
             let info = self.info.clone();
             self.upload_task = Some(Task::new(async move {
-                let root = format!("{}/{path}", dir::charts()?);
+                let root = format!("{}/{}", dir::charts()?, split_local_path(&path).0);
                 let root = Path::new(&root);
+                let info_yml = serde_yaml::to_string(&read_local_info(&path)?)?;
                 let chart_bytes = {
                     let mut bytes = Vec::new();
                     let mut zip = ZipWriter::new(Cursor::new(&mut bytes));
@@ -1664,7 +1856,11 @@ impl Scene for SongScene {
                         let entry = entry?;
                         let path = entry.path();
                         let name = path.strip_prefix(root)?;
-                        if path.is_file() {
+                        if name == Path::new("info.yml") {
+                            // a chart is uploaded on its own, even when its package lists several difficulties
+                            zip.start_file_from_path(name, options)?;
+                            zip.write_all(info_yml.as_bytes())?;
+                        } else if path.is_file() {
                             zip.start_file_from_path(name, options)?;
                             let mut f = File::open(path)?;
                             std::io::copy(&mut f, &mut zip)?;
@@ -1693,11 +1889,10 @@ impl Scene for SongScene {
                     .await?
                     .json()
                     .await?;
-                    let conf = root.join("info.yml");
-                    let mut info: ChartInfo = serde_yaml::from_reader(File::open(&conf)?)?;
-                    info.updated = Some(resp.updated);
-                    info.chart_updated = Some(resp.chart_updated);
-                    serde_yaml::to_writer(File::create(conf)?, &info)?;
+                    let info = update_local_info(&path, |info| {
+                        info.updated = Some(resp.updated);
+                        info.chart_updated = Some(resp.chart_updated);
+                    })?;
                     Ok(info.into())
                 } else {
                     #[derive(Deserialize)]
@@ -1714,14 +1909,13 @@ impl Scene for SongScene {
                     .await?
                     .json()
                     .await?;
-                    let conf = root.join("info.yml");
-                    let mut info: ChartInfo = serde_yaml::from_reader(File::open(&conf)?)?;
-                    info.id = Some(resp.id);
-                    info.created = Some(resp.created);
-                    info.updated = Some(resp.created);
-                    info.chart_updated = Some(resp.created);
-                    info.uploader = Some(get_data().me.as_ref().unwrap().id);
-                    serde_yaml::to_writer(File::create(conf)?, &info)?;
+                    let info = update_local_info(&path, |info| {
+                        info.id = Some(resp.id);
+                        info.created = Some(resp.created);
+                        info.updated = Some(resp.created);
+                        info.chart_updated = Some(resp.created);
+                        info.uploader = Some(get_data().me.as_ref().unwrap().id);
+                    })?;
                     Ok(info.into())
                 }
             }));
@@ -1882,8 +2076,11 @@ impl Scene for SongScene {
     fn render(&mut self, tm: &mut TimeManager, ui: &mut Ui) -> Result<()> {
         set_camera(&ui.camera());
         let t = tm.now() as f32;
-        ui.fill_rect(ui.screen_rect(), (*self.illu.texture.1, ui.screen_rect()));
-        ui.fill_rect(ui.screen_rect(), semi_black(0.55));
+        let rt = tm.real_time() as f32;
+        // the background blurs as the side panel takes focus
+        self.background.set_source(self.illu.texture.1.clone());
+        let background = self.background.get(self.side_progress(rt) * SIDE_BLUR, 0.55);
+        ui.fill_rect(ui.screen_rect(), (background, ui.screen_rect()));
 
         let c = semi_white((t / FADE_IN_TIME).clamp(-1., 0.) + 1.);
 
@@ -1898,12 +2095,21 @@ impl Scene for SongScene {
             .pos(r.right() + 0.02, r.y)
             .color(c)
             .draw();
-        ui.text(&self.info.composer)
+        let r = ui
+            .text(&self.info.composer)
             .size(0.5)
             .pos(r.x + 0.02, r.bottom() + 0.03)
             .color(Color { a: c.a * 0.8, ..c })
             .draw();
 
+        let mut x = r.x;
+        for (btn, path, level) in &mut self.diff_btns {
+            let w = ui.text(&*level).size(0.45).measure().w + 0.06;
+            let br = Rect::new(x, r.bottom() + 0.03, w, 0.07);
+            btn.render_text(ui, br, t, c.a, &*level, 0.45, Some(&*path) == self.local_path.as_ref());
+            x += w + 0.02;
+        }
+
         // bottom bar
         let s = 0.25;
         let r = Rect::new(-0.94, ui.top - s - 0.06, s, s);
@@ -2009,11 +2215,8 @@ impl Scene for SongScene {
             dl.render(ui, t);
         }
 
-        let rt = tm.real_time() as f32;
         if self.side_enter_time.is_finite() {
-            let p = ((rt - self.side_enter_time.abs()) / EDIT_TRANSIT).min(1.);
-            let p = 1. - (1. - p).powi(3);
-            let p = if self.side_enter_time < 0. { 1. - p } else { p };
+            let p = self.side_progress(rt);
             ui.fill_rect(ui.screen_rect(), semi_black(p * 0.6));
             let w = self.side_content.width();
             let lf = f32::tween(&1.04, &(1. - w), p);
@@ -2055,7 +2258,6 @@ impl Scene for SongScene {
         if self.edit_tags_task.is_some() || self.rate_task.is_some() {
             ui.full_loading("", t);
         }
-        let rt = tm.real_time() as f32;
         self.tags.render(ui, rt);
         self.rate_dialog.render(ui, rt);
 
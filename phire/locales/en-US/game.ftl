@@ -5,6 +5,9 @@ offset-cancel = Cancel
 offset-reset = Reset
 offset-save = Save
 speed = Speed
+failed = FAILED
+reload-failed = Failed to reload chart: { $error }
+reload-effects-added = Effects were added to the chart, reopen it to see them
 
 ex-time-out-of-range = Time is out of range
 ex-invalid-format = Invalid format
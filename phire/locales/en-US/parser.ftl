@@ -13,7 +13,7 @@ color-events-parse-failed = Failed to parse color events
 gif-events-parse-failed = Failed to parse gif events
 
 illustration-load-failed = Failed to load illustration at { $path }
-gif-load-failed = Failed to load gif at { $path }
+gif-load-failed = Failed to load animated image at { $path }
 
 judge-line-location-name = In judge line #{ $jlid } ({ $name })
 hitsound-missing = Hitsound `{ $name }` is missing
@@ -41,5 +41,12 @@ line-location = On line #{ $lid }
 # extra
 shader-load-failed = Cannot load shader from { $path }
 shader-not-found = Cannot find preset shader { $shader }
+shader-compile-failed = Failed to compile shader { $path }, it is skipped
 effect-location = In effect #{ $id }
+effect-texture-load-failed = Failed to load effect texture from { $path }
+effect-target-conflict = An effect can only be global, or target either a line or a layer
+unknown-music-input = Unknown music input { $input }
 video-load-failed = Failed to read video from { $path }
+effect-line-not-found = Line { $line } the effect targets does not exist
+emitter-location = In emitter #{ $id }
+emitter-line-not-found = Line { $line } the emitter is attached to does not exist
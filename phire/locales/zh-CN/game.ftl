@@ -5,6 +5,9 @@ offset-cancel = 取消
 offset-reset = 重置
 offset-save = 保存
 speed = 速度
+failed = 挑战失败
+reload-failed = 谱面重载失败：{ $error }
+reload-effects-added = 谱面新增了 effect，需重新打开谱面才能生效
 
 ex-time-out-of-range = 时间不在范围内
 ex-invalid-format = 格式有误
@@ -13,7 +13,7 @@ color-events-parse-failed = color 事件解析失败
 gif-events-parse-failed = gif 事件解析失败
 
 illustration-load-failed = 位于 { $path } 的插图加载失败
-gif-load-failed = 位于 { $path } 的 gif 加载失败
+gif-load-failed = 位于 { $path } 的动图加载失败
 
 judge-line-location-name = #{ $jlid } ({ $name }) 判定线中
 hitsound-missing = 缺少打击音 `{ $name }`
@@ -41,5 +41,12 @@ line-location = #{ $lid } 行中
 # extra
 shader-load-failed = 无法从 { $path } 中加载 shader
 shader-not-found = 未找到预置 shader { $shader }
+shader-compile-failed = shader { $path } 编译失败，已跳过
 effect-location = #{ $id } 号 effect 中
+effect-texture-load-failed = 无法从 { $path } 中加载 effect 纹理
+effect-target-conflict = effect 只能为全局，或只作用于一条判定线或一个层级
+unknown-music-input = 未知的音乐输入 { $input }
 video-load-failed = 从 { $path } 中加载视频失败
+effect-line-not-found = effect 作用的判定线 { $line } 不存在
+emitter-location = #{ $id } 号粒子发射器中
+emitter-line-not-found = 粒子发射器绑定的判定线 { $line } 不存在
@@ -0,0 +1,268 @@
+use sasa::{AudioClip, Frame};
+use std::f32::consts::PI;
+
+// WSOLA parameters, in seconds
+const STRETCH_WINDOW: f32 = 0.04;
+const STRETCH_TOLERANCE: f32 = 0.012;
+const STRETCH_COARSE_STEP: usize = 4;
+
+fn sample(mono: &[f32], i: isize) -> f32 {
+    if i >= 0 && (i as usize) < mono.len() {
+        mono[i as usize]
+    } else {
+        0.
+    }
+}
+
+fn correlation(mono: &[f32], a: isize, b: isize, len: usize, step: usize) -> f32 {
+    (0..len).step_by(step).map(|i| sample(mono, a + i as isize) * sample(mono, b + i as isize)).sum()
+}
+
+fn best_offset(mono: &[f32], natural: isize, nominal: isize, tolerance: isize, len: usize) -> isize {
+    let mut best = (nominal, f32::NEG_INFINITY);
+    let mut d = -tolerance;
+    while d <= tolerance {
+        let corr = correlation(mono, natural, nominal + d, len, STRETCH_COARSE_STEP);
+        if corr > best.1 {
+            best = (nominal + d, corr);
+        }
+        d += STRETCH_COARSE_STEP as isize;
+    }
+    let coarse = best.0;
+    for cand in (coarse - STRETCH_COARSE_STEP as isize)..=(coarse + STRETCH_COARSE_STEP as isize) {
+        if (cand - nominal).abs() > tolerance {
+            continue;
+        }
+        let corr = correlation(mono, natural, cand, len, 1);
+        if corr > best.1 {
+            best = (cand, corr);
+        }
+    }
+    best.0
+}
+
+/// Stretches `frames` so that playing the result at normal rate sounds like
+/// playing the input at `speed`, without changing the pitch (WSOLA).
+///
+/// Speeds that aren't positive leave `frames` unchanged.
+pub fn time_stretch(frames: &[Frame], sample_rate: u32, speed: f32) -> Vec<Frame> {
+    if (speed - 1.).abs() < 1e-3 || !(speed > 0. && speed.is_finite()) || frames.is_empty() {
+        return frames.to_vec();
+    }
+    let win = ((sample_rate as f32 * STRETCH_WINDOW) as usize / 2 * 2).max(64);
+    let hop = win / 2;
+    let tolerance = (sample_rate as f32 * STRETCH_TOLERANCE) as isize;
+    // periodic Hann window, sums to one at 50% overlap
+    let window: Vec<f32> = (0..win).map(|i| 0.5 - 0.5 * (2. * PI * i as f32 / win as f32).cos()).collect();
+    let mono: Vec<f32> = frames.iter().map(|it| (it.0 + it.1) * 0.5).collect();
+
+    let out_len = (frames.len() as f64 / speed as f64) as usize;
+    let mut out = vec![Frame::default(); out_len + win];
+    let mut prev: Option<isize> = None;
+    let mut out_pos = 0;
+    while out_pos < out_len {
+        let nominal = (out_pos as f64 * speed as f64) as isize;
+        let pos = match prev {
+            Some(prev) => best_offset(&mono, prev + hop as isize, nominal, tolerance, hop),
+            None => nominal,
+        };
+        for (i, w) in window.iter().enumerate() {
+            let src = pos + i as isize;
+            if src < 0 || src as usize >= frames.len() {
+                continue;
+            }
+            let frame = &frames[src as usize];
+            let dst = &mut out[out_pos + i];
+            dst.0 += frame.0 * w;
+            dst.1 += frame.1 * w;
+        }
+        prev = Some(pos);
+        out_pos += hop;
+    }
+    out.truncate(out_len);
+    out
+}
+
+pub fn time_stretch_clip(clip: &AudioClip, speed: f32) -> AudioClip {
+    AudioClip::from_raw(time_stretch(clip.frames(), clip.sample_rate(), speed), clip.sample_rate())
+}
+
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 3],
+    z: [f64; 2],
+}
+
+impl Biquad {
+    fn new(b: [f64; 3], a: [f64; 3]) -> Self {
+        Self { b, a, z: [0.; 2] }
+    }
+
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b[0] * x + self.z[0];
+        self.z[0] = self.b[1] * x - self.a[1] * y + self.z[1];
+        self.z[1] = self.b[2] * x - self.a[2] * y;
+        y
+    }
+}
+
+// K-weighting pre-filter from ITU-R BS.1770, derived for arbitrary sample rates
+fn k_weighting(sample_rate: u32) -> (Biquad, Biquad) {
+    use std::f64::consts::PI;
+    let rate = sample_rate as f64;
+
+    let f0 = 1681.974450955533;
+    let g = 3.999843853973347;
+    let q = 0.7071752369554196;
+    let k = (PI * f0 / rate).tan();
+    let vh = 10f64.powf(g / 20.);
+    let vb = vh.powf(0.4996667741545416);
+    let a0 = 1. + k / q + k * k;
+    let shelf = Biquad::new(
+        [(vh + vb * k / q + k * k) / a0, 2. * (k * k - vh) / a0, (vh - vb * k / q + k * k) / a0],
+        [1., 2. * (k * k - 1.) / a0, (1. - k / q + k * k) / a0],
+    );
+
+    let f0 = 38.13547087602444;
+    let q = 0.5003270373238773;
+    let k = (PI * f0 / rate).tan();
+    let a0 = 1. + k / q + k * k;
+    let high_pass = Biquad::new([1., -2., 1.], [1., 2. * (k * k - 1.) / a0, (1. - k / q + k * k) / a0]);
+
+    (shelf, high_pass)
+}
+
+fn block_loudness(power: f64) -> f64 {
+    -0.691 + 10. * power.log10()
+}
+
+/// Integrated loudness in LUFS as specified by EBU R128, or `None` if the
+/// clip is too short or silent.
+pub fn integrated_loudness(frames: &[Frame], sample_rate: u32) -> Option<f32> {
+    let segment = (sample_rate / 10) as usize;
+    if segment == 0 {
+        return None;
+    }
+    let mut filters = [k_weighting(sample_rate), k_weighting(sample_rate)];
+    let mut segments = Vec::with_capacity(frames.len() / segment + 1);
+    for chunk in frames.chunks(segment) {
+        if chunk.len() < segment {
+            break;
+        }
+        let mut sum = 0.;
+        for frame in chunk {
+            for (x, (shelf, high_pass)) in [frame.0, frame.1].into_iter().zip(filters.iter_mut()) {
+                let y = high_pass.process(shelf.process(x as f64));
+                sum += y * y;
+            }
+        }
+        segments.push(sum);
+    }
+    // 400ms gating blocks with 75% overlap
+    let blocks: Vec<f64> = segments.windows(4).map(|it| it.iter().sum::<f64>() / (segment * 4) as f64).collect();
+    let gated: Vec<f64> = blocks.into_iter().filter(|it| block_loudness(*it) > -70.).collect();
+    if gated.is_empty() {
+        return None;
+    }
+    let relative = block_loudness(gated.iter().sum::<f64>() / gated.len() as f64) - 10.;
+    let gated: Vec<f64> = gated.into_iter().filter(|it| block_loudness(*it) > relative).collect();
+    if gated.is_empty() {
+        return None;
+    }
+    Some(block_loudness(gated.iter().sum::<f64>() / gated.len() as f64) as f32)
+}
+
+/// Linear gain bringing `loudness` to `target` (both in LUFS), limited to -20dB..+10dB.
+pub fn loudness_gain(loudness: f32, target: f32) -> f32 {
+    10f32.powf((target - loudness).clamp(-20., 10.) / 20.)
+}
+
+/// Number of bands in [`MusicFeatures::spectrum`].
+pub const SPECTRUM_BANDS: usize = 8;
+// in frames, has to be a power of two
+const ANALYSIS_WINDOW: usize = 1024;
+
+/// What the music sounds like at some position, for effects following it.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MusicFeatures {
+    /// RMS amplitude around the position
+    pub rms: f32,
+    /// Beats since the start of the chart
+    pub beat: f32,
+    /// Amplitudes of logarithmically spaced frequency bands, from low to high
+    pub spectrum: [f32; SPECTRUM_BANDS],
+}
+
+impl MusicFeatures {
+    /// Analyzes the window of frames centered at `time` (in seconds).
+    pub fn analyze(frames: &[Frame], sample_rate: u32, time: f32, beat: f32) -> Self {
+        let start = (time * sample_rate as f32) as isize - ANALYSIS_WINDOW as isize / 2;
+        let mut re: Vec<f32> = (0..ANALYSIS_WINDOW as isize)
+            .map(|i| {
+                let index = start + i;
+                if index >= 0 && (index as usize) < frames.len() {
+                    let frame = &frames[index as usize];
+                    (frame.0 + frame.1) / 2.
+                } else {
+                    0.
+                }
+            })
+            .collect();
+        let rms = (re.iter().map(|it| it * it).sum::<f32>() / ANALYSIS_WINDOW as f32).sqrt();
+
+        // Hann window
+        for (i, x) in re.iter_mut().enumerate() {
+            *x *= 0.5 - 0.5 * (2. * PI * i as f32 / (ANALYSIS_WINDOW - 1) as f32).cos();
+        }
+        let mut im = vec![0.; ANALYSIS_WINDOW];
+        fft(&mut re, &mut im);
+        // a full scale sine gives about 1 in its band, after compensating for the window
+        let magnitudes: Vec<f32> = (0..ANALYSIS_WINDOW / 2)
+            .map(|i| (re[i] * re[i] + im[i] * im[i]).sqrt() * 4. / ANALYSIS_WINDOW as f32)
+            .collect();
+
+        let mut spectrum = [0.; SPECTRUM_BANDS];
+        let top = (ANALYSIS_WINDOW / 2) as f32;
+        for (band, value) in spectrum.iter_mut().enumerate() {
+            // skips the DC bin
+            let low = top.powf(band as f32 / SPECTRUM_BANDS as f32) as usize;
+            let high = (top.powf((band + 1) as f32 / SPECTRUM_BANDS as f32) as usize).max(low + 1);
+            *value = magnitudes[low..high].iter().copied().fold(0., f32::max);
+        }
+        Self { rms, beat, spectrum }
+    }
+}
+
+// in-place radix-2 FFT
+fn fft(re: &mut [f32], im: &mut [f32]) {
+    let n = re.len();
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+    let mut len = 2;
+    while len <= n {
+        let angle = -2. * PI / len as f32;
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let (sin, cos) = (angle * k as f32).sin_cos();
+                let (a, b) = (start + k, start + k + len / 2);
+                let (tr, ti) = (re[b] * cos - im[b] * sin, re[b] * sin + im[b] * cos);
+                re[b] = re[a] - tr;
+                im[b] = im[a] - ti;
+                re[a] += tr;
+                im[a] += ti;
+            }
+        }
+        len <<= 1;
+    }
+}
@@ -3,12 +3,13 @@ use crate::{
         Anim, AnimVector, BezierTween, BpmList, Chart, ChartExtra, ChartSettings, ClampedTween, CtrlObject, JudgeLine, JudgeLineCache, JudgeLineKind,
         Keyframe, Note, NoteKind, Object, StaticTween, Tweenable, UIElement,
     },
+    ext::EMPTY_TEXTURE,
     judge::{HitSound, JudgeStatus},
     parse::process_lines,
 };
 use anyhow::{bail, Result};
 use byteorder::{LittleEndian as LE, ReadBytesExt, WriteBytesExt};
-use macroquad::{prelude::Color, texture::Texture2D};
+use macroquad::prelude::Color;
 use std::{
     cell::RefCell,
     collections::HashMap,
@@ -436,7 +437,7 @@ impl BinaryData for JudgeLine {
         let object = r.read()?;
         let kind = match r.read::<u8>()? {
             0 => JudgeLineKind::Normal,
-            1 => JudgeLineKind::Texture(Texture2D::empty().into(), r.read()?),
+            1 => JudgeLineKind::Texture(EMPTY_TEXTURE.clone(), r.read()?),
             2 => JudgeLineKind::Text(r.read()?),
             3 => JudgeLineKind::Paint(r.read()?, RefCell::default()),
             _ => bail!("invalid judge line kind"),
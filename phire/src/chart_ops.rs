@@ -0,0 +1,163 @@
+//! Transformations baked into a parsed [`Chart`], for producing practice and remix variants.
+//!
+//! These only touch the chart itself. Effects, videos and emitters in [`Chart::extra`] can't be retimed, so operations moving
+//! things in time refuse charts having any of them, while [`mirror`] leaves them as is.
+
+use crate::{
+    core::{Anim, Chart, JudgeLine, JudgeLineKind, Keyframe, Note, NoteKind, Tweenable},
+    parse::process_lines,
+};
+use anyhow::{ensure, Result};
+use std::ops::Range;
+
+fn ensure_no_extra(chart: &Chart) -> Result<()> {
+    let extra = &chart.extra;
+    ensure!(!extra.has_effects(), "charts with effects can't be retimed");
+    ensure!(extra.videos.is_empty(), "charts with videos can't be retimed");
+    ensure!(extra.emitters.is_empty(), "charts with particle emitters can't be retimed");
+    Ok(())
+}
+
+fn map_line_times(line: &mut JudgeLine, f: impl Fn(f32) -> f32 + Copy) {
+    let object = &mut line.object;
+    object.alpha.map_time(f);
+    object.scale.0.map_time(f);
+    object.scale.1.map_time(f);
+    object.rotation.map_time(f);
+    object.translation.0.map_time(f);
+    object.translation.1.map_time(f);
+    line.height.map_time(f);
+    line.incline.map_time(f);
+    line.color.map_time(f);
+    match &mut line.kind {
+        JudgeLineKind::TextureGif(anim, ..) | JudgeLineKind::Paint(anim, _) => anim.map_time(f),
+        JudgeLineKind::Text(anim) => anim.map_time(f),
+        JudgeLineKind::Normal | JudgeLineKind::Texture(..) => {}
+    }
+    for note in &mut line.notes {
+        map_note_times(note, f);
+    }
+}
+
+fn map_note_times(note: &mut Note, f: impl Fn(f32) -> f32 + Copy) {
+    note.time = f(note.time);
+    if let NoteKind::Hold { end_time, .. } = &mut note.kind {
+        *end_time = f(*end_time);
+    }
+    let object = &mut note.object;
+    object.alpha.map_time(f);
+    object.scale.0.map_time(f);
+    object.scale.1.map_time(f);
+    object.rotation.map_time(f);
+    object.translation.0.map_time(f);
+    object.translation.1.map_time(f);
+}
+
+// notes are resorted, and simultaneous notes marked again
+fn refresh(chart: &mut Chart) {
+    for note in chart.lines.iter_mut().flat_map(|it| it.notes.iter_mut()) {
+        note.multiple_hint = false;
+    }
+    process_lines(&mut chart.lines);
+    for line in &mut chart.lines {
+        line.cache.reset(&mut line.notes);
+    }
+}
+
+/// Mirrors the chart horizontally. Unlike the `FLIP_X` mod, this is baked into the chart.
+///
+/// Line textures themselves are not flipped.
+pub fn mirror(chart: &mut Chart) {
+    for line in &mut chart.lines {
+        line.object.translation.0.map_value(|x| -x);
+        line.object.rotation.map_value(|r| -r);
+        line.anchor[0] = 1. - line.anchor[0];
+        for note in &mut line.notes {
+            note.object.translation.0.map_value(|x| -x);
+        }
+    }
+    refresh(chart);
+}
+
+/// Moves everything in the chart `delta` seconds later.
+///
+/// Fails if that would move any note before time 0.
+pub fn shift(chart: &mut Chart, delta: f32) -> Result<()> {
+    ensure!(delta.is_finite(), "invalid time shift: {delta}");
+    ensure_no_extra(chart)?;
+    if let Some(first) = chart.lines.iter().flat_map(|it| &it.notes).map(|it| it.time).min_by(f32::total_cmp) {
+        ensure!(first + delta >= 0., "shifting by {delta}s would move the note at {first}s before the start");
+    }
+    for line in &mut chart.lines {
+        map_line_times(line, |t| t + delta);
+    }
+    chart.bpm_list.get_mut().retime(1., delta);
+    Ok(())
+}
+
+/// Plays the chart `rate` times as fast, rescaling all times and BPMs.
+///
+/// The music has to be resampled accordingly.
+pub fn change_rate(chart: &mut Chart, rate: f32) -> Result<()> {
+    ensure!(rate > 0. && rate.is_finite(), "rate must be positive, got {rate}");
+    ensure_no_extra(chart)?;
+    for line in &mut chart.lines {
+        map_line_times(line, |t| t / rate);
+    }
+    chart.bpm_list.get_mut().retime(rate, 0.);
+    chart.offset /= rate;
+    refresh(chart);
+    Ok(())
+}
+
+// drops keyframes that have no effect within the range, keeping those needed to interpolate at its bounds
+fn trim_anim<T: Tweenable>(anim: &mut Anim<T>, range: &Range<f32>) {
+    let keyframes = std::mem::take(&mut anim.keyframes).into_vec();
+    let first = keyframes.iter().rposition(|it| it.time <= range.start).unwrap_or(0);
+    let last = keyframes.iter().position(|it| it.time >= range.end).unwrap_or(keyframes.len().saturating_sub(1));
+    anim.keyframes = keyframes
+        .into_iter()
+        .enumerate()
+        .filter(|(index, _)| (first..=last).contains(index))
+        .map(|(_, it)| it)
+        .collect::<Vec<Keyframe<T>>>()
+        .into_boxed_slice();
+    anim.cursor = 0;
+    if let Some(next) = &mut anim.next {
+        trim_anim(next, range);
+    }
+}
+
+fn trim_line(line: &mut JudgeLine, range: &Range<f32>) {
+    let object = &mut line.object;
+    trim_anim(&mut object.alpha, range);
+    trim_anim(&mut object.scale.0, range);
+    trim_anim(&mut object.scale.1, range);
+    trim_anim(&mut object.rotation, range);
+    trim_anim(&mut object.translation.0, range);
+    trim_anim(&mut object.translation.1, range);
+    trim_anim(&mut line.height, range);
+    trim_anim(&mut line.incline, range);
+    trim_anim(&mut line.color, range);
+    match &mut line.kind {
+        JudgeLineKind::TextureGif(anim, ..) | JudgeLineKind::Paint(anim, _) => trim_anim(anim, range),
+        JudgeLineKind::Text(anim) => trim_anim(anim, range),
+        JudgeLineKind::Normal | JudgeLineKind::Texture(..) => {}
+    }
+    line.notes.retain(|note| range.contains(&note.time));
+}
+
+/// Keeps only what happens within the time range, which then starts at time 0.
+///
+/// Hold notes starting within the range are kept entirely.
+pub fn extract(chart: &mut Chart, range: Range<f32>) -> Result<()> {
+    ensure!(range.start < range.end, "empty time range {range:?}");
+    ensure_no_extra(chart)?;
+    for line in &mut chart.lines {
+        trim_line(line, &range);
+    }
+    chart.bpm_list.get_mut().trim(range.clone());
+    shift(chart, -range.start)?;
+    refresh(chart);
+    Ok(())
+}
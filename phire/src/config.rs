@@ -1,3 +1,4 @@
+use crate::{audio::loudness_gain, rks::RksContext};
 use bitflags::bitflags;
 use serde::{Deserialize, Serialize};
 
@@ -9,6 +10,13 @@ bitflags! {
         const FLIP_X = 2;
         const FADE_OUT = 4;
         const FULL_SCREEN_JUDGE = 8;
+        const SUDDEN_DEATH = 16;
+        const PERFECT_ONLY = 32;
+        const LIFE_GAUGE = 64;
+        const HIDDEN = 128;
+        const SUDDEN = 256;
+        const RANDOM = 512;
+        const NO_LINE = 1024;
     }
 }
 
@@ -44,19 +52,30 @@ pub struct Config {
     pub double_click_to_pause: bool,
     pub fxaa: bool,
     pub interactive: bool,
+    pub loudness_normalization: bool,
     pub note_scale: f32,
     pub mods: Mods,
     pub mp_enabled: bool,
     pub mp_address: String,
+    pub nightcore: bool,
     pub offline_mode: bool,
     pub offset: f32,
     pub particle: bool,
     pub player_name: String,
     pub player_rks: f32,
+    /// Seed of the `RANDOM` mod, with 0 picking a new one for every play
+    pub random_seed: u64,
     pub res_pack_path: Option<String>,
+    /// Local records for the offline RKS projection, filled in by the client before playing
+    #[serde(skip)]
+    pub rks_context: Option<RksContext>,
     pub sample_count: u32,
     pub show_acc: bool,
     pub speed: f32,
+    /// Chart time to start playing from, set by the command-line launcher
+    #[serde(skip)]
+    pub start_time: f32,
+    pub target_loudness: f32,
     pub touch_debug: bool,
     pub volume_music: f32,
     pub volume_sfx: f32,
@@ -116,19 +135,25 @@ impl Default for Config {
             double_click_to_pause: true,
             fxaa: false,
             interactive: true,
+            loudness_normalization: false,
             mods: Mods::default(),
             mp_address: "mp2.phira.cn:12345".to_owned(),
             mp_enabled: false,
+            nightcore: false,
             note_scale: 1.0,
             offline_mode: false,
             offset: 0.0,
             particle: true,
             player_name: "Guest".to_string(),
             player_rks: 15.,
+            random_seed: 0,
             res_pack_path: None,
+            rks_context: None,
             sample_count: 1,
             show_acc: false,
             speed: 1.0,
+            start_time: 0.,
+            target_loudness: -14.0,
             touch_debug: false,
             volume_music: 1.0,
             volume_sfx: 0.0,
@@ -193,8 +218,20 @@ impl Config {
         self.has_mod(Mods::FLIP_X)
     }
 
+    pub fn music_gain(&self, loudness: Option<f32>) -> f32 {
+        match loudness {
+            Some(loudness) if self.loudness_normalization => loudness_gain(loudness, self.target_loudness),
+            _ => 1.,
+        }
+    }
+
     #[inline]
     pub fn full_scrrn_judge(&self) -> bool {
         self.has_mod(Mods::FULL_SCREEN_JUDGE)
     }
+
+    #[inline]
+    pub fn can_fail(&self) -> bool {
+        !self.autoplay() && self.mods.intersects(Mods::SUDDEN_DEATH | Mods::PERFECT_ONLY | Mods::LIFE_GAUGE)
+    }
 }
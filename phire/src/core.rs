@@ -12,14 +12,20 @@ pub type Matrix = nalgebra::Matrix3<f32>;
 mod anim;
 pub use anim::{Anim, AnimFloat, AnimVector, Keyframe};
 
+mod animated;
+pub use animated::AnimatedTexture;
+
 mod chart;
-pub use chart::{Chart, ChartExtra, ChartSettings, HitSoundMap};
+pub use chart::{Chart, ChartExtra, ChartSettings, EffectLayer, HitSoundMap, LayerEffect};
 
 mod effect;
-pub use effect::{Effect, Uniform};
+pub use effect::{Effect, EffectPass, EffectTexture, IntUniform, MusicInput, MusicUniform, ShaderCompileError, Uniform};
+
+mod emitter;
+pub use emitter::ChartEmitter;
 
 mod line;
-pub use line::{GifFrames, JudgeLine, JudgeLineCache, JudgeLineKind, UIElement};
+pub use line::{JudgeLine, JudgeLineCache, JudgeLineKind, UIElement};
 
 mod note;
 use macroquad::prelude::set_pc_assets_folder;
@@ -32,7 +38,7 @@ mod render;
 pub use render::{copy_fbo, internal_id, MSRenderTarget};
 
 mod resource;
-pub use resource::{NoteStyle, ParticleEmitter, ResPackInfo, Resource, ResourcePack, SfxMap, BUFFER_SIZE, DPI_VALUE};
+pub use resource::{NoteAnimation, NoteStyle, ParticleEmitter, ParticleInfo, ResPackInfo, Resource, ResourcePack, SfxMap, BUFFER_SIZE, DPI_VALUE};
 
 mod smooth;
 pub use smooth::Smooth;
@@ -41,7 +47,7 @@ mod tween;
 pub use tween::{easing_from, BezierTween, ClampedTween, StaticTween, TweenFunction, TweenId, TweenMajor, TweenMinor, Tweenable, TWEEN_FUNCTIONS};
 
 mod video;
-pub use video::Video;
+pub use video::{Video, VideoAlpha};
 
 pub fn init_assets() {
     if let Ok(mut exe) = std::env::current_exe() {
@@ -101,6 +107,37 @@ impl BpmList {
         BpmList { elements, cursor: 0 }
     }
 
+    /// BPM changes as `(time, bpm)` pairs.
+    pub fn changes(&self) -> Vec<(f32, f32)> {
+        self.elements.iter().map(|it| (it.1, it.2)).collect()
+    }
+
+    /// Maps every time `t` to `t / rate + shift`, keeping notes on the same beats.
+    pub fn retime(&mut self, rate: f32, shift: f32) {
+        for (_, time, bpm) in &mut self.elements {
+            *time = *time / rate + shift;
+            *bpm *= rate;
+        }
+        self.cursor = 0;
+    }
+
+    /// Keeps only the BPM changes within the time range, with the one in effect at its start moved there, and counts beats
+    /// from that start.
+    pub fn trim(&mut self, range: std::ops::Range<f32>) {
+        if self.elements.is_empty() {
+            return;
+        }
+        let base = self.beat(range.start);
+        let first = self.elements.iter().rposition(|it| it.1 <= range.start).unwrap_or(0);
+        self.elements.drain(..first);
+        self.elements.retain(|it| it.1 < range.end);
+        self.elements[0].1 = range.start;
+        for (index, (beats, ..)) in self.elements.iter_mut().enumerate() {
+            *beats = if index == 0 { 0. } else { *beats - base };
+        }
+        self.cursor = 0;
+    }
+
     pub fn time_beats(&mut self, beats: f32) -> f32 {
         while let Some(kf) = self.elements.get(self.cursor + 1) {
             if kf.0 > beats {
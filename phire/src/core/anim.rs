@@ -125,6 +125,18 @@ impl<T: Tweenable> Anim<T> {
         })
     }
 
+    pub fn map_time(&mut self, f: impl Fn(f32) -> f32 + Copy) {
+        self.keyframes.iter_mut().for_each(|it| it.time = f(it.time));
+        if let Some(next) = &mut self.next {
+            next.map_time(f);
+        }
+        // forces the cursor to be recomputed
+        let time = f(self.time);
+        self.cursor = 0;
+        self.time = f32::NAN;
+        self.set_time(time);
+    }
+
     pub fn map_value(&mut self, mut f: impl FnMut(T) -> T) {
         self.keyframes.iter_mut().for_each(|it| it.value = f(it.value.clone()));
         if let Some(next) = &mut self.next {
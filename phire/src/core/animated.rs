@@ -0,0 +1,320 @@
+use crate::{
+    ext::{SafeTexture, BLACK_TEXTURE},
+    fs::spawn_task,
+};
+use anyhow::{bail, Result};
+use image::{
+    codecs::{gif::GifDecoder, png::PngDecoder, webp::WebPDecoder},
+    imageops, AnimationDecoder, DynamicImage, Frames, ImageFormat, RgbaImage,
+};
+use macroquad::prelude::Rect;
+use once_cell::unsync::OnceCell;
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    io::Cursor,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{self, Receiver, SyncSender},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+use tracing::{debug, warn};
+
+/// Atlases never grow larger than this in either dimension.
+const MAX_ATLAS_SIZE: u32 = 2048;
+/// Frames decoded ahead of the one shown when streaming, and kept around after.
+const RING_SIZE: usize = 4;
+
+enum Storage {
+    /// Frames laid out in rows of `columns`, each of the same size
+    Atlas { texture: SafeTexture, columns: u32 },
+    /// Frames too large to fit into an atlas
+    Streamed(RefCell<FrameStream>),
+}
+
+/// Decoded frames waiting to be uploaded, which needs a GL context
+enum Decoded {
+    Atlas(RgbaImage, u32),
+    Streamed(Arc<[u8]>, ImageFormat, RgbaImage),
+}
+
+/// Frames decoded on a separate thread, staying a few frames ahead of the one shown.
+struct FrameStream {
+    /// Index of the frame shown last
+    wanted: Arc<AtomicUsize>,
+    rx: Receiver<(usize, RgbaImage)>,
+    /// Most recently decoded last
+    ring: VecDeque<(usize, SafeTexture)>,
+}
+
+impl FrameStream {
+    fn new(data: Arc<[u8]>, format: ImageFormat, count: usize, first: RgbaImage) -> Self {
+        let wanted = Arc::new(AtomicUsize::new(0));
+        let (tx, rx) = mpsc::sync_channel(RING_SIZE);
+        thread::spawn({
+            let wanted = Arc::clone(&wanted);
+            move || {
+                if let Err(err) = stream_frames(data, format, count, wanted, tx) {
+                    warn!("failed to stream frames: {err:?}");
+                }
+            }
+        });
+        let mut ring = VecDeque::with_capacity(RING_SIZE);
+        ring.push_back((0, DynamicImage::ImageRgba8(first).into()));
+        Self { wanted, rx, ring }
+    }
+
+    fn frame(&mut self, index: usize) -> SafeTexture {
+        self.wanted.store(index, Ordering::Relaxed);
+        while let Ok((index, image)) = self.rx.try_recv() {
+            if self.ring.len() == RING_SIZE {
+                self.ring.pop_front();
+            }
+            self.ring.push_back((index, DynamicImage::ImageRgba8(image).into()));
+        }
+        // keeps showing whatever was decoded last if the frame isn't ready yet, rather than waiting for it
+        let found = self.ring.iter().find(|it| it.0 == index).or(self.ring.back());
+        found.map_or_else(|| BLACK_TEXTURE.clone(), |it| it.1.clone())
+    }
+}
+
+// decodes frames in a loop, sending those within `RING_SIZE` frames from the wanted one on, until the stream is dropped
+// or, for animations fitting into the ring, until all of them are sent
+fn stream_frames(data: Arc<[u8]>, format: ImageFormat, count: usize, wanted: Arc<AtomicUsize>, tx: SyncSender<(usize, RgbaImage)>) -> Result<()> {
+    let mut decoder = open(Arc::clone(&data), format)?;
+    if count <= RING_SIZE {
+        // the ring holds the whole animation, so it's decoded only once, the first frame being there already
+        for (index, frame) in decoder.enumerate().skip(1) {
+            if tx.send((index, frame?.into_buffer())).is_err() {
+                break;
+            }
+        }
+        return Ok(());
+    }
+    let mut index = 0;
+    loop {
+        let target = wanted.load(Ordering::Relaxed);
+        // how far the frame to decode is ahead of the wanted one, wrapping around
+        let ahead = (index + count - target) % count;
+        if ahead >= RING_SIZE && ahead <= count / 2 {
+            // far enough ahead, wait for the wanted frame to catch up
+            if Arc::strong_count(&wanted) == 1 {
+                return Ok(());
+            }
+            thread::sleep(Duration::from_millis(5));
+            continue;
+        }
+        // frames may depend on previous ones, so seeking backwards restarts decoding from the first frame
+        if ahead >= RING_SIZE && target < index {
+            decoder = open(Arc::clone(&data), format)?;
+            index = 0;
+            continue;
+        }
+        let Some(frame) = decoder.next() else { bail!("frame {index} is missing") };
+        let frame = frame?;
+        if ahead < RING_SIZE && tx.send((index, frame.into_buffer())).is_err() {
+            return Ok(());
+        }
+        index += 1;
+        if index == count {
+            decoder = open(Arc::clone(&data), format)?;
+            index = 0;
+        }
+    }
+}
+
+fn open(data: Arc<[u8]>, format: ImageFormat) -> Result<Frames<'static>> {
+    let reader = Cursor::new(data);
+    Ok(match format {
+        ImageFormat::Gif => GifDecoder::new(reader)?.into_frames(),
+        ImageFormat::Png => PngDecoder::new(reader)?.apng().into_frames(),
+        ImageFormat::WebP => WebPDecoder::new(reader)?.into_frames(),
+        _ => bail!("{format:?} images are not animated"),
+    })
+}
+
+fn is_animated(data: &Arc<[u8]>, format: ImageFormat) -> Result<bool> {
+    Ok(match format {
+        ImageFormat::Gif => true,
+        ImageFormat::Png => PngDecoder::new(Cursor::new(Arc::clone(data)))?.is_apng(),
+        ImageFormat::WebP => WebPDecoder::new(Cursor::new(Arc::clone(data)))?.has_animation(),
+        _ => false,
+    })
+}
+
+// like browsers do, delays of 10ms or less are treated as 100ms
+fn delay_millis(delay: Duration) -> u128 {
+    match delay.as_millis() {
+        it if it <= 10 => 100,
+        it => it,
+    }
+}
+
+/// Frames of a GIF, APNG or animated WebP image, looping over time.
+///
+/// Still images are supported as a single frame, so animated assets can always be loaded through this.
+pub struct AnimatedTexture {
+    /// Time of each frame in milliseconds
+    delays: Vec<u128>,
+    /// Milliseconds
+    total_time: u128,
+    size: (u32, u32),
+    decoded: RefCell<Option<Decoded>>,
+    storage: OnceCell<Storage>,
+}
+
+impl AnimatedTexture {
+    pub fn from_texture(texture: SafeTexture) -> Self {
+        Self {
+            delays: vec![100],
+            total_time: 100,
+            size: (texture.width() as u32, texture.height() as u32),
+            decoded: RefCell::default(),
+            storage: Storage::Atlas { texture, columns: 1 }.into(),
+        }
+    }
+
+    /// Decodes and uploads the frames, see [`Self::decode`].
+    pub async fn load(data: Vec<u8>) -> Result<Self> {
+        let res = Self::decode(data).await?;
+        res.upload();
+        Ok(res)
+    }
+
+    /// Decodes the frame timings up front, packing frames into a single image, or streaming them if they don't fit.
+    ///
+    /// Nothing is uploaded until [`Self::upload`] is called or a frame is first shown, so this doesn't need a GL context.
+    pub async fn decode(data: Vec<u8>) -> Result<Self> {
+        let data: Arc<[u8]> = data.into();
+        let format = image::guess_format(&data)?;
+        if !is_animated(&data, format)? {
+            let image = image::load_from_memory_with_format(&data, format)?.into_rgba8();
+            return Ok(Self {
+                delays: vec![100],
+                total_time: 100,
+                size: image.dimensions(),
+                decoded: Some(Decoded::Atlas(image, 1)).into(),
+                storage: OnceCell::new(),
+            });
+        }
+        debug!("decoding animated {format:?}");
+        let (delays, size, first, atlas) = spawn_task({
+            let data = Arc::clone(&data);
+            move || {
+                let mut delays = Vec::new();
+                let mut size = (0, 0);
+                let mut first = None;
+                let mut atlas: Option<(RgbaImage, u32)> = None;
+                for frame in open(data, format)? {
+                    let frame = frame?;
+                    delays.push(delay_millis(frame.delay().into()));
+                    let buffer = frame.buffer();
+                    if delays.len() == 1 {
+                        size = buffer.dimensions();
+                        first = Some(buffer.clone());
+                        let columns = (MAX_ATLAS_SIZE / size.0.max(1)).max(1);
+                        if size.0 <= MAX_ATLAS_SIZE {
+                            atlas = Some((RgbaImage::new(size.0 * columns, size.1), columns));
+                        }
+                    }
+                    // the atlas grows a row at a time, and is dropped once too large
+                    if let Some((image, columns)) = &mut atlas {
+                        let index = delays.len() as u32 - 1;
+                        let (x, y) = (index % *columns * size.0, index / *columns * size.1);
+                        if y + size.1 > MAX_ATLAS_SIZE {
+                            atlas = None;
+                        } else {
+                            if y + size.1 > image.height() {
+                                let mut grown = RgbaImage::new(image.width(), y + size.1);
+                                imageops::replace(&mut grown, image, 0, 0);
+                                *image = grown;
+                            }
+                            imageops::replace(image, buffer, x as i64, y as i64);
+                        }
+                    }
+                }
+                let Some(first) = first else { bail!("no frames") };
+                Ok((delays, size, first, atlas))
+            }
+        })
+        .await?;
+        debug!("{} frames decoded", delays.len());
+        let decoded = match atlas {
+            Some((image, columns)) => Decoded::Atlas(image, columns),
+            None => Decoded::Streamed(data, format, first),
+        };
+        Ok(Self {
+            total_time: delays.iter().sum(),
+            delays,
+            size,
+            decoded: Some(decoded).into(),
+            storage: OnceCell::new(),
+        })
+    }
+
+    /// Uploads the decoded frames, and starts streaming them if they didn't fit into an atlas. Does nothing if already uploaded.
+    pub fn upload(&self) {
+        self.storage();
+    }
+
+    fn storage(&self) -> &Storage {
+        self.storage.get_or_init(|| match self.decoded.take().expect("frames are decoded until uploaded") {
+            Decoded::Atlas(image, columns) => Storage::Atlas {
+                texture: DynamicImage::ImageRgba8(image).into(),
+                columns,
+            },
+            Decoded::Streamed(data, format, first) => Storage::Streamed(RefCell::new(FrameStream::new(data, format, self.delays.len(), first))),
+        })
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.delays.len()
+    }
+
+    /// Size of a single frame in pixels
+    pub fn size(&self) -> (u32, u32) {
+        self.size
+    }
+
+    /// Returns the texture holding the frame shown at `time` (in milliseconds), along with the source rect of the frame in pixels.
+    pub fn get_time_frame(&self, time: u128) -> (SafeTexture, Rect) {
+        let mut time = time % self.total_time;
+        let index = self
+            .delays
+            .iter()
+            .position(|t| {
+                let found = time < *t;
+                time = time.saturating_sub(*t);
+                found
+            })
+            .unwrap_or(self.delays.len() - 1);
+        self.frame(index)
+    }
+
+    pub fn get_prog_frame(&self, prog: f32) -> (SafeTexture, Rect) {
+        let time = (prog * self.total_time as f32) as u128;
+        self.get_time_frame(time)
+    }
+
+    fn frame(&self, index: usize) -> (SafeTexture, Rect) {
+        let (w, h) = self.size;
+        match self.storage() {
+            Storage::Atlas { texture, columns } => {
+                let index = index as u32;
+                let rect = Rect::new((index % columns * w) as f32, (index / columns * h) as f32, w as f32, h as f32);
+                // half a texel in from the edges, so that filtering doesn't bleed in neighbouring frames
+                let rect = if self.delays.len() > 1 { Rect::new(rect.x + 0.5, rect.y + 0.5, rect.w - 1., rect.h - 1.) } else { rect };
+                (texture.clone(), rect)
+            }
+            Storage::Streamed(stream) => (stream.borrow_mut().frame(index), Rect::new(0., 0., w as f32, h as f32)),
+        }
+    }
+
+    /// Milliseconds
+    pub fn total_time(&self) -> u128 {
+        self.total_time
+    }
+}
@@ -1,17 +1,87 @@
 crate::tl_file!("parser");
 
-use super::{BpmList, Effect, JudgeLine, JudgeLineKind, Matrix, Resource, UIElement, Vector, Video};
-use crate::{core::Object, fs::FileSystem, judge::JudgeStatus, scene::show_error, ui::Ui};
+use super::{BpmList, ChartEmitter, Effect, JudgeLine, JudgeLineKind, MSRenderTarget, Matrix, Resource, UIElement, Vector, Video};
+use crate::{
+    core::Object,
+    ext::{shader, SafeTexture},
+    fs::FileSystem,
+    judge::JudgeStatus,
+    scene::show_error,
+    ui::Ui,
+};
 use anyhow::{Context, Result};
 use macroquad::prelude::*;
+use miniquad::{BlendFactor, BlendState, BlendValue, Equation};
+use once_cell::sync::Lazy;
 use sasa::AudioClip;
-use std::{cell::RefCell, collections::HashMap};
+use std::{cell::RefCell, collections::HashMap, ops::Range};
+
+// the default material, except that alpha is accumulated as `src + dst * (1 - src)` so that layers end up premultiplied
+static LAYER_MATERIAL: Lazy<Material> = Lazy::new(|| premultiplied_material(BlendFactor::Value(BlendValue::SourceAlpha)));
+// draws premultiplied layers back
+static COMPOSITE_MATERIAL: Lazy<Material> = Lazy::new(|| premultiplied_material(BlendFactor::One));
+
+fn premultiplied_material(color_factor: BlendFactor) -> Material {
+    let one_minus_alpha = BlendFactor::OneMinusValue(BlendValue::SourceAlpha);
+    load_material(
+        shader::VERTEX,
+        shader::FRAGMENT,
+        MaterialParams {
+            pipeline_params: PipelineParams {
+                color_blend: Some(BlendState::new(Equation::Add, color_factor, one_minus_alpha)),
+                alpha_blend: Some(BlendState::new(Equation::Add, BlendFactor::One, one_minus_alpha)),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+    )
+    .unwrap()
+}
+
+/// Lines a [`LayerEffect`] applies to.
+#[derive(Clone, Debug, PartialEq)]
+pub enum EffectLayer {
+    /// A single judge line, along with its notes
+    Line(usize),
+    /// All lines whose z index is within the range, inclusive
+    ZIndex(i32, i32),
+}
+
+impl EffectLayer {
+    pub fn contains(&self, id: usize, line: &JudgeLine) -> bool {
+        match self {
+            Self::Line(target) => *target == id,
+            Self::ZIndex(low, high) => (*low..=*high).contains(&line.z_index),
+        }
+    }
+}
+
+/// Effect rendered on only some of the lines, which are drawn into an intermediate target first.
+pub struct LayerEffect {
+    pub layer: EffectLayer,
+    pub effect: Effect,
+    target: Option<MSRenderTarget>,
+}
+
+impl LayerEffect {
+    pub fn new(layer: EffectLayer, effect: Effect) -> Self {
+        Self { layer, effect, target: None }
+    }
+}
 
 #[derive(Default)]
 pub struct ChartExtra {
     pub effects: Vec<Effect>,
     pub global_effects: Vec<Effect>,
+    pub layer_effects: Vec<LayerEffect>,
     pub videos: Vec<Video>,
+    pub emitters: Vec<ChartEmitter>,
+}
+
+impl ChartExtra {
+    pub fn has_effects(&self) -> bool {
+        !self.effects.is_empty() || !self.global_effects.is_empty() || !self.layer_effects.is_empty()
+    }
 }
 
 #[derive(Default)]
@@ -61,6 +131,23 @@ impl Chart {
         }
     }
 
+    /// Checks that everything in [`ChartExtra`] referring to lines refers to existing ones, which can only be done once both are parsed.
+    pub fn check_extra(&self) -> Result<()> {
+        for (id, emitter) in self.extra.emitters.iter().enumerate() {
+            if let Some(line) = emitter.line.filter(|it| *it >= self.lines.len()) {
+                return Err(tl!(err "emitter-line-not-found", "line" => line)).with_context(|| tl!("emitter-location", "id" => id));
+            }
+        }
+        for layer in &self.extra.layer_effects {
+            if let EffectLayer::Line(line) = layer.layer {
+                if line >= self.lines.len() {
+                    return Err(tl!(err "effect-line-not-found", "line" => line));
+                }
+            }
+        }
+        Ok(())
+    }
+
     #[inline]
     pub fn with_element<R>(&self, ui: &mut Ui, res: &Resource, element: UIElement, scale_point: Option<(f32, f32)>, rotation_point: Option<(f32, f32)>, f: impl FnOnce(&mut Ui, Color) -> R) -> R {
         if let Some(id) = self.attach_ui[element as usize - 1] {
@@ -99,9 +186,21 @@ impl Chart {
     }
 
     pub async fn load_textures(&mut self, fs: &mut dyn FileSystem) -> Result<()> {
+        let mut textures: HashMap<String, SafeTexture> = HashMap::new();
         for line in &mut self.lines {
-            if let JudgeLineKind::Texture(tex, path) = &mut line.kind {
-                *tex = image::load_from_memory(&fs.load_file(path).await.with_context(|| format!("failed to load illustration {path}"))?)?.into();
+            match &mut line.kind {
+                JudgeLineKind::Texture(tex, path) => {
+                    if let Some(loaded) = textures.get(path) {
+                        *tex = loaded.clone();
+                        continue;
+                    }
+                    let data = fs.load_file(path).await.with_context(|| tl!("illustration-load-failed", "path" => path.clone()))?;
+                    *tex = SafeTexture::from(image::load_from_memory(&data).with_context(|| tl!("illustration-load-failed", "path" => path.clone()))?)
+                        .with_mipmap();
+                    textures.insert(path.clone(), tex.clone());
+                }
+                JudgeLineKind::TextureGif(_, frames, _) => frames.upload(),
+                _ => {}
             }
         }
         Ok(())
@@ -139,27 +238,147 @@ impl Chart {
         for effect in &mut self.extra.effects {
             effect.update(res);
         }
+        for layer in &mut self.extra.layer_effects {
+            layer.effect.update(res);
+        }
+        for emitter in &mut self.extra.emitters {
+            emitter.update(res, &self.lines);
+        }
     }
 
-    pub fn render(&self, ui: &mut Ui, res: &mut Resource) {
+    // for each video, the index in `order` it is drawn before, or `None` if drawn under everything
+    fn video_slots(&self) -> Vec<Option<usize>> {
+        self.extra
+            .videos
+            .iter()
+            .map(|video| video.z.map(|z| self.order.partition_point(|id| self.lines[*id].z_index < z)))
+            .collect()
+    }
+
+    // splits `order` into runs of lines, each either drawn directly or through an active layer effect. Runs also break where
+    // videos are inserted
+    fn layer_runs(&self, res: &Resource, video_slots: &[Option<usize>]) -> Vec<(Option<usize>, Range<usize>)> {
+        let mut runs: Vec<(Option<usize>, Range<usize>)> = Vec::new();
+        for (index, id) in self.order.iter().enumerate() {
+            let layer = if res.no_effect {
+                None
+            } else {
+                // the first matching effect wins when layers overlap
+                self.extra
+                    .layer_effects
+                    .iter()
+                    .position(|it| it.effect.is_active() && it.layer.contains(*id, &self.lines[*id]))
+            };
+            let video_here = video_slots.contains(&Some(index));
+            match runs.last_mut() {
+                Some((last, range)) if *last == layer && !video_here => range.end = index + 1,
+                _ => runs.push((layer, index..index + 1)),
+            }
+        }
+        runs
+    }
+
+    fn render_videos(&self, res: &mut Resource, video_slots: &[Option<usize>], slot: Option<usize>) {
         res.apply_model_of(&Matrix::identity().append_nonuniform_scaling(&Vector::new(if res.config.flip_x() { -1. } else { 1. }, 1.)), |res| {
-            for video in &self.extra.videos {
+            for (video, _) in self.extra.videos.iter().zip(video_slots).filter(|it| *it.1 == slot) {
                 video.render(res);
             }
         });
+    }
+
+    fn render_lines(&self, ui: &mut Ui, res: &mut Resource, ids: &[usize]) {
         res.apply_model_of(&Matrix::identity().append_nonuniform_scaling(&Vector::new(if res.config.flip_x() { -1. } else { 1. }, -1.)), |res| {
             let mut guard = self.bpm_list.borrow_mut();
-            for id in &self.order {
+            for id in ids {
                 self.lines[*id].render(ui, res, &self.lines, &mut guard, &self.settings, *id);
             }
             drop(guard);
             res.note_buffer.borrow_mut().draw_all();
-            if res.config.sample_count > 1 {
-                unsafe { get_internal_gl() }.flush();
-                if let Some(target) = &res.chart_target {
-                    target.blit();
+        });
+    }
+
+    fn render_layer(&mut self, ui: &mut Ui, res: &mut Resource, camera: &Camera2D, index: usize, ids: Range<usize>) {
+        let mut gl = unsafe { get_internal_gl() };
+        gl.flush();
+        let old_pass = gl.quad_gl.get_active_render_pass();
+        let dim = res.chart_target.as_ref().unwrap().dim();
+        let msaa = res.config.sample_count > 1;
+
+        let layer = &mut self.extra.layer_effects[index];
+        if layer.target.as_ref().map_or(true, |it| it.dim() != dim) {
+            layer.target = Some(MSRenderTarget::transparent(dim, res.config.sample_count));
+        }
+        let mut target = layer.target.take().unwrap();
+        gl.quad_gl.render_pass(Some(if msaa { target.input() } else { target.output() }.render_pass));
+        clear_background(Color::default());
+        // only draws through the default material are affected, which covers lines and notes
+        gl_use_material(*LAYER_MATERIAL);
+        self.render_lines(ui, res, &self.order[ids]);
+        gl.flush();
+        gl_use_default_material();
+        if msaa {
+            target.blit();
+        }
+
+        let layer = &mut self.extra.layer_effects[index];
+        set_camera(&Camera2D {
+            zoom: vec2(1., res.aspect_ratio),
+            ..Default::default()
+        });
+        layer.effect.render_on(&mut target, res.aspect_ratio);
+
+        // composite the processed layer back
+        set_camera(&Camera2D {
+            zoom: vec2(1., res.aspect_ratio),
+            ..Default::default()
+        });
+        gl.quad_gl.render_pass(old_pass);
+        let top = 1. / res.aspect_ratio;
+        gl_use_material(*COMPOSITE_MATERIAL);
+        draw_texture_ex(
+            target.output().texture,
+            -1.,
+            -top,
+            WHITE,
+            DrawTextureParams {
+                dest_size: Some(vec2(2., top * 2.)),
+                ..Default::default()
+            },
+        );
+        gl.flush();
+        gl_use_default_material();
+        layer.target = Some(target);
+
+        set_camera(camera);
+        gl.quad_gl.render_pass(old_pass);
+    }
+
+    /// Renders the chart with `camera`, which is restored after drawing layer effects.
+    pub fn render(&mut self, ui: &mut Ui, res: &mut Resource, camera: &Camera2D) {
+        let video_slots = self.video_slots();
+        self.render_videos(res, &video_slots, None);
+        if self.extra.layer_effects.is_empty() && video_slots.iter().all(Option::is_none) {
+            // nothing to split the lines by
+            self.render_lines(ui, res, &self.order);
+        } else {
+            for (layer, range) in self.layer_runs(res, &video_slots) {
+                self.render_videos(res, &video_slots, Some(range.start));
+                match layer {
+                    Some(index) => self.render_layer(ui, res, camera, index, range),
+                    None => self.render_lines(ui, res, &self.order[range]),
                 }
             }
-        });
+        }
+        // overlays above every line
+        self.render_videos(res, &video_slots, Some(self.order.len()));
+        for emitter in &mut self.extra.emitters {
+            emitter.draw(res);
+        }
+        if res.config.sample_count > 1 {
+            unsafe { get_internal_gl() }.flush();
+            if let Some(target) = &res.chart_target {
+                target.blit();
+            }
+        }
     }
 }
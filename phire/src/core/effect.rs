@@ -1,12 +1,19 @@
-use super::{Anim, Resource, Tweenable};
-use crate::ext::{get_viewport, screen_aspect};
+use super::{copy_fbo, internal_id, Anim, MSRenderTarget, Resource, Tweenable};
+use crate::{
+    audio::MusicFeatures,
+    ext::{get_viewport, screen_aspect, SafeTexture},
+};
 use anyhow::{anyhow, bail, Result};
 use macroquad::prelude::*;
-use miniquad::UniformType;
+use miniquad::{ShaderError, UniformType};
 use once_cell::sync::Lazy;
 use phf::phf_map;
 use regex::Regex;
-use std::{collections::HashSet, ops::Range};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    ops::Range,
+};
 
 static SHADERS: phf::Map<&'static str, &'static str> = phf_map! {
     "chromatic" => include_str!("shaders/chromatic.glsl"),
@@ -70,10 +77,25 @@ impl UniformValue for Color {
     const UNIFORM_TYPE: UniformType = UniformType::Float4;
 }
 
+// also used for bool uniforms, which are set as integers in GLSL
+impl UniformValue for i32 {
+    const UNIFORM_TYPE: UniformType = UniformType::Int1;
+}
+
+impl UniformValue for Mat4 {
+    const UNIFORM_TYPE: UniformType = UniformType::Mat4;
+}
+
 pub trait Uniform {
     fn uniform_pair(&self) -> (String, UniformType);
     fn set_time(&mut self, t: f32);
     fn apply(&self, material: &Material);
+
+    fn uses_music(&self) -> bool {
+        false
+    }
+
+    fn set_music(&mut self, _features: &MusicFeatures) {}
 }
 
 impl<T: UniformValue> Uniform for (String, T) {
@@ -102,25 +124,223 @@ impl<T: UniformValue + Tweenable> Uniform for (String, Anim<T>) {
     }
 }
 
-pub struct Effect {
-    time_range: Range<f32>,
-    t: f32,
+/// Animated `int` or `bool` uniform, rounded from a float animation.
+pub struct IntUniform(pub String, pub Anim<f32>);
+
+impl Uniform for IntUniform {
+    fn uniform_pair(&self) -> (String, UniformType) {
+        (self.0.clone(), UniformType::Int1)
+    }
+
+    fn set_time(&mut self, t: f32) {
+        self.1.set_time(t);
+    }
+
+    fn apply(&self, material: &Material) {
+        material.set_uniform(&self.0, self.1.now().round() as i32);
+    }
+}
+
+/// Value computed from the playing music.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MusicInput {
+    /// RMS amplitude, as a `float`
+    Rms,
+    /// Beats since the start of the chart, as a `float`
+    Beat,
+    /// Position within the current beat in `0..1`, as a `float`
+    BeatPhase,
+    /// The lower half of [`MusicFeatures::spectrum`], as a `vec4`
+    SpectrumLow,
+    /// The higher half of [`MusicFeatures::spectrum`], as a `vec4`
+    SpectrumHigh,
+}
+
+impl MusicInput {
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "rms" => Self::Rms,
+            "beat" => Self::Beat,
+            "beatPhase" => Self::BeatPhase,
+            "spectrumLow" => Self::SpectrumLow,
+            "spectrumHigh" => Self::SpectrumHigh,
+            _ => return None,
+        })
+    }
+
+    /// Inputs are bound by convention to uniforms named after them with a `music` prefix, e.g. `musicBeatPhase`.
+    pub fn from_uniform_name(name: &str) -> Option<Self> {
+        let name = name.strip_prefix("music")?;
+        let mut chars = name.chars();
+        let first = chars.next()?;
+        Self::from_name(&(first.to_ascii_lowercase().to_string() + chars.as_str()))
+    }
+
+    pub fn glsl_type(self) -> &'static str {
+        match self {
+            Self::SpectrumLow | Self::SpectrumHigh => "vec4",
+            _ => "float",
+        }
+    }
+}
+
+pub struct MusicUniform {
+    name: String,
+    input: MusicInput,
+    value: Vec4,
+}
+
+impl MusicUniform {
+    pub fn new(name: String, input: MusicInput) -> Self {
+        Self { name, input, value: Vec4::ZERO }
+    }
+}
+
+impl Uniform for MusicUniform {
+    fn uniform_pair(&self) -> (String, UniformType) {
+        (
+            self.name.clone(),
+            if self.input.glsl_type() == "vec4" { UniformType::Float4 } else { UniformType::Float1 },
+        )
+    }
+
+    fn set_time(&mut self, _t: f32) {}
+
+    fn apply(&self, material: &Material) {
+        if self.input.glsl_type() == "vec4" {
+            material.set_uniform(&self.name, self.value);
+        } else {
+            material.set_uniform(&self.name, self.value.x);
+        }
+    }
+
+    fn uses_music(&self) -> bool {
+        true
+    }
+
+    fn set_music(&mut self, features: &MusicFeatures) {
+        let spectrum = &features.spectrum;
+        self.value = match self.input {
+            MusicInput::Rms => Vec4::splat(features.rms),
+            MusicInput::Beat => Vec4::splat(features.beat),
+            MusicInput::BeatPhase => Vec4::splat(features.beat.rem_euclid(1.)),
+            MusicInput::SpectrumLow => Vec4::new(spectrum[0], spectrum[1], spectrum[2], spectrum[3]),
+            MusicInput::SpectrumHigh => Vec4::new(spectrum[4], spectrum[5], spectrum[6], spectrum[7]),
+        };
+    }
+}
+
+#[derive(Clone)]
+pub enum EffectTexture {
+    /// An image from the chart
+    Image(SafeTexture),
+    /// The frame as it was before the effect
+    Input,
+    /// The output of the effect in the last frame
+    Previous,
+}
+
+/// GLSL compile or link failure, with the offending source lines quoted.
+#[derive(Debug)]
+pub struct ShaderCompileError(pub String);
+
+impl fmt::Display for ShaderCompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for ShaderCompileError {}
+
+impl ShaderCompileError {
+    fn new(error: ShaderError, source: &str) -> Self {
+        // drivers report locations as `0:12(3)`, `0:12:` or `0(12)`
+        static LINE_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"\b0(?::(\d+)|\((\d+)\))").unwrap());
+        let log = match error {
+            ShaderError::CompilationError { error_message, .. } => error_message,
+            ShaderError::LinkError(message) => format!("link error: {message}"),
+            error => return Self(format!("{error:?}")),
+        };
+        let lines: Vec<_> = source.lines().collect();
+        let mut res = String::new();
+        for message in log.lines().map(str::trim).filter(|it| !it.is_empty()) {
+            res += message;
+            res.push('\n');
+            let line = LINE_REGEX
+                .captures(message)
+                .and_then(|caps| caps.get(1).or(caps.get(2))?.as_str().parse::<usize>().ok());
+            if let Some(code) = line.and_then(|it| lines.get(it.checked_sub(1)?)) {
+                res += &format!("  {} | {}\n", line.unwrap(), code.trim_end());
+            }
+        }
+        Self(res.trim_end().to_owned())
+    }
+}
+
+/// A single shader of an [`Effect`], sampling the output of the previous pass as `screenTexture`.
+pub struct EffectPass {
     material: Material,
     defaults: Vec<Box<dyn Uniform>>,
     uniforms: Vec<Box<dyn Uniform>>,
-    pub global: bool,
+    textures: Vec<(String, EffectTexture)>,
+    /// Path of the shader in the chart folder, if not a preset
+    pub path: Option<String>,
 }
 
-impl Effect {
-    pub fn get_preset(name: &str) -> Option<&'static str> {
-        SHADERS.get(name).copied()
+impl EffectPass {
+    /// Fails with [`ShaderCompileError`] if the shader does not compile.
+    pub fn new(shader: &str, uniforms: Vec<Box<dyn Uniform>>, textures: Vec<(String, EffectTexture)>) -> Result<Self> {
+        let texture_names: Vec<_> = textures.iter().map(|it| it.0.clone()).collect();
+        let (material, defaults) = Self::compile(shader, &uniforms, &texture_names)?;
+        Ok(Self {
+            material,
+            defaults,
+            uniforms,
+            textures,
+            path: None,
+        })
     }
 
-    pub fn get_rpe_preset(name: &str) -> Option<&'static str> {
-        RPE_SHADERS.get(name).copied()
+    /// Like [`Self::new`], but falls back to [`Self::pass_through`] if the shader does not compile, returning the
+    /// [`ShaderCompileError`] along with it. Other errors are still returned as is.
+    pub fn new_or_pass_through(
+        shader: &str,
+        uniforms: Vec<Box<dyn Uniform>>,
+        textures: Vec<(String, EffectTexture)>,
+    ) -> Result<(Self, Option<anyhow::Error>)> {
+        let texture_names: Vec<_> = textures.iter().map(|it| it.0.clone()).collect();
+        match Self::compile(shader, &uniforms, &texture_names) {
+            Ok((material, defaults)) => Ok((
+                Self {
+                    material,
+                    defaults,
+                    uniforms,
+                    textures,
+                    path: None,
+                },
+                None,
+            )),
+            Err(err) if err.is::<ShaderCompileError>() => Ok((Self::pass_through(uniforms, textures), Some(err))),
+            Err(err) => Err(err),
+        }
     }
 
-    pub fn new(time_range: Range<f32>, shader: &str, uniforms: Vec<Box<dyn Uniform>>, global: bool) -> Result<Self> {
+    /// Keeps the uniforms and textures, but only shows the previous pass as is.
+    pub fn pass_through(uniforms: Vec<Box<dyn Uniform>>, textures: Vec<(String, EffectTexture)>) -> Self {
+        Self::new(PASS_THROUGH_SHADER, uniforms, textures).unwrap()
+    }
+
+    /// Replaces the shader, keeping the current one if the new one fails to compile.
+    pub fn reload(&mut self, shader: &str) -> Result<()> {
+        let texture_names: Vec<_> = self.textures.iter().map(|it| it.0.clone()).collect();
+        let (material, defaults) = Self::compile(shader, &self.uniforms, &texture_names)?;
+        self.material.delete();
+        self.material = material;
+        self.defaults = defaults;
+        Ok(())
+    }
+
+    fn compile(shader: &str, uniforms: &[Box<dyn Uniform>], texture_names: &[String]) -> Result<(Material, Vec<Box<dyn Uniform>>)> {
         static DEF_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"uniform\s+(\w+)\s+(\w+);\s+//\s+%([^%]+)%").unwrap());
         let defaults = DEF_REGEX
             .captures_iter(shader)
@@ -128,8 +348,17 @@ impl Effect {
                 let type_name = caps.get(1).unwrap().as_str();
                 let name = caps.get(2).unwrap().as_str().to_owned();
                 let value = caps.get(3).unwrap().as_str();
+                let floats = |len: usize| -> Result<Vec<f32>> {
+                    let values = value.split(',').map(|it| it.trim().parse()).collect::<Result<Vec<f32>, _>>()?;
+                    if values.len() != len {
+                        bail!("Expected {len} values");
+                    }
+                    Ok(values)
+                };
                 Ok(match type_name {
                     "float" => Box::new((name, value.parse::<f32>()?)),
+                    "int" => Box::new((name, value.trim().parse::<i32>()?)),
+                    "bool" => Box::new((name, value.trim().parse::<bool>()? as i32)),
                     "vec2" => Box::new((name, {
                         let (x, y) = value.split_once(',').ok_or_else(|| anyhow!("Expected x,y"))?;
                         vec2(x.trim().parse()?, y.trim().parse()?)
@@ -141,6 +370,7 @@ impl Effect {
                         }
                         Color::new(values[0].parse()?, values[1].parse()?, values[2].parse()?, values[3].parse()?)
                     })),
+                    "mat4" => Box::new((name, Mat4::from_cols_slice(&floats(16)?))),
                     _ => bail!("Unknown type: {type_name}"),
                 })
             })
@@ -158,72 +388,210 @@ impl Effect {
         add_uniform(("time".to_owned(), UniformType::Float1));
         add_uniform(("screenSize".to_owned(), UniformType::Float2));
         add_uniform(("UVScale".to_owned(), UniformType::Float2));
-        for u in &uniforms {
+        for u in uniforms {
             add_uniform(u.uniform_pair());
         }
-        Ok(Self {
+        let texture_names = std::iter::once("screenTexture".to_owned()).chain(texture_names.iter().cloned()).collect();
+        let material = load_material(
+            VERTEX_SHADER,
+            shader,
+            MaterialParams {
+                uniforms: new_uniforms,
+                textures: texture_names,
+                ..Default::default()
+            },
+        )
+        .map_err(|err| ShaderCompileError::new(err, shader))?;
+        Ok((material, defaults))
+    }
+
+    fn uses(&self, f: impl Fn(&EffectTexture) -> bool) -> bool {
+        self.textures.iter().any(|it| f(&it.1))
+    }
+}
+
+impl Drop for EffectPass {
+    fn drop(&mut self) {
+        self.material.delete();
+    }
+}
+
+pub struct Effect {
+    time_range: Range<f32>,
+    t: f32,
+    passes: Vec<EffectPass>,
+    // copies of the frame for passes sampling `EffectTexture::Input` or `EffectTexture::Previous`
+    input: Option<RenderTarget>,
+    previous: Option<RenderTarget>,
+    pub global: bool,
+}
+
+impl Effect {
+    pub fn get_preset(name: &str) -> Option<&'static str> {
+        SHADERS.get(name).copied()
+    }
+
+    pub fn get_rpe_preset(name: &str) -> Option<&'static str> {
+        RPE_SHADERS.get(name).copied()
+    }
+
+    pub fn preset_names() -> impl Iterator<Item = &'static str> {
+        SHADERS.keys().copied()
+    }
+
+    pub fn rpe_preset_names() -> impl Iterator<Item = &'static str> {
+        RPE_SHADERS.keys().copied()
+    }
+
+    /// Types of uniforms declared in the shader, by name.
+    pub fn declared_uniforms(shader: &str) -> HashMap<String, String> {
+        static DECL_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"uniform\s+(?:(?:lowp|mediump|highp)\s+)?(\w+)\s+(\w+)\s*;").unwrap());
+        DECL_REGEX
+            .captures_iter(shader)
+            .map(|caps| (caps[2].to_owned(), caps[1].to_owned()))
+            .collect()
+    }
+
+    pub fn new(time_range: Range<f32>, shader: &str, uniforms: Vec<Box<dyn Uniform>>, global: bool) -> Result<Self> {
+        Ok(Self::with_passes(time_range, vec![EffectPass::new(shader, uniforms, Vec::new())?], global))
+    }
+
+    /// Chains the passes in order, each one processing the output of the previous.
+    pub fn with_passes(time_range: Range<f32>, passes: Vec<EffectPass>, global: bool) -> Self {
+        Self {
             time_range,
             t: f32::NEG_INFINITY,
-            defaults,
-            material: load_material(
-                VERTEX_SHADER,
-                shader,
-                MaterialParams {
-                    uniforms: new_uniforms,
-                    textures: vec!["screenTexture".to_owned()],
-                    ..Default::default()
-                },
-            )?,
-            uniforms,
+            passes,
+            input: None,
+            previous: None,
             global,
-        })
+        }
+    }
+
+    /// Paths of the shaders loaded from the chart folder.
+    pub fn shader_paths(&self) -> impl Iterator<Item = &str> {
+        self.passes.iter().filter_map(|it| it.path.as_deref())
+    }
+
+    /// Reloads passes using the shader at `path`, returning whether there were any.
+    ///
+    /// Every such pass is reloaded even if some fail, in which case the errors of all of them are returned.
+    pub fn reload_shader(&mut self, path: &str, shader: &str) -> Result<bool> {
+        let mut found = false;
+        let mut errors = Vec::new();
+        for (index, pass) in self.passes.iter_mut().enumerate().filter(|(_, it)| it.path.as_deref() == Some(path)) {
+            found = true;
+            if let Err(err) = pass.reload(shader) {
+                errors.push(format!("pass #{index}: {err:?}"));
+            }
+        }
+        if !errors.is_empty() {
+            bail!("{}", errors.join("\n"));
+        }
+        Ok(found)
+    }
+
+    /// Whether any uniform follows the music, which then has to be analyzed.
+    pub fn uses_music(&self) -> bool {
+        self.passes.iter().flat_map(|it| it.uniforms.iter()).any(|it| it.uses_music())
     }
 
     pub fn update(&mut self, res: &Resource) {
         let t = res.time;
         self.t = t;
         if self.time_range.contains(&t) {
-            for uniform in &mut self.uniforms {
+            for uniform in self.passes.iter_mut().flat_map(|it| it.uniforms.iter_mut()) {
                 uniform.set_time(t);
+                uniform.set_music(&res.music_features);
+            }
+        }
+    }
+
+    fn copy_target(dst: &mut Option<RenderTarget>, src: RenderTarget) -> RenderTarget {
+        let dim = (src.texture.width() as u32, src.texture.height() as u32);
+        if let Some(target) = dst {
+            if (target.texture.width() as u32, target.texture.height() as u32) != dim {
+                target.delete();
+                *dst = None;
             }
         }
+        let target = *dst.get_or_insert_with(|| render_target(dim.0, dim.1));
+        copy_fbo(internal_id(src), internal_id(target), dim);
+        target
     }
 
-    pub fn render(&self, res: &mut Resource) {
-        if !self.time_range.contains(&self.t) {
+    pub fn is_active(&self) -> bool {
+        self.time_range.contains(&self.t)
+    }
+
+    pub fn render(&mut self, res: &mut Resource) {
+        let aspect_ratio = res.aspect_ratio;
+        self.render_on(res.chart_target.as_mut().unwrap(), aspect_ratio);
+    }
+
+    /// Processes the content of `target`, with `aspect_ratio` being the one of the chart.
+    pub fn render_on(&mut self, target: &mut MSRenderTarget, aspect_ratio: f32) {
+        if !self.is_active() {
             return;
         }
         let mut gl = unsafe { get_internal_gl() };
         gl.flush();
 
-        for def in &self.defaults {
-            def.apply(&self.material);
-        }
-        for uniform in &self.uniforms {
-            uniform.apply(&self.material);
+        let input = if self.passes.iter().any(|it| it.uses(|it| matches!(it, EffectTexture::Input))) {
+            Some(Self::copy_target(&mut self.input, target.output()))
+        } else {
+            None
+        };
+        let use_previous = self.passes.iter().any(|it| it.uses(|it| matches!(it, EffectTexture::Previous)));
+        if use_previous && self.previous.is_none() {
+            // nothing rendered before, starts from the current frame
+            Self::copy_target(&mut self.previous, target.output());
         }
-        self.material.set_uniform("time", self.t);
-        let target = res.chart_target.as_mut().unwrap();
-        target.swap();
-        let tex = target.old().texture;
-        self.material.set_texture("screenTexture", tex);
-        let screen_dim = vec2(tex.width(), tex.height());
-        self.material.set_uniform("screenSize", screen_dim);
-        gl.quad_gl.render_pass(Some(target.output().render_pass));
 
         let vp = get_viewport();
-        self.material.set_uniform("UVScale", vec2(vp.2 as _, vp.3 as _) / screen_dim);
+        let top = 1. / if self.global { screen_aspect() } else { aspect_ratio };
+        for pass in &self.passes {
+            gl.flush();
+            for def in &pass.defaults {
+                def.apply(&pass.material);
+            }
+            for uniform in &pass.uniforms {
+                uniform.apply(&pass.material);
+            }
+            pass.material.set_uniform("time", self.t);
+            target.swap();
+            let tex = target.old().texture;
+            pass.material.set_texture("screenTexture", tex);
+            for (name, texture) in &pass.textures {
+                let texture = match texture {
+                    EffectTexture::Image(image) => **image,
+                    EffectTexture::Input => input.unwrap().texture,
+                    EffectTexture::Previous => self.previous.unwrap().texture,
+                };
+                pass.material.set_texture(name, texture);
+            }
+            let screen_dim = vec2(tex.width(), tex.height());
+            pass.material.set_uniform("screenSize", screen_dim);
+            gl.quad_gl.render_pass(Some(target.output().render_pass));
+
+            pass.material.set_uniform("UVScale", vec2(vp.2 as _, vp.3 as _) / screen_dim);
 
-        gl_use_material(self.material);
-        let top = 1. / if self.global { screen_aspect() } else { res.aspect_ratio };
-        draw_rectangle(-1., -top, 2., top * 2., WHITE);
-        gl_use_default_material();
+            gl_use_material(pass.material);
+            draw_rectangle(-1., -top, 2., top * 2., WHITE);
+            gl_use_default_material();
+        }
+        if use_previous {
+            gl.flush();
+            Self::copy_target(&mut self.previous, target.output());
+        }
     }
 }
 
 impl Drop for Effect {
     fn drop(&mut self) {
-        self.material.delete();
+        for target in self.input.iter().chain(self.previous.iter()) {
+            target.delete();
+        }
     }
 }
 
@@ -242,3 +610,13 @@ void main() {
     gl_Position = Projection * Model * vec4(position, 1);
     uv = (texcoord - vec2(0.5)) * UVScale + vec2(0.5);
 }"#;
+
+const PASS_THROUGH_SHADER: &str = r#"#version 100
+precision mediump float;
+
+varying lowp vec2 uv;
+uniform sampler2D screenTexture;
+
+void main() {
+    gl_FragColor = texture2D(screenTexture, uv);
+}"#;
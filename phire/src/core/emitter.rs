@@ -0,0 +1,102 @@
+use super::{Anim, JudgeLine, Point, Resource, Vector};
+use crate::{
+    ext::SafeTexture,
+    particle::{Emitter, EmitterConfig},
+};
+use macroquad::prelude::*;
+use std::ops::Range;
+
+/// Particles emitted by the chart itself during some time range, declared in `extra.json`.
+pub struct ChartEmitter {
+    time_range: Range<f32>,
+    /// Where particles are emitted, in the same coordinates as line positions.
+    /// Relative to the line's transform if attached to one.
+    pub position: Anim<Vec2>,
+    /// Particles emitted per second
+    pub rate: Anim<f32>,
+    /// Line the emitter is attached to
+    pub line: Option<usize>,
+    // in chart coordinates, converted to the emitter's ones on each update
+    direction: Vec2,
+    gravity: Vec2,
+    emitter: Emitter,
+    _texture: Option<SafeTexture>,
+    last_time: f32,
+    last_draw_time: f32,
+    // fraction of a particle carried over to the next update
+    pending: f32,
+}
+
+impl ChartEmitter {
+    pub fn new(
+        time_range: Range<f32>,
+        position: Anim<Vec2>,
+        rate: Anim<f32>,
+        line: Option<usize>,
+        texture: Option<SafeTexture>,
+        config: EmitterConfig,
+    ) -> Self {
+        Self {
+            time_range,
+            position,
+            rate,
+            line,
+            direction: config.initial_direction,
+            gravity: config.gravity,
+            emitter: Emitter::new(EmitterConfig {
+                local_coords: false,
+                emitting: false,
+                texture: texture.as_deref().copied(),
+                ..config
+            }),
+            _texture: texture,
+            last_time: f32::NAN,
+            last_draw_time: f32::NAN,
+            pending: 0.,
+        }
+    }
+
+    pub fn update(&mut self, res: &Resource, lines: &[JudgeLine]) {
+        let t = res.time;
+        let dt = t - std::mem::replace(&mut self.last_time, t);
+        // nothing is emitted when paused or seeking, or with particles turned off
+        if !(0. ..1.).contains(&dt) || !self.time_range.contains(&t) || !res.config.particle {
+            self.pending = 0.;
+            return;
+        }
+        self.rate.set_time(t);
+        self.pending += self.rate.now().max(0.) * dt;
+        let count = self.pending as usize;
+        if count == 0 {
+            return;
+        }
+        self.pending -= count as f32;
+
+        self.position.set_time(t);
+        let pos = self.position.now();
+        let mut pt = Point::new(pos.x, pos.y / res.aspect_ratio);
+        let mut direction = Vector::new(self.direction.x, self.direction.y);
+        let mut gravity = Vector::new(self.gravity.x, self.gravity.y);
+        if let Some(line) = self.line.and_then(|it| lines.get(it)) {
+            let tr = line.now_transform(res, lines);
+            pt = tr.transform_point(&pt);
+            direction = tr.transform_vector(&direction);
+            gravity = tr.transform_vector(&gravity);
+        }
+        // same as hit effects, see `Resource::emit_at_origin`
+        let pt = res.world_to_screen(pt);
+        let flip = if res.config.flip_x() { -1. } else { 1. };
+        let config = &mut self.emitter.config;
+        config.initial_direction = vec2(direction.x * flip, -direction.y);
+        config.gravity = vec2(gravity.x * flip, -gravity.y);
+        self.emitter.emit(vec2(pt.x * flip, -pt.y), count);
+    }
+
+    pub fn draw(&mut self, res: &Resource) {
+        let dt = res.time - std::mem::replace(&mut self.last_draw_time, res.time);
+        if !res.config.particle {
+            return;
+        }
+        self.emitter.draw(vec2(0., 0.), if dt > 0. { dt } else { 0. });
+    }
+}
@@ -1,4 +1,6 @@
-use super::{chart::ChartSettings, object::CtrlObject, Anim, AnimFloat, BpmList, Matrix, Note, Object, Point, RenderConfig, Resource, Vector};
+use super::{
+    chart::ChartSettings, object::CtrlObject, Anim, AnimFloat, AnimatedTexture, BpmList, Matrix, Note, Object, Point, RenderConfig, Resource, Vector,
+};
 use crate::{
     config::Mods,
     ext::{get_viewport, parse_alpha, NotNanExt, SafeTexture},
@@ -9,6 +11,7 @@ use crate::{
 use macroquad::prelude::*;
 use miniquad::{RenderPass, Texture, TextureParams, TextureWrap};
 use nalgebra::Rotation2;
+use rand_pcg::rand_core::RngCore;
 use serde::{Deserialize, Serialize};
 use std::cell::RefCell;
 
@@ -40,46 +43,12 @@ impl UIElement {
     }
 }
 
-pub struct GifFrames {
-    /// time of each frame in milliseconds
-    frames: Vec<(u128, SafeTexture)>,
-    /// milliseconds
-    total_time: u128,
-}
-
-impl GifFrames {
-    pub fn new(frames: Vec<(u128, SafeTexture)>) -> Self {
-        let total_time = frames.iter().map(|(time, _)| *time).sum();
-        Self { frames, total_time }
-    }
-
-    pub fn get_time_frame(&self, time: u128) -> &SafeTexture {
-        let mut time = time % self.total_time;
-        for (t, frame) in &self.frames {
-            if time < *t {
-                return frame;
-            }
-            time -= t;
-        }
-        &self.frames.last().unwrap().1
-    }
-
-    pub fn get_prog_frame(&self, prog: f32) -> &SafeTexture {
-        let time = (prog * self.total_time as f32) as u128;
-        self.get_time_frame(time)
-    }
-
-    pub fn total_time(&self) -> u128 {
-        self.total_time
-    }
-}
-
 #[derive(Default)]
 pub enum JudgeLineKind {
     #[default]
     Normal,
     Texture(SafeTexture, String),
-    TextureGif(Anim<f32>, GifFrames, String),
+    TextureGif(Anim<f32>, AnimatedTexture, String),
     Text(Anim<String>),
     Paint(Anim<f32>, RefCell<(Option<RenderPass>, bool)>),
 }
@@ -152,6 +121,9 @@ pub struct JudgeLine {
     pub anchor: [f32; 2],
 }
 
+// notes are kept within this horizontal range when shifted by the Random mod
+const RANDOM_X_BOUND: f32 = 0.9;
+
 unsafe impl Sync for JudgeLine {}
 unsafe impl Send for JudgeLine {}
 
@@ -207,6 +179,46 @@ impl JudgeLine {
         });
     }
 
+    /// Mirrors and/or shifts the x-positions of all notes on this line. The same
+    /// transform is applied to every note so that hold and drag patterns stay playable.
+    pub fn randomize(&mut self, rng: &mut impl RngCore) {
+        let mirror = rng.next_u32() % 2 == 1;
+        let (mut min, mut max) = (f32::INFINITY, f32::NEG_INFINITY);
+        for note in &self.notes {
+            // layers chained through `next` add up, so the sum of their bounds bounds the position
+            let (mut low, mut high, mut any) = (0., 0., false);
+            let mut layer = Some(&note.object.translation.0);
+            while let Some(anim) = layer {
+                let values = anim.keyframes.iter().map(|kf| if mirror { -kf.value } else { kf.value });
+                let (l, h) = values.fold((f32::INFINITY, f32::NEG_INFINITY), |(l, h), x| (l.min(x), h.max(x)));
+                if l.is_finite() {
+                    low += l;
+                    high += h;
+                    any = true;
+                }
+                layer = anim.next.as_deref();
+            }
+            if any {
+                min = min.min(low);
+                max = max.max(high);
+            }
+        }
+        let (low, high) = (-RANDOM_X_BOUND - min, RANDOM_X_BOUND - max);
+        let shift = if low < high && min.is_finite() {
+            low + (high - low) * (rng.next_u32() as f32 / u32::MAX as f32)
+        } else {
+            0.
+        };
+        for note in &mut self.notes {
+            let translation = &mut note.object.translation.0;
+            if mirror {
+                translation.map_value(|x| -x);
+            }
+            // shifting only the first layer shifts their sum once
+            translation.keyframes.iter_mut().for_each(|kf| kf.value += shift);
+        }
+    }
+
     pub fn fetch_pos(&self, res: &Resource, lines: &[JudgeLine]) -> Vector {
         let current_translation = self.object.now_translation(res);
         if let Some(parent) = self.parent {
@@ -240,14 +252,32 @@ impl JudgeLine {
             res.with_model(self.object.now_scale(), |res| {
                 res.apply_model(|res| match &self.kind {
                     JudgeLineKind::Normal => {
-                        if res.config.render_line {
+                        if res.config.render_line && !res.config.has_mod(Mods::NO_LINE) {
                             let mut color = color.unwrap_or(res.judge_line_color);
                             color.a = parse_alpha(color.a * alpha.max(0.0), res.alpha, 0.15, res.config.chart_debug_line > 0.);
                             if color.a == 0.0 {
                                 return;
                             }
                             let len = res.info.line_length;
-                            draw_line(-len, 0., len, 0., 0.0075, color);
+                            if let Some(line) = &res.res_pack.line {
+                                let (tex, source) = line.get_time_frame((res.time.max(0.) * 1000.) as u128);
+                                // keeps the aspect ratio of the texture, stretched over the whole line
+                                let h = source.h / source.w * len;
+                                draw_texture_ex(
+                                    *tex,
+                                    -len,
+                                    -h,
+                                    color,
+                                    DrawTextureParams {
+                                        source: Some(source),
+                                        dest_size: Some(vec2(len * 2., h * 2.)),
+                                        flip_y: true,
+                                        ..Default::default()
+                                    },
+                                );
+                            } else {
+                                draw_line(-len, 0., len, 0., 0.0075, color);
+                            }
                         }
                     }
                     JudgeLineKind::Texture(texture, _) => {
@@ -279,19 +309,20 @@ impl JudgeLine {
                     JudgeLineKind::TextureGif(anim, frames, _) => {
                         if res.config.render_line_extra {
                             let t = anim.now_opt().unwrap_or(0.0);
-                            let frame = frames.get_prog_frame(t);
+                            let (frame, source) = frames.get_prog_frame(t);
                             let mut color = color.unwrap_or(WHITE);
                             color.a = parse_alpha(alpha.max(0.0), res.alpha, 0.15, res.config.chart_debug_line > 0.);
                             if color.a == 0.0 {
                                 return;
                             }
-                            let hf = vec2(frame.width(), frame.height());
+                            let hf = vec2(source.w, source.h);
                             draw_texture_ex(
-                                **frame,
+                                *frame,
                                 -hf.x / 2.,
                                 -hf.y / 2.,
                                 color,
                                 DrawTextureParams {
+                                    source: Some(source),
                                     dest_size: Some(hf),
                                     flip_y: true,
                                     pivot: Some(Vec2::new(self.anchor[0], -self.anchor[1] + 1.)),
@@ -2,7 +2,7 @@ use super::{
     chart::ChartSettings, BpmList, CtrlObject, JudgeLine, Matrix, Object, Point, Resource, Vector
 };
 use crate::{
-    core::HEIGHT_RATIO, ext::parse_alpha, info::ChartFormat, judge::JudgeStatus, parse::RPE_HEIGHT, ui::Ui
+    config::Mods, core::HEIGHT_RATIO, ext::parse_alpha, info::ChartFormat, judge::JudgeStatus, parse::RPE_HEIGHT, ui::Ui
 };
 
 
@@ -14,6 +14,9 @@ pub use crate::{
 //const HOLD_PARTICLE_INTERVAL: f32 = 0.15;
 const FADEOUT_TIME: f32 = 0.16;
 const BAD_TIME: f32 = 0.5;
+// distance to the line over which notes fade out (Hidden) or in (Sudden)
+const HIDDEN_RANGE: (f32, f32) = (0.25, 0.6);
+const SUDDEN_RANGE: (f32, f32) = (0.6, 0.9);
 
 #[derive(Clone, Debug)]
 pub enum NoteKind {
@@ -116,8 +119,8 @@ fn draw_tex_pts(res: &Resource, texture: Texture2D, order: i8, p: [Point; 4], co
         .push((order, texture.raw_miniquad_texture_handle().gl_internal_id()), vertices);
 }
 
-fn draw_center(res: &Resource, tex: Texture2D, order: i8, scale: f32, color: Color) {
-    let hf = vec2(scale, tex.height() * scale / tex.width());
+fn draw_center(res: &Resource, tex: Texture2D, source: Rect, order: i8, scale: f32, color: Color) {
+    let hf = vec2(scale, tex.height() * source.h * scale / (tex.width() * source.w));
     draw_tex(
         res,
         tex,
@@ -126,6 +129,7 @@ fn draw_center(res: &Resource, tex: Texture2D, order: i8, scale: f32, color: Col
         -hf.y,
         color,
         DrawTextureParams {
+            source: Some(source),
             dest_size: Some(hf * 2.),
             ..Default::default()
         },
@@ -146,7 +150,7 @@ impl Note {
     pub fn update(&mut self, res: &mut Resource, parent_rot: f32, parent_tr: &Matrix, ctrl_obj: &mut CtrlObject, line_height: f32, bpm_list: &mut BpmList, index: usize) {
         self.object.set_time(res.time);
         //let mut _immediate_particle = false;
-        let color = if let JudgeStatus::Hold(perfect, ref mut at, ..) = self.judge {
+        let fx = if let JudgeStatus::Hold(perfect, ref mut at, ..) = self.judge {
             if res.time >= *at {
                 //_immediate_particle = true;
                 let beat = 30. / bpm_list.now_bpm(
@@ -155,9 +159,9 @@ impl Note {
                 //println!("{} {} {}", index, bpm_list.now_bpm(index as f32), beat);
                 *at = res.time + beat / res.config.speed; //HOLD_PARTICLE_INTERVAL
                 Some(if perfect && !res.config.all_good && !res.config.all_bad {
-                    res.res_pack.info.fx_perfect()
+                    (res.res_pack.info.fx_perfect(), false)
                 } else {
-                    res.res_pack.info.fx_good()
+                    (res.res_pack.info.fx_good(), true)
                 })
             } else {
                 None
@@ -166,11 +170,11 @@ impl Note {
             None
         };
 
-        if let Some(color) = color {
+        if let Some((color, good)) = fx {
             self.init_ctrl_obj(ctrl_obj, line_height);
             let rotation = if self.above { 0. } else { 180. };
             res.with_model(parent_tr * self.now_transform(res, ctrl_obj, 0., 0., false, false), |res| {
-                res.emit_at_origin(parent_rot + rotation, color)
+                res.emit_at_origin(parent_rot + rotation, color, good)
             });
         }
     }
@@ -304,9 +308,15 @@ impl Note {
                 color.a *= (base - over) / (fade_out - over);
             }
         }
+        if res.config.has_mod(Mods::HIDDEN) {
+            color.a *= ((base - HIDDEN_RANGE.0) / (HIDDEN_RANGE.1 - HIDDEN_RANGE.0)).clamp(0., 1.);
+        }
+        if res.config.has_mod(Mods::SUDDEN) {
+            color.a *= ((SUDDEN_RANGE.1 - base) / (SUDDEN_RANGE.1 - SUDDEN_RANGE.0)).clamp(0., 1.);
+        }
 
         let scale = (if res.config.render_double_hint && self.multiple_hint {
-            res.res_pack.note_style_mh.click.width() / res.res_pack.note_style.click.width()
+            res.res_pack.note_style_mh.click_width() / res.res_pack.note_style.click_width()
         } else {
             1.0
         }) * res.note_width;
@@ -316,19 +326,20 @@ impl Note {
         } else {
             &res.res_pack.note_style
         };
-        let draw = |res: &mut Resource, tex: Texture2D| {
+        let draw = |res: &mut Resource, tex: Texture2D, frames: u32| {
             let mut color = color;
             if !config.draw_below {
                 color.a *= (self.time - res.time).min(0.) / FADEOUT_TIME + 1.;
             }
+            let source = res.res_pack.info.note_animation.frame(frames, res.time);
             res.with_model(self.now_transform(res, ctrl_obj, base, config.incline_sin, true, true), |res| {
-                draw_center(res, tex, order, scale, color);
+                draw_center(res, tex, source, order, scale, color);
             });
         };
         match self.kind {
             NoteKind::Click => {
                 if self.fake && res.time >= self.time { return };
-                draw(res, *style.click);
+                draw(res, *style.click, style.animation.click);
             }
             NoteKind::Hold { end_time, end_height, end_speed } => {
                 if self.fake && res.time >= end_time { return };
@@ -452,11 +463,11 @@ impl Note {
             }
             NoteKind::Flick => {
                 if self.fake && res.time >= self.time { return };
-                draw(res, *style.flick);
+                draw(res, *style.flick, style.animation.flick);
             }
             NoteKind::Drag => {
                 if self.fake && res.time >= self.time { return };
-                draw(res, *style.drag);
+                draw(res, *style.drag, style.animation.drag);
             }
         }
         if res.config.chart_debug_note > 0. {
@@ -543,14 +554,16 @@ impl BadNote {
         }
         res.with_model(self.matrix, |res| {
             let style = &res.res_pack.note_style;
+            let (tex, frames) = match &self.kind {
+                NoteKind::Click => (*style.click, style.animation.click),
+                NoteKind::Drag => (*style.drag, style.animation.drag),
+                NoteKind::Flick => (*style.flick, style.animation.flick),
+                _ => unreachable!(),
+            };
             draw_center(
                 res,
-                match &self.kind {
-                    NoteKind::Click => *style.click,
-                    NoteKind::Drag => *style.drag,
-                    NoteKind::Flick => *style.flick,
-                    _ => unreachable!(),
-                },
+                tex,
+                style.animation.frame(frames, res.time),
                 self.kind.order(),
                 res.note_width,
                 Color::new(0.423529, 0.262745, 0.262745, (self.time - res.time).max(-1.) / BAD_TIME + 1.),
@@ -8,6 +8,7 @@ pub struct MSRenderTarget {
     dim: (u32, u32),
     fbo: GLuint,
     rbo: GLuint,
+    format: TextureFormat,
     dummy: RenderTarget,
     output: [Option<RenderTarget>; 2],
 }
@@ -29,13 +30,23 @@ pub fn internal_id(target: RenderTarget) -> GLuint {
 
 impl MSRenderTarget {
     pub fn new(dim: (u32, u32), samples: u32) -> Self {
+        Self::with_format(dim, samples, TextureFormat::RGB8)
+    }
+
+    /// Render target with an alpha channel, for layers composited onto another target.
+    pub fn transparent(dim: (u32, u32), samples: u32) -> Self {
+        Self::with_format(dim, samples, TextureFormat::RGBA8)
+    }
+
+    fn with_format(dim: (u32, u32), samples: u32, format: TextureFormat) -> Self {
         let mut fbo = 0;
         let mut rbo = 0;
         unsafe {
             use miniquad::gl::*;
             glGenRenderbuffers(1, &mut rbo as *mut _);
             glBindRenderbuffer(GL_RENDERBUFFER, rbo);
-            glRenderbufferStorageMultisample(GL_RENDERBUFFER, samples as _, GL_RGB8, dim.0 as _, dim.1 as _);
+            let internal_format = if format == TextureFormat::RGBA8 { GL_RGBA8 } else { GL_RGB8 };
+            glRenderbufferStorageMultisample(GL_RENDERBUFFER, samples as _, internal_format, dim.0 as _, dim.1 as _);
             glGenFramebuffers(1, &mut fbo as *mut _);
             glBindFramebuffer(GL_FRAMEBUFFER, fbo);
             glFramebufferRenderbuffer(GL_FRAMEBUFFER, GL_COLOR_ATTACHMENT0, GL_RENDERBUFFER, rbo);
@@ -46,7 +57,7 @@ impl MSRenderTarget {
             miniquad::TextureParams {
                 width: dim.0,
                 height: dim.1,
-                format: TextureFormat::RGB8,
+                format,
                 ..Default::default()
             },
         );
@@ -56,6 +67,7 @@ impl MSRenderTarget {
             dim,
             fbo,
             rbo,
+            format,
             dummy: RenderTarget {
                 texture: Texture2D::from_miniquad_texture(texture),
                 render_pass: dummy_render_pass,
@@ -70,6 +82,10 @@ impl MSRenderTarget {
         }
     }
 
+    pub fn dim(&self) -> (u32, u32) {
+        self.dim
+    }
+
     pub fn blit(&self) {
         copy_fbo(self.fbo, internal_id(self.output[0].unwrap()), self.dim);
     }
@@ -83,7 +99,7 @@ impl MSRenderTarget {
                 miniquad::TextureParams {
                     width: self.dim.0,
                     height: self.dim.1,
-                    format: TextureFormat::RGB8,
+                    format: self.format,
                     ..Default::default()
                 },
             );
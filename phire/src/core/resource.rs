@@ -1,10 +1,13 @@
-use super::{MSRenderTarget, Matrix, Point, NOTE_WIDTH_RATIO_BASE};
+use super::{AnimatedTexture, MSRenderTarget, Matrix, Point, NOTE_WIDTH_RATIO_BASE};
 use crate::{
+    audio::{integrated_loudness, time_stretch_clip, MusicFeatures},
     config::Config,
     ext::{create_audio_manger, nalgebra_to_glm, SafeTexture},
-    fs::FileSystem,
+    fs::{spawn_task, FileSystem},
     info::{ChartFormat, ChartInfo},
     particle::{AtlasConfig, ColorCurve, Emitter, EmitterConfig, ParticleShape},
+    task::Task,
+    ui::FontArc,
 };
 use anyhow::{bail, Context, Result};
 use macroquad::prelude::*;
@@ -12,6 +15,7 @@ use miniquad::{gl::{GLuint, GL_LINEAR}, Texture, TextureWrap};
 use sasa::{AudioClip, AudioManager, Sfx};
 use serde::Deserialize;
 use std::{cell::RefCell, collections::{BTreeMap, HashMap, VecDeque}, ops::DerefMut, path::Path, sync::atomic::AtomicU32};
+use tracing::warn;
 use rand_pcg::{
     Pcg32,
     rand_core::SeedableRng
@@ -57,6 +61,119 @@ fn default_tinted() -> bool {
     true
 }
 
+#[inline]
+fn default_frames() -> u32 {
+    1
+}
+
+#[inline]
+fn default_fps() -> f32 {
+    10.
+}
+
+#[inline]
+fn default_particle_count() -> usize {
+    4
+}
+
+#[inline]
+fn default_particle_speed() -> f32 {
+    4.3
+}
+
+#[inline]
+fn default_particle_randomness() -> f32 {
+    0.3
+}
+
+#[inline]
+fn default_particle_accel() -> f32 {
+    -9.
+}
+
+#[inline]
+fn default_particle_spread() -> f32 {
+    360.
+}
+
+/// Sprite sheet animation of note textures, whose frames are laid out from left to right.
+///
+/// Multi-hit variants share the frame counts of the normal ones. Hold textures are not animated.
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NoteAnimation {
+    #[serde(default = "default_frames")]
+    pub click: u32,
+    #[serde(default = "default_frames")]
+    pub drag: u32,
+    #[serde(default = "default_frames")]
+    pub flick: u32,
+    #[serde(default = "default_fps")]
+    pub fps: f32,
+}
+
+impl Default for NoteAnimation {
+    fn default() -> Self {
+        Self {
+            click: 1,
+            drag: 1,
+            flick: 1,
+            fps: default_fps(),
+        }
+    }
+}
+
+impl NoteAnimation {
+    /// The source rect of the frame shown at `time`, for a sheet of `frames` frames.
+    pub fn frame(&self, frames: u32, time: f32) -> Rect {
+        let frames = frames.max(1);
+        let index = ((time * self.fps).floor() as i64).rem_euclid(frames as i64);
+        let w = 1. / frames as f32;
+        Rect::new(index as f32 * w, 0., w, 1.)
+    }
+}
+
+/// Particles spawned along with hit effects.
+#[derive(Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ParticleInfo {
+    /// Particles spawned per hit
+    #[serde(default = "default_particle_count")]
+    pub count: usize,
+    /// Defaults to `hitFxDuration`
+    #[serde(default)]
+    pub lifetime: Option<f32>,
+    #[serde(default = "default_particle_speed")]
+    pub speed: f32,
+    #[serde(default = "default_particle_randomness")]
+    pub speed_randomness: f32,
+    /// Relative to the default size
+    #[serde(default = "default_scale")]
+    pub size: f32,
+    #[serde(default = "default_particle_randomness")]
+    pub size_randomness: f32,
+    #[serde(default = "default_particle_accel")]
+    pub acceleration: f32,
+    /// In degrees
+    #[serde(default = "default_particle_spread")]
+    pub spread: f32,
+}
+
+impl Default for ParticleInfo {
+    fn default() -> Self {
+        Self {
+            count: default_particle_count(),
+            lifetime: None,
+            speed: default_particle_speed(),
+            speed_randomness: default_particle_randomness(),
+            size: default_scale(),
+            size_randomness: default_particle_randomness(),
+            acceleration: default_particle_accel(),
+            spread: default_particle_spread(),
+        }
+    }
+}
+
 #[allow(dead_code)]
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -65,6 +182,9 @@ pub struct ResPackInfo {
     pub author: String,
 
     pub hit_fx: (u32, u32),
+    /// Atlas of `hit_fx_good.png`, used for Good judgements instead of `hit_fx.png` if present
+    #[serde(default)]
+    pub hit_fx_good: Option<(u32, u32)>,
     #[serde(default = "default_duration")]
     pub hit_fx_duration: f32,
     #[serde(default = "default_scale")]
@@ -75,6 +195,8 @@ pub struct ResPackInfo {
     pub hide_particles: bool,
     #[serde(default)]
     pub circle_particles: bool,
+    #[serde(default)]
+    pub particles: ParticleInfo,
     #[serde(default = "default_tinted")]
     pub hit_fx_tinted: bool,
     #[serde(default = "default_tinted")]
@@ -91,6 +213,9 @@ pub struct ResPackInfo {
     #[serde(default)]
     pub hold_compact: bool,
 
+    #[serde(default)]
+    pub note_animation: NoteAnimation,
+
     #[serde(default = "default_perfect_fx")]
     pub color_perfect_fx: (f32, f32, f32, f32),
     #[serde(default = "default_good_fx")]
@@ -146,6 +271,7 @@ pub struct NoteStyle {
     pub drag: SafeTexture,
     pub hold_body: Option<SafeTexture>,
     pub hold_atlas: (u32, u32),
+    pub animation: NoteAnimation,
 }
 
 impl NoteStyle {
@@ -153,9 +279,23 @@ impl NoteStyle {
         if (self.hold_atlas.0 + self.hold_atlas.1) as f32 >= self.hold.height() {
             bail!("Invalid atlas");
         }
+        for (name, tex, frames) in [
+            ("click", &self.click, self.animation.click),
+            ("drag", &self.drag, self.animation.drag),
+            ("flick", &self.flick, self.animation.flick),
+        ] {
+            if frames == 0 || tex.width() as u32 % frames != 0 {
+                bail!("Width of {name} texture is not a multiple of its {frames} frames");
+            }
+        }
         Ok(())
     }
 
+    /// Width of a single frame of the click texture
+    pub fn click_width(&self) -> f32 {
+        self.click.width() / self.animation.click as f32
+    }
+
     #[inline]
     fn to_uv(&self, t: u32) -> f32 {
         t as f32 / self.hold.height()
@@ -191,6 +331,11 @@ pub struct ResourcePack {
     pub sfx_flick: AudioClip,
     pub endings: [AudioClip; 8],
     pub hit_fx: SafeTexture,
+    pub hit_fx_good: Option<SafeTexture>,
+    /// Drawn in place of normal judge lines if present, may be an APNG
+    pub line: Option<AnimatedTexture>,
+    /// Font of the in-game UI if present
+    pub font: Option<FontArc>,
 }
 
 impl ResourcePack {
@@ -220,6 +365,7 @@ impl ResourcePack {
             drag: load_tex!("drag.png"),
             hold_body: None,
             hold_atlas: info.hold_atlas,
+            animation: info.note_animation,
         };
         note_style.verify()?;
         let mut note_style_mh = NoteStyle {
@@ -229,6 +375,7 @@ impl ResourcePack {
             drag: load_tex!("drag_mh.png"),
             hold_body: None,
             hold_atlas: info.hold_atlas_mh,
+            animation: info.note_animation,
         };
         note_style_mh.verify()?;
         if info.hold_repeat {
@@ -250,6 +397,19 @@ impl ResourcePack {
             get_body(&mut note_style_mh);
         }
         let hit_fx = image::load_from_memory(&fs.load_file("hit_fx.png").await.context("Missing hit_fx.png")?)?.into();
+        let hit_fx_good = if info.hit_fx_good.is_some() {
+            Some(image::load_from_memory(&fs.load_file("hit_fx_good.png").await.context("Missing hit_fx_good.png")?)?.into())
+        } else {
+            None
+        };
+        let line = match fs.load_file("line.png").await {
+            Ok(bytes) => Some(AnimatedTexture::load(bytes).await.context("Invalid line.png")?),
+            Err(_) => None,
+        };
+        let font = match fs.load_file("font.ttf").await {
+            Ok(bytes) => Some(FontArc::try_from_vec(bytes).context("Invalid font.ttf")?),
+            Err(_) => None,
+        };
 
         macro_rules! load_clip {
             ($path:literal) => {
@@ -300,6 +460,9 @@ impl ResourcePack {
                 load_ending!("")
                 ],
             hit_fx,
+            hit_fx_good,
+            line,
+            font,
         })
     }
 }
@@ -307,8 +470,11 @@ impl ResourcePack {
 pub struct ParticleEmitter {
     pub scale: f32,
     pub emitter: Emitter,
+    pub emitter_good: Option<Emitter>,
     pub emitter_square: Emitter,
     pub hide_particles: bool,
+    particle_count: usize,
+    particle_size: f32,
 }
 
 impl ParticleEmitter {
@@ -342,51 +508,72 @@ impl ParticleEmitter {
         } else {
             ParticleShape::Rectangle { aspect_ratio: 1.0 }
         };
+        let particles = &res_pack.info.particles;
         let rng = Pcg32::seed_from_u64(RNG_SEED);
         let emitter_square_config = EmitterConfig {
             max_particles: config.max_particles,
             rng: Some(rng),
             local_coords: false,
-            lifetime: res_pack.info.hit_fx_duration,
+            lifetime: particles.lifetime.unwrap_or(res_pack.info.hit_fx_duration),
             lifetime_randomness: 0.0,
-            initial_direction_spread: 2. * std::f32::consts::PI,
-            size_randomness: 0.3,
+            initial_direction_spread: particles.spread.to_radians(),
+            size_randomness: particles.size_randomness,
             emitting: false,
-            initial_velocity: 4.3 * scale,
-            initial_velocity_randomness: 0.3  * scale,
-            linear_accel: -9.0,
+            initial_velocity: particles.speed * scale,
+            initial_velocity_randomness: particles.speed_randomness * scale,
+            linear_accel: particles.acceleration,
             shape,
             colors_curve,
             ..Default::default()
         };
+        let emitter_good = res_pack.hit_fx_good.as_ref().zip(res_pack.info.hit_fx_good).map(|(texture, atlas)| {
+            Emitter::new(EmitterConfig {
+                texture: Some(**texture),
+                atlas: Some(AtlasConfig::new(atlas.0 as _, atlas.1 as _, ..)),
+                ..emitter_config.clone()
+            })
+        });
         let mut res = Self {
             scale: res_pack.info.hit_fx_scale,
             emitter: Emitter::new(emitter_config),
+            emitter_good,
             emitter_square: Emitter::new(emitter_square_config),
             hide_particles,
+            particle_count: particles.count,
+            particle_size: particles.size,
         };
         res.set_scale(scale);
         res
     }
 
-    pub fn emit_at(&mut self, pt: Vec2, rotation: f32, color: Color) {
-        self.emitter.config.initial_rotation = rotation;
-        self.emitter.config.base_color = color;
-        self.emitter.emit(pt, 1);
+    pub fn emit_at(&mut self, pt: Vec2, rotation: f32, color: Color, good: bool) {
+        let emitter = match &mut self.emitter_good {
+            Some(emitter) if good => emitter,
+            _ => &mut self.emitter,
+        };
+        emitter.config.initial_rotation = rotation;
+        emitter.config.base_color = color;
+        emitter.emit(pt, 1);
         if !self.hide_particles {
             self.emitter_square.config.base_color = color;
-            self.emitter_square.emit(pt, 4);
+            self.emitter_square.emit(pt, self.particle_count);
         }
     }
 
     pub fn draw(&mut self, dt: f32) {
         self.emitter.draw(vec2(0., 0.), dt);
+        if let Some(emitter) = &mut self.emitter_good {
+            emitter.draw(vec2(0., 0.), dt);
+        }
         self.emitter_square.draw(vec2(0., 0.), dt);
     }
 
     pub fn set_scale(&mut self, scale: f32) {
         self.emitter.config.size = self.scale * scale / 5.;
-        self.emitter_square.config.size = self.scale * scale / 44.;
+        if let Some(emitter) = &mut self.emitter_good {
+            emitter.config.size = self.scale * scale / 5.;
+        }
+        self.emitter_square.config.size = self.particle_size * self.scale * scale / 44.;
     }
 }
 
@@ -451,6 +638,10 @@ pub struct Resource {
 
     pub audio: AudioManager,
     pub music: AudioClip,
+    pub music_stretched: Option<(f32, AudioClip)>,
+    /// Stretching in the background, along with the speed it's for
+    music_stretching: Option<(f32, Task<Result<AudioClip>>)>,
+    pub music_gain: f32,
     pub track_length: f32,
     pub sfx_click: Sfx,
     pub sfx_drag: Sfx,
@@ -460,6 +651,8 @@ pub struct Resource {
 
     pub chart_target: Option<MSRenderTarget>,
     pub no_effect: bool,
+    /// Only updated when some effect follows the music
+    pub music_features: MusicFeatures,
 
     pub note_buffer: RefCell<NoteBuffer>,
 
@@ -537,6 +730,11 @@ impl Resource {
         let mut audio = create_audio_manger(&config)?;
         let music = AudioClip::new(fs.load_file(&info.music).await?)?;
         let track_length = music.length();
+        let music_gain = if config.loudness_normalization {
+            config.music_gain(info.loudness.or_else(|| integrated_loudness(music.frames(), music.sample_rate())))
+        } else {
+            1.
+        };
         let buffer_size = Some(BUFFER_SIZE);
         let sfx_click = audio.create_sfx(res_pack.sfx_click.clone(), buffer_size)?;
         let sfx_drag = audio.create_sfx(res_pack.sfx_drag.clone(), buffer_size)?;
@@ -583,6 +781,9 @@ impl Resource {
 
             audio,
             music,
+            music_stretched: None,
+            music_stretching: None,
+            music_gain,
             track_length,
             sfx_click,
             sfx_drag,
@@ -592,6 +793,7 @@ impl Resource {
 
             chart_target: None,
             no_effect,
+            music_features: MusicFeatures::default(),
 
             note_buffer: RefCell::new(NoteBuffer::default()),
 
@@ -604,7 +806,44 @@ impl Resource {
         self.emitter = ParticleEmitter::new(&self.res_pack, self.config.note_scale, self.res_pack.info.hide_particles, Some(self.config.clone()));
     }
 
-    pub fn emit_at_origin(&mut self, rotation: f32, color: Color) {
+    /// Returns the clip to play at the configured speed and its playback rate.
+    ///
+    /// Stretching the whole song takes a while, so it's done in the background. Until then the original clip is played faster
+    /// or slower, changing its pitch as well, and [`Self::poll_music_stretch`] tells when to recreate the music.
+    pub fn music_for_speed(&mut self) -> (AudioClip, f32) {
+        let speed = self.config.speed;
+        if self.config.nightcore || (speed - 1.).abs() < 1e-3 {
+            return (self.music.clone(), speed);
+        }
+        if let Some((_, clip)) = self.music_stretched.as_ref().filter(|(it, _)| (it - speed).abs() < 1e-3) {
+            return (clip.clone(), 1.);
+        }
+        if self.music_stretching.as_ref().map_or(true, |(it, _)| (it - speed).abs() > 1e-3) {
+            let music = self.music.clone();
+            self.music_stretching = Some((speed, Task::new(spawn_task(move || Ok(time_stretch_clip(&music, speed))))));
+        }
+        (self.music.clone(), speed)
+    }
+
+    /// Whether the clip stretched for the configured speed has just become ready.
+    pub fn poll_music_stretch(&mut self) -> bool {
+        let Some(result) = self.music_stretching.as_mut().and_then(|it| it.1.take()) else {
+            return false;
+        };
+        let (speed, _) = self.music_stretching.take().unwrap();
+        match result {
+            Ok(clip) => {
+                self.music_stretched = Some((speed, clip));
+                !self.config.nightcore && (self.config.speed - speed).abs() < 1e-3
+            }
+            Err(err) => {
+                warn!("failed to stretch music: {err:?}");
+                false
+            }
+        }
+    }
+
+    pub fn emit_at_origin(&mut self, rotation: f32, color: Color, good: bool) {
         if !self.config.particle {
             return;
         }
@@ -613,6 +852,7 @@ impl Resource {
             vec2(if self.config.flip_x() { -pt.x } else { pt.x }, -pt.y),
             if self.res_pack.info.hit_fx_rotate { rotation.to_radians() } else { 0. },
             color,
+            good,
         );
     }
 
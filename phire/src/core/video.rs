@@ -2,13 +2,38 @@ use super::{Anim, Resource};
 use crate::ext::{source_of_image, ScaleType};
 use anyhow::{Ok, Result};
 use macroquad::prelude::*;
-use miniquad::{Texture, TextureFormat, TextureParams, TextureWrap};
+use miniquad::{BlendFactor, BlendState, BlendValue, Equation, Texture, TextureFormat, TextureParams, TextureWrap};
 use prpr_avc::AVPixelFormat;
+use serde::Deserialize;
 use std::{cell::RefCell, io::Write};
 use tempfile::NamedTempFile;
 
 thread_local! {
-    static VIDEO_BUFFERS: RefCell<[Vec<u8>; 3]> = RefCell::default();
+    static VIDEO_BUFFERS: RefCell<[Vec<u8>; 4]> = RefCell::default();
+}
+
+/// Where the transparency of a video comes from.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum VideoAlpha {
+    /// Fully opaque
+    #[default]
+    None,
+    /// The alpha plane of the video itself, as in ProRes 4444. Videos decoded without one fail to load, as do VP8/VP9 WebM videos,
+    /// whose alpha the bundled ffmpeg can't decode; use [`VideoAlpha::Packed`] for those.
+    Native,
+    /// The left half holds the colors, and the luma of the right half the alpha
+    Packed,
+}
+
+impl VideoAlpha {
+    fn pix_fmt(self) -> AVPixelFormat {
+        if self == Self::Native {
+            AVPixelFormat::YUVA420P
+        } else {
+            AVPixelFormat::YUV420P
+        }
+    }
 }
 
 pub struct Video {
@@ -19,7 +44,11 @@ pub struct Video {
     tex_y: Texture2D,
     tex_u: Texture2D,
     tex_v: Texture2D,
+    tex_a: Option<Texture2D>,
 
+    /// Lines with a z-index lower than this are drawn below the video, or every line if `None`
+    pub z: Option<i32>,
+    alpha_mode: VideoAlpha,
     start_time: f32,
     scale_type: ScaleType,
     alpha: Anim<f32>,
@@ -43,23 +72,49 @@ fn new_tex(w: u32, h: u32) -> Texture2D {
 }
 
 impl Video {
-    pub fn new(data: Vec<u8>, start_time: f32, scale_type: ScaleType, alpha: Anim<f32>, dim: Anim<f32>) -> Result<Self> {
+    pub fn new(
+        data: Vec<u8>,
+        start_time: f32,
+        scale_type: ScaleType,
+        alpha: Anim<f32>,
+        dim: Anim<f32>,
+        alpha_mode: VideoAlpha,
+        z: Option<i32>,
+    ) -> Result<Self> {
         let mut video_file = NamedTempFile::new()?;
         video_file.write_all(&data)?;
         drop(data);
-        let video = prpr_avc::Video::open(video_file.path().as_os_str().to_str().unwrap(), AVPixelFormat::YUV420P)?;
+        let video = prpr_avc::Video::open(video_file.path().as_os_str().to_str().unwrap(), alpha_mode.pix_fmt())?;
         let frame_delta = video.frame_rate().to_f64_inv();
         let format = video.stream_format();
         let w = format.width as u32;
         let h = format.height as u32;
 
+        let mut textures = vec!["tex_y".to_owned(), "tex_u".to_owned(), "tex_v".to_owned()];
+        if alpha_mode == VideoAlpha::Native {
+            textures.push("tex_a".to_owned());
+        }
         let material = load_material(
             shader::VERTEX,
-            shader::FRAGMENT,
+            match alpha_mode {
+                VideoAlpha::None => shader::FRAGMENT,
+                VideoAlpha::Native => shader::FRAGMENT_ALPHA,
+                VideoAlpha::Packed => shader::FRAGMENT_PACKED,
+            },
             MaterialParams {
-                pipeline_params: PipelineParams::default(),
+                pipeline_params: PipelineParams {
+                    // opaque videos keep overwriting whatever is below, as they always did
+                    color_blend: (alpha_mode != VideoAlpha::None).then(|| {
+                        BlendState::new(
+                            Equation::Add,
+                            BlendFactor::Value(BlendValue::SourceAlpha),
+                            BlendFactor::OneMinusValue(BlendValue::SourceAlpha),
+                        )
+                    }),
+                    ..Default::default()
+                },
                 uniforms: Vec::new(),
-                textures: vec!["tex_y".to_owned(), "tex_u".to_owned(), "tex_v".to_owned()],
+                textures,
             },
         )?;
         let tex_y = new_tex(w, h);
@@ -68,6 +123,10 @@ impl Video {
         material.set_texture("tex_y", tex_y);
         material.set_texture("tex_u", tex_u);
         material.set_texture("tex_v", tex_v);
+        let tex_a = (alpha_mode == VideoAlpha::Native).then(|| new_tex(w, h));
+        if let Some(tex_a) = tex_a {
+            material.set_texture("tex_a", tex_a);
+        }
 
         Ok(Self {
             video,
@@ -77,7 +136,10 @@ impl Video {
             tex_y,
             tex_u,
             tex_v,
+            tex_a,
 
+            z,
+            alpha_mode,
             start_time,
             scale_type,
             alpha,
@@ -102,12 +164,16 @@ impl Video {
                     buf[0].clear();
                     buf[1].clear();
                     buf[2].clear();
+                    buf[3].clear();
                     if self
                         .video
                         .with_frame(|frame| {
                             buf[0].extend_from_slice(frame.data(0));
                             buf[1].extend_from_slice(frame.data_half(1));
                             buf[2].extend_from_slice(frame.data_half(2));
+                            if self.tex_a.is_some() {
+                                buf[3].extend_from_slice(frame.data(3));
+                            }
                         })
                         .is_none()
                     {
@@ -120,6 +186,9 @@ impl Video {
                 self.tex_y.raw_miniquad_texture_handle().update(ctx, &buf[0]);
                 self.tex_u.raw_miniquad_texture_handle().update(ctx, &buf[1]);
                 self.tex_v.raw_miniquad_texture_handle().update(ctx, &buf[2]);
+                if let Some(tex_a) = self.tex_a {
+                    tex_a.raw_miniquad_texture_handle().update(ctx, &buf[3]);
+                }
             });
         }
         Ok(())
@@ -132,7 +201,14 @@ impl Video {
         gl_use_material(self.material);
         let top = 1. / res.aspect_ratio;
         let r = Rect::new(-1., -top, 2., top * 2.);
-        let s = source_of_image(&self.tex_y, r, self.scale_type).unwrap_or_else(|| Rect::new(0., 0., 1., 1.));
+        let s = if self.alpha_mode == VideoAlpha::Packed {
+            // only the left half is shown, and the shader looks up the alpha on the right. Doubling the width of `r` keeps
+            // its aspect ratio relative to the half frame
+            let s = source_of_image(&self.tex_y, Rect::new(r.x, r.y, r.w * 2., r.h), self.scale_type).unwrap_or_else(|| Rect::new(0., 0., 1., 1.));
+            Rect::new(s.x / 2., s.y, s.w / 2., s.h)
+        } else {
+            source_of_image(&self.tex_y, r, self.scale_type).unwrap_or_else(|| Rect::new(0., 0., 1., 1.))
+        };
         let dim = 1. - self.dim.now();
         let color = Color::new(dim, dim, dim, self.alpha.now_opt().unwrap_or(1.));
         let vertices = [
@@ -150,7 +226,7 @@ impl Video {
     pub fn reset(&mut self) -> Result<()> {
         self.next_frame = 0;
         self.ended = false;
-        self.video = prpr_avc::Video::open(self.video_file.path().as_os_str().to_str().unwrap(), AVPixelFormat::YUV420P)?;
+        self.video = prpr_avc::Video::open(self.video_file.path().as_os_str().to_str().unwrap(), self.alpha_mode.pix_fmt())?;
         Ok(())
     }
 }
@@ -173,7 +249,11 @@ void main() {
     uv = texcoord;
 }"#;
 
-    pub const FRAGMENT: &str = r#"#version 100
+    // the fragment shader of a video, with `$alpha` giving the alpha of the pixel at `uv`
+    macro_rules! fragment {
+        ($uniforms:literal, $alpha:literal) => {
+            concat!(
+                r#"#version 100
 precision lowp float;
 
 varying lowp vec4 color;
@@ -182,7 +262,9 @@ varying lowp vec2 uv;
 uniform sampler2D tex_y;
 uniform sampler2D tex_u;
 uniform sampler2D tex_v;
-
+"#,
+                $uniforms,
+                r#"
 void main() {
     vec3 yuv = vec3(
         texture2D(tex_y, uv).a,
@@ -196,6 +278,18 @@ void main() {
         vec3(1.0,   1.772,   0.0  )
     );
 
-    gl_FragColor = vec4(yuv * color_matrix, 1.0) * color;
-}"#;
+    gl_FragColor = vec4(yuv * color_matrix, "#,
+                $alpha,
+                r#") * color;
+}"#
+            )
+        };
+    }
+
+    pub const FRAGMENT: &str = fragment!("", "1.0");
+
+    pub const FRAGMENT_ALPHA: &str = fragment!("uniform sampler2D tex_a;", "texture2D(tex_a, uv).a");
+
+    // alpha is stored as limited range luma in the right half
+    pub const FRAGMENT_PACKED: &str = fragment!("", "clamp(1.1643 * (texture2D(tex_y, uv + vec2(0.5, 0.0)).a - 0.0625), 0.0, 1.0)");
 }
@@ -0,0 +1,338 @@
+//! Structural comparison between two versions of a chart, for reviewing chart updates.
+
+use crate::{
+    core::{AnimFloat, Chart, JudgeLine, Note, NoteKind},
+    info::ChartInfo,
+};
+use anyhow::{bail, Result};
+use serde::Serialize;
+use serde_json::Value;
+use std::fmt;
+
+/// Default tolerance in seconds under which times are considered equal.
+pub const DEFAULT_TOLERANCE: f32 = 1e-3;
+
+/// Farthest in seconds a note may move to be reported as retimed. Notes moved farther are reported as removed and added, as a
+/// distant note of the same kind and position is more likely a different one.
+pub const MAX_RETIME_SHIFT: f32 = 2.;
+
+const VALUE_EPS: f32 = 1e-4;
+
+#[derive(Clone, Debug, Serialize)]
+pub struct NoteDesc {
+    pub time: f32,
+    pub kind: &'static str,
+    pub x: f32,
+    pub above: bool,
+    pub fake: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end_time: Option<f32>,
+}
+
+impl NoteDesc {
+    fn new(note: &Note) -> Self {
+        let (kind, end_time) = match note.kind {
+            NoteKind::Click => ("click", None),
+            NoteKind::Hold { end_time, .. } => ("hold", Some(end_time)),
+            NoteKind::Flick => ("flick", None),
+            NoteKind::Drag => ("drag", None),
+        };
+        Self {
+            time: note.time,
+            kind,
+            x: note.object.translation.0.now(),
+            above: note.above,
+            fake: note.fake,
+            end_time,
+        }
+    }
+
+    // same note, regardless of its time
+    fn same_place(&self, other: &Self) -> bool {
+        self.kind == other.kind && self.above == other.above && self.fake == other.fake && (self.x - other.x).abs() < VALUE_EPS
+    }
+
+    fn duration(&self) -> f32 {
+        self.end_time.map_or(0., |it| it - self.time)
+    }
+}
+
+impl fmt::Display for NoteDesc {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {:.3}s", self.kind, self.time)?;
+        if let Some(end_time) = self.end_time {
+            write!(f, " until {end_time:.3}s")?;
+        }
+        write!(f, " (x {:.3}, {}", self.x, if self.above { "above" } else { "below" })?;
+        if self.fake {
+            write!(f, ", fake")?;
+        }
+        write!(f, ")")
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct RetimedNote {
+    pub note: NoteDesc,
+    pub new_time: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new_end_time: Option<f32>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct EventChange {
+    pub event: &'static str,
+    pub old_keyframes: usize,
+    pub new_keyframes: usize,
+    /// Time of the first keyframe that differs
+    pub first_difference: f32,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct LineDiff {
+    pub line: usize,
+    pub added: Vec<NoteDesc>,
+    pub removed: Vec<NoteDesc>,
+    pub retimed: Vec<RetimedNote>,
+    pub events: Vec<EventChange>,
+}
+
+impl LineDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.retimed.is_empty() && self.events.is_empty()
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct FieldChange {
+    pub field: String,
+    pub old: Value,
+    pub new: Value,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Change<T> {
+    pub old: T,
+    pub new: T,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct ChartDiff {
+    pub info: Vec<FieldChange>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset: Option<Change<f32>>,
+    /// BPM changes as `(time, bpm)` pairs
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bpm: Option<Change<Vec<(f32, f32)>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line_count: Option<Change<usize>>,
+    /// Lines with differences. Lines present in only one of the charts are compared against an empty line.
+    pub lines: Vec<LineDiff>,
+}
+
+impl ChartDiff {
+    pub fn new(old: &Chart, new: &Chart, tolerance: f32) -> Self {
+        let old_bpm = old.bpm_list.borrow().changes();
+        let new_bpm = new.bpm_list.borrow().changes();
+        let bpm_changed = old_bpm.len() != new_bpm.len()
+            || old_bpm
+                .iter()
+                .zip(&new_bpm)
+                .any(|(a, b)| (a.0 - b.0).abs() > tolerance || (a.1 - b.1).abs() > VALUE_EPS);
+        let lines = (0..old.lines.len().max(new.lines.len()))
+            .map(|index| diff_lines(index, old.lines.get(index), new.lines.get(index), tolerance))
+            .filter(|it| !it.is_empty())
+            .collect();
+        Self {
+            info: Vec::new(),
+            offset: ((old.offset - new.offset).abs() > tolerance).then_some(Change {
+                old: old.offset,
+                new: new.offset,
+            }),
+            bpm: bpm_changed.then_some(Change { old: old_bpm, new: new_bpm }),
+            line_count: (old.lines.len() != new.lines.len()).then_some(Change {
+                old: old.lines.len(),
+                new: new.lines.len(),
+            }),
+            lines,
+        }
+    }
+
+    /// Also compares chart infos, whose changed fields are listed in [`ChartDiff::info`].
+    pub fn with_info(mut self, old: &ChartInfo, new: &ChartInfo) -> Result<Self> {
+        self.info = diff_infos(old, new)?;
+        Ok(self)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.info.is_empty() && self.offset.is_none() && self.bpm.is_none() && self.line_count.is_none() && self.lines.is_empty()
+    }
+}
+
+impl fmt::Display for ChartDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "no differences");
+        }
+        if !self.info.is_empty() {
+            writeln!(f, "info:")?;
+            for change in &self.info {
+                writeln!(f, "  {}: {} -> {}", change.field, change.old, change.new)?;
+            }
+        }
+        if let Some(Change { old, new }) = &self.offset {
+            writeln!(f, "offset: {old:.3}s -> {new:.3}s")?;
+        }
+        if let Some(Change { old, new }) = &self.bpm {
+            let list = |v: &[(f32, f32)]| v.iter().map(|(time, bpm)| format!("{bpm} at {time:.3}s")).collect::<Vec<_>>().join(", ");
+            writeln!(f, "bpm: [{}] -> [{}]", list(old), list(new))?;
+        }
+        if let Some(Change { old, new }) = &self.line_count {
+            writeln!(f, "lines: {old} -> {new}")?;
+        }
+        for line in &self.lines {
+            writeln!(f, "line {}:", line.line)?;
+            for note in &line.added {
+                writeln!(f, "  + {note}")?;
+            }
+            for note in &line.removed {
+                writeln!(f, "  - {note}")?;
+            }
+            for note in &line.retimed {
+                write!(f, "  ~ {} moved to {:.3}s", note.note, note.new_time)?;
+                if let Some(end_time) = note.new_end_time {
+                    write!(f, " until {end_time:.3}s")?;
+                }
+                writeln!(f)?;
+            }
+            for event in &line.events {
+                writeln!(
+                    f,
+                    "  {} events: {} -> {} keyframes, first difference at {:.3}s",
+                    event.event, event.old_keyframes, event.new_keyframes, event.first_difference
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+pub fn diff_infos(old: &ChartInfo, new: &ChartInfo) -> Result<Vec<FieldChange>> {
+    let (Value::Object(old), Value::Object(new)) = (serde_json::to_value(old)?, serde_json::to_value(new)?) else {
+        bail!("chart info is not serialized as an object");
+    };
+    Ok(old
+        .into_iter()
+        .filter_map(|(field, old)| {
+            let new = new.get(&field).cloned().unwrap_or_default();
+            (old != new).then_some(FieldChange { field, old, new })
+        })
+        .collect())
+}
+
+fn keyframes(anim: &AnimFloat) -> Vec<(f32, f32)> {
+    let mut res = Vec::new();
+    let mut cur = Some(anim);
+    while let Some(anim) = cur {
+        res.extend(anim.keyframes.iter().map(|it| (it.time, it.value)));
+        cur = anim.next.as_deref();
+    }
+    res
+}
+
+fn events(line: Option<&JudgeLine>) -> Vec<(&'static str, Vec<(f32, f32)>)> {
+    let Some(line) = line else {
+        return Vec::new();
+    };
+    let object = &line.object;
+    [
+        ("alpha", &object.alpha),
+        ("move X", &object.translation.0),
+        ("move Y", &object.translation.1),
+        ("rotate", &object.rotation),
+        ("scale X", &object.scale.0),
+        ("scale Y", &object.scale.1),
+        ("speed", &line.height),
+        ("incline", &line.incline),
+    ]
+    .into_iter()
+    .map(|(name, anim)| (name, keyframes(anim)))
+    .collect()
+}
+
+fn diff_events(old: Option<&JudgeLine>, new: Option<&JudgeLine>, tolerance: f32) -> Vec<EventChange> {
+    let (old, new) = (events(old), events(new));
+    let names = if old.is_empty() { &new } else { &old };
+    names
+        .iter()
+        .enumerate()
+        .filter_map(|(index, (event, _))| {
+            let old = old.get(index).map_or(&[][..], |it| &it.1[..]);
+            let new = new.get(index).map_or(&[][..], |it| &it.1[..]);
+            let first_difference = old
+                .iter()
+                .zip(new)
+                .find(|(a, b)| (a.0 - b.0).abs() > tolerance || (a.1 - b.1).abs() > VALUE_EPS)
+                .map(|(a, b)| a.0.min(b.0))
+                .or_else(|| (old.len() != new.len()).then(|| old.get(new.len()).or(new.get(old.len())).unwrap().0))?;
+            Some(EventChange {
+                event,
+                old_keyframes: old.len(),
+                new_keyframes: new.len(),
+                first_difference,
+            })
+        })
+        .collect()
+}
+
+fn diff_lines(index: usize, old: Option<&JudgeLine>, new: Option<&JudgeLine>, tolerance: f32) -> LineDiff {
+    fn notes(line: Option<&JudgeLine>) -> Vec<NoteDesc> {
+        let mut notes: Vec<_> = line.map_or(&[][..], |it| &it.notes[..]).iter().map(NoteDesc::new).collect();
+        notes.sort_by(|a, b| a.time.total_cmp(&b.time));
+        notes
+    }
+    let old_notes = notes(old);
+    let mut new_notes: Vec<_> = notes(new).into_iter().map(Some).collect();
+
+    let mut unmatched = Vec::new();
+    for note in old_notes {
+        let same = new_notes.iter().position(|it| {
+            it.as_ref().map_or(false, |it| {
+                it.same_place(&note) && (it.time - note.time).abs() <= tolerance && (it.duration() - note.duration()).abs() <= tolerance
+            })
+        });
+        match same {
+            Some(index) => new_notes[index] = None,
+            None => unmatched.push(note),
+        }
+    }
+    // what's left is either moved to another time, or removed
+    let mut removed = Vec::new();
+    let mut retimed = Vec::new();
+    for note in unmatched {
+        let nearest = new_notes
+            .iter()
+            .enumerate()
+            .filter_map(|(index, it)| it.as_ref().filter(|it| it.same_place(&note)).map(|it| (index, (it.time - note.time).abs())))
+            .filter(|(_, shift)| *shift <= MAX_RETIME_SHIFT)
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+        match nearest {
+            Some((index, _)) => {
+                let moved = new_notes[index].take().unwrap();
+                retimed.push(RetimedNote {
+                    note,
+                    new_time: moved.time,
+                    new_end_time: moved.end_time,
+                });
+            }
+            None => removed.push(note),
+        }
+    }
+    LineDiff {
+        line: index,
+        added: new_notes.into_iter().flatten().collect(),
+        removed,
+        retimed,
+        events: diff_events(old, new, tolerance),
+    }
+}
@@ -15,6 +15,11 @@ impl Dir {
         Ok(Self(path))
     }
 
+    #[inline]
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+
     pub fn join(&self, path: impl AsRef<Path>) -> Result<PathBuf> {
         let path = path.as_ref();
         let mut res = self.0.clone();
@@ -22,6 +22,9 @@ use std::{
 use tracing::{debug, info_span};
 use lazy_static::lazy_static;
 
+mod blur;
+pub use blur::BlurredTexture;
+
 pub type LocalTask<R> = Option<Pin<Box<dyn Future<Output = R>>>>;
 
 pub trait JoinToString {
@@ -153,6 +156,8 @@ impl From<DynamicImage> for SafeTexture {
 }
 
 pub static BLACK_TEXTURE: Lazy<SafeTexture> = Lazy::new(|| Texture2D::from_rgba8(1, 1, &[0, 0, 0, 255]).into());
+/// Stands in for textures loaded later on. It is never dropped, so it's safe to use without a GL context.
+pub static EMPTY_TEXTURE: Lazy<SafeTexture> = Lazy::new(|| Texture2D::empty().into());
 
 pub fn nalgebra_to_glm(mat: &Matrix) -> Mat4 {
     /*
@@ -652,33 +657,8 @@ pub fn round_to_step(value: f32, step: f32) -> f32 {
     (aligned * factor).round() / factor
 }
 
-pub fn blur_image(image: DynamicImage, blur: f32) -> Result<SafeTexture> {
-    let (w, h) = (image.width(), image.height());
-    let size = w as usize * h as usize;
-    let mut blurred_rgb = image.to_rgb8();
-    let mut vec = unsafe { Vec::from_raw_parts(std::mem::transmute(blurred_rgb.as_mut_ptr()), size, size) };
-    fastblur::gaussian_blur(&mut vec, w as _, h as _, blur);
-    std::mem::forget(vec);
-    let mut blurred = Vec::with_capacity(size * 4);
-    for input in blurred_rgb.chunks_exact(3) {
-        //blurred.extend_from_slice(input);
-        blurred.push(input[0]);
-        blurred.push(input[1]);
-        blurred.push(input[2]);
-        blurred.push(255);
-    }
-    Ok((
-        Texture2D::from_image(&Image {
-            width: w as _,
-            height: h as _,
-            bytes: blurred,
-        })
-    ).into())
-
-}
-
-
-mod shader {
+// the default shaders of macroquad
+pub(crate) mod shader {
     pub const VERTEX: &str = r#"#version 100
 attribute vec3 position;
 attribute vec2 texcoord;
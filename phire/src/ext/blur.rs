@@ -0,0 +1,185 @@
+use super::SafeTexture;
+use crate::core::internal_id;
+use macroquad::prelude::*;
+use once_cell::sync::Lazy;
+
+/// Largest standard deviation, in pixels of the render target, blurred at full resolution. Stronger blurs are rendered at a lower
+/// resolution instead, halving it each time, which keeps the kernel small and barely shows once blurred.
+const MAX_SIGMA: f32 = 8.;
+
+static BLUR_MATERIAL: Lazy<Material> = Lazy::new(|| {
+    load_material(
+        shader::VERTEX,
+        shader::FRAGMENT,
+        MaterialParams {
+            uniforms: vec![
+                ("direction".to_owned(), UniformType::Float2),
+                ("sigma".to_owned(), UniformType::Float1),
+                ("dim".to_owned(), UniformType::Float1),
+            ],
+            textures: vec!["source".to_owned()],
+            ..Default::default()
+        },
+    )
+    .unwrap()
+});
+
+/// A texture blurred and dimmed on the GPU.
+///
+/// The result is cached in a render target and only redrawn when the strength or dim changes, so both can be animated cheaply.
+pub struct BlurredTexture {
+    source: SafeTexture,
+    /// Blur and dim of the cached result
+    rendered: Option<(f32, f32)>,
+    /// The horizontal pass renders into the first, and the vertical one into the second
+    targets: Option<[RenderTarget; 2]>,
+}
+
+impl BlurredTexture {
+    pub fn new(source: SafeTexture) -> Self {
+        Self {
+            source,
+            rendered: None,
+            targets: None,
+        }
+    }
+
+    pub fn source(&self) -> &SafeTexture {
+        &self.source
+    }
+
+    pub fn set_source(&mut self, source: SafeTexture) {
+        if self.source != source {
+            self.source = source;
+            self.rendered = None;
+        }
+    }
+
+    /// Returns the source blurred by `blur`, on the same scale as the `bg_blurriness` option, and darkened by `dim`.
+    pub fn get(&mut self, blur: f32, dim: f32) -> Texture2D {
+        if blur <= 0. && dim <= 0. {
+            return *self.source;
+        }
+        if self.rendered != Some((blur, dim)) {
+            self.render(blur, dim);
+            self.rendered = Some((blur, dim));
+        }
+        self.targets.unwrap()[1].texture
+    }
+
+    /// Renders once and keeps only the result, for backgrounds that never change.
+    pub fn into_texture(mut self, blur: f32, dim: f32) -> SafeTexture {
+        if blur <= 0. && dim <= 0. {
+            return self.source.clone();
+        }
+        self.render(blur, dim);
+        let [temp, output] = self.targets.take().unwrap();
+        temp.delete();
+        // deleting the whole render target would take the texture with it
+        unsafe {
+            miniquad::gl::glDeleteFramebuffers(1, &internal_id(output));
+        }
+        output.texture.into()
+    }
+
+    fn render(&mut self, blur: f32, dim: f32) {
+        let scale = (blur / MAX_SIGMA).max(1.).log2().ceil().exp2();
+        let size = (((self.source.width() / scale).ceil() as u32).max(1), ((self.source.height() / scale).ceil() as u32).max(1));
+        if let Some(targets) = self.targets {
+            if (targets[0].texture.width() as u32, targets[0].texture.height() as u32) != size {
+                targets.iter().for_each(|it| it.delete());
+                self.targets = None;
+            }
+        }
+        let targets = *self.targets.get_or_insert_with(|| {
+            [(); 2].map(|_| {
+                let target = render_target(size.0, size.1);
+                target.texture.set_filter(FilterMode::Linear);
+                target
+            })
+        });
+
+        let mut gl = unsafe { get_internal_gl() };
+        gl.flush();
+        let old_pass = gl.quad_gl.get_active_render_pass();
+        let old_viewport = gl.quad_gl.get_viewport();
+        gl.quad_gl.viewport(None);
+
+        let material = *BLUR_MATERIAL;
+        material.set_uniform("sigma", blur / scale);
+        gl_use_material(material);
+        // the shader maps positions straight to clip space, keeping the first row of the source the first row of the targets
+        let vertices = [
+            Vertex::new(-1., -1., 0., 0., 0., WHITE),
+            Vertex::new(1., -1., 0., 1., 0., WHITE),
+            Vertex::new(-1., 1., 0., 0., 1., WHITE),
+            Vertex::new(1., 1., 0., 1., 1., WHITE),
+        ];
+        let passes = [
+            (*self.source, targets[0], vec2(1. / size.0 as f32, 0.), 0.),
+            (targets[0].texture, targets[1], vec2(0., 1. / size.1 as f32), dim),
+        ];
+        for (input, output, direction, dim) in passes {
+            material.set_texture("source", input);
+            material.set_uniform("direction", direction);
+            material.set_uniform("dim", dim);
+            gl.quad_gl.render_pass(Some(output.render_pass));
+            gl.quad_gl.draw_mode(DrawMode::Triangles);
+            gl.quad_gl.geometry(&vertices, &[0, 1, 2, 1, 2, 3]);
+            // uniforms are only read when flushing
+            gl.flush();
+        }
+        gl_use_default_material();
+
+        gl.quad_gl.render_pass(old_pass);
+        gl.quad_gl.viewport(old_viewport);
+    }
+}
+
+impl Drop for BlurredTexture {
+    fn drop(&mut self) {
+        for target in self.targets.iter().flatten() {
+            target.delete();
+        }
+    }
+}
+
+mod shader {
+    pub const VERTEX: &str = r#"#version 100
+attribute vec3 position;
+attribute vec2 texcoord;
+
+varying vec2 uv;
+
+void main() {
+    gl_Position = vec4(position, 1);
+    uv = texcoord;
+}"#;
+
+    // one direction of a separable Gaussian, cut off at three standard deviations
+    pub const FRAGMENT: &str = r#"#version 100
+precision mediump float;
+
+varying vec2 uv;
+
+uniform sampler2D source;
+uniform vec2 direction;
+uniform float sigma;
+uniform float dim;
+
+void main() {
+    float radius = ceil(sigma * 3.0);
+    vec3 sum = vec3(0.0);
+    float total = 0.0;
+    for (int i = -24; i <= 24; ++i) {
+        float x = float(i);
+        if (abs(x) > radius) {
+            continue;
+        }
+        float weight = sigma > 0.0 ? exp(-x * x / (2.0 * sigma * sigma)) : 1.0;
+        sum += texture2D(source, uv + direction * x).rgb * weight;
+        total += weight;
+    }
+    gl_FragColor = vec4(sum / total * (1.0 - dim), 1.0);
+}"#;
+}
@@ -11,8 +11,14 @@ use std::{
     collections::HashMap,
     fs,
     io::{Cursor, Read, Seek, Write},
-    path::Path,
-    sync::{Arc, Mutex},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, SystemTime},
 };
 use tracing::warn;
 use zip::{write::FileOptions, CompressionMethod, ZipArchive, ZipWriter};
@@ -304,18 +310,50 @@ fn info_from_txt(text: &str) -> Result<ChartInfo> {
     info_from_kv(kvs.into_iter(), false)
 }
 
-fn info_from_csv(text: &str) -> Result<ChartInfo> {
+fn infos_from_csv(text: &str) -> Result<Vec<ChartInfo>> {
     let mut reader = csv::ReaderBuilder::new().flexible(true).from_reader(Cursor::new(text));
     // shitty design
     let headers = reader.headers()?.iter().map(str::to_owned).collect::<Vec<_>>();
-    let record = reader.into_records().last().ok_or_else(|| anyhow!("expected csv records"))??; // ??
-    info_from_kv(
-        headers
-            .iter()
-            .zip(record.into_iter())
-            .map(|(key, value)| (key.as_str(), value.to_owned())),
-        true,
-    )
+    let infos = reader
+        .into_records()
+        .map(|record| {
+            let record = record?;
+            info_from_kv(
+                headers
+                    .iter()
+                    .zip(record.into_iter())
+                    .map(|(key, value)| (key.as_str(), value.to_owned())),
+                true,
+            )
+        })
+        .collect::<Result<Vec<_>>>()?;
+    if infos.is_empty() {
+        bail!("expected csv records");
+    }
+    Ok(infos)
+}
+
+/// Parses `info.yml`, which holds either a single chart or a list of difficulties sharing the same package.
+pub fn infos_from_yaml(text: &str) -> Result<Vec<ChartInfo>> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Infos {
+        Single(Box<ChartInfo>),
+        Multiple(Vec<ChartInfo>),
+    }
+    Ok(match serde_yaml::from_str(text)? {
+        Infos::Single(info) => vec![*info],
+        Infos::Multiple(infos) if infos.is_empty() => bail!("expected at least one chart"),
+        Infos::Multiple(infos) => infos,
+    })
+}
+
+/// Serializes infos into `info.yml`, keeping the single-chart format whenever possible.
+pub fn infos_to_yaml(infos: &[ChartInfo]) -> Result<String> {
+    Ok(match infos {
+        [info] => serde_yaml::to_string(info)?,
+        _ => serde_yaml::to_string(infos)?,
+    })
 }
 
 pub async fn fix_info(fs: &mut dyn FileSystem, info: &mut ChartInfo) -> Result<()> {
@@ -412,22 +450,110 @@ fn bytes_to_text_auto(data: &[u8]) -> String {
     s.into_owned()
 }
 
-pub async fn load_info(fs: &mut dyn FileSystem) -> Result<ChartInfo> {
-    let info = if let Ok(bytes) = fs.load_file(":info").await {
-        serde_yaml::from_str(&bytes_to_text_auto(&bytes))?
+/// Loads infos of all difficulties in the package, the default one first.
+///
+/// That is the first one listed, except for `info.csv` where it has always been the last row, so rows are read starting from there.
+pub async fn load_infos(fs: &mut dyn FileSystem) -> Result<Vec<ChartInfo>> {
+    Ok(if let Ok(bytes) = fs.load_file(":info").await {
+        infos_from_yaml(&bytes_to_text_auto(&bytes))?
     } else if let Ok(bytes) = fs.load_file("info.yml").await {
-        serde_yaml::from_str(&bytes_to_text_auto(&bytes))?
+        infos_from_yaml(&bytes_to_text_auto(&bytes))?
     } else if let Ok(bytes) = fs.load_file("info.txt").await {
-        info_from_txt(&bytes_to_text_auto(&bytes))?
+        vec![info_from_txt(&bytes_to_text_auto(&bytes))?]
     } else if let Ok(bytes) = fs.load_file("info.csv").await {
-        info_from_csv(&bytes_to_text_auto(&bytes))?
+        let mut infos = infos_from_csv(&bytes_to_text_auto(&bytes))?;
+        infos.rotate_right(1);
+        infos
     } else {
         warn!("none of info.yml, info.txt and info.csv is found, inferring");
         let mut info = ChartInfo::default();
         fix_info(fs, &mut info).await?;
-        info
-    };
-    Ok(info)
+        vec![info]
+    })
+}
+
+/// Loads the info of the default difficulty, see [`load_infos`].
+pub async fn load_info(fs: &mut dyn FileSystem) -> Result<ChartInfo> {
+    Ok(load_infos(fs).await?.swap_remove(0))
+}
+
+/// Watches the folder behind an [`ExternalFileSystem`] by polling modification times of all files in it on a separate thread.
+pub struct FolderWatcher {
+    root: PathBuf,
+    rx: Receiver<Vec<PathBuf>>,
+    dropped: Arc<AtomicBool>,
+}
+
+impl FolderWatcher {
+    /// Returns `None` if the file system is not backed by a folder. The folder is rescanned every `interval`.
+    pub fn new(fs: &mut dyn FileSystem, interval: Duration) -> Option<Self> {
+        let root = fs.as_any().downcast_ref::<ExternalFileSystem>()?.0.path().to_owned();
+        let (tx, rx) = mpsc::channel();
+        let dropped = Arc::new(AtomicBool::default());
+        thread::spawn({
+            let root = root.clone();
+            let dropped = Arc::clone(&dropped);
+            move || {
+                let mut stamps = Self::scan(&root);
+                while !dropped.load(Ordering::Relaxed) {
+                    thread::sleep(interval);
+                    let new_stamps = Self::scan(&root);
+                    let changes: Vec<_> = new_stamps
+                        .iter()
+                        .filter(|(path, time)| stamps.get(*path) != Some(time))
+                        .map(|it| it.0)
+                        .chain(stamps.keys().filter(|it| !new_stamps.contains_key(*it)))
+                        .filter_map(|it| it.strip_prefix(&root).ok().map(Path::to_path_buf))
+                        .collect();
+                    stamps = new_stamps;
+                    if !changes.is_empty() && tx.send(changes).is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+        Some(Self { root, rx, dropped })
+    }
+
+    fn scan(root: &Path) -> HashMap<PathBuf, SystemTime> {
+        fn walk(dir: &Path, stamps: &mut HashMap<PathBuf, SystemTime>) {
+            let Ok(entries) = fs::read_dir(dir) else { return };
+            for entry in entries.flatten() {
+                let Ok(meta) = entry.metadata() else { continue };
+                if meta.is_dir() {
+                    walk(&entry.path(), stamps);
+                } else if let Ok(time) = meta.modified() {
+                    stamps.insert(entry.path(), time);
+                }
+            }
+        }
+        let mut stamps = HashMap::new();
+        walk(root, &mut stamps);
+        stamps
+    }
+
+    /// Returns whether any file has been added, removed or modified since the last call.
+    pub fn changed(&mut self) -> bool {
+        !self.changes().is_empty()
+    }
+
+    /// Returns paths relative to the folder of files added, removed or modified since the last call, without waiting for a scan.
+    pub fn changes(&mut self) -> Vec<PathBuf> {
+        let mut changes: Vec<_> = self.rx.try_iter().flatten().collect();
+        changes.sort();
+        changes.dedup();
+        changes
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+}
+
+impl Drop for FolderWatcher {
+    fn drop(&mut self) {
+        self.dropped.store(true, Ordering::Relaxed);
+    }
 }
 
 pub fn fs_from_file(path: &Path) -> Result<Box<dyn FileSystem + Send + Sync + 'static>> {
@@ -49,6 +49,10 @@ pub struct ChartInfo {
     pub created: Option<DateTime<Utc>>,
     pub updated: Option<DateTime<Utc>>,
     pub chart_updated: Option<DateTime<Utc>>,
+
+    // integrated loudness of the music in LUFS, measured and cached by the client
+    #[serde(skip)]
+    pub loudness: Option<f32>,
 }
 
 impl Default for ChartInfo {
@@ -88,6 +92,8 @@ impl Default for ChartInfo {
             created: None,
             updated: None,
             chart_updated: None,
+
+            loudness: None,
         }
     }
 }
@@ -1,5 +1,5 @@
 use crate::{
-    config::Config,
+    config::{Config, Mods},
     core::{BadNote, Chart, Note, NoteKind, Point, Resource, Vector, NOTE_WIDTH_RATIO_BASE},
     ext::{get_viewport, NotNanExt},
 };
@@ -20,6 +20,8 @@ pub const LIMIT_BAD: f32 = 0.22;
 pub const UP_TOLERANCE: f32 = 0.05;
 pub const DIST_FACTOR: f32 = 0.2;
 const LATE_OFFSET: f32 = 0.13;
+// life gauge change for Perfect, Good, Bad and Miss
+const HEALTH_DELTA: [f32; 4] = [0.01, 0.002, -0.08, -0.12];
 
 pub fn play_sfx(sfx: &mut Sfx, config: &Config) {
     if config.volume_sfx <= 1e-2 {
@@ -257,6 +259,8 @@ pub struct Judge {
 
     pub(crate) inner: JudgeInner,
     pub judgements: RefCell<Vec<(f32, u32, u32, Result<Judgement, bool>)>>,
+
+    pub health: f32,
 }
 
 static SUBSCRIBER_ID: Lazy<usize> = Lazy::new(register_input_subscriber);
@@ -284,6 +288,8 @@ impl Judge {
 
             inner: JudgeInner::new(chart.lines.iter().map(|it| it.notes.iter().filter(|it| !it.fake).count() as u32).sum()),
             judgements: RefCell::new(Vec::new()),
+
+            health: 1.,
         }
     }
 
@@ -292,11 +298,35 @@ impl Judge {
         self.trackers.clear();
         self.inner.reset();
         self.judgements.borrow_mut().clear();
+        self.health = 1.;
+    }
+
+    /// Marks notes before `time` as judged without counting them, for playing from partway through the chart.
+    pub fn skip_before(&mut self, chart: &mut Chart, time: f32) {
+        for (line, (idx, st)) in chart.lines.iter_mut().zip(self.notes.iter_mut()) {
+            while let Some(id) = idx.get(*st) {
+                let note = &mut line.notes[*id as usize];
+                if note.time >= time {
+                    break;
+                }
+                note.judge = JudgeStatus::Judged;
+                *st += 1;
+            }
+        }
     }
 
     pub fn commit(&mut self, t: f32, what: Judgement, line_id: u32, note_id: u32, diff: f32) {
         self.judgements.borrow_mut().push((t, line_id, note_id, Ok(what)));
         self.inner.commit(what, diff);
+        self.health = (self.health + HEALTH_DELTA[what as usize]).clamp(0., 1.);
+    }
+
+    /// Whether the fail conditions enabled by `mods` have been met.
+    pub fn failed(&self, mods: Mods) -> bool {
+        let counts = self.counts();
+        (mods.contains(Mods::SUDDEN_DEATH) && counts[2] + counts[3] != 0)
+            || (mods.contains(Mods::PERFECT_ONLY) && counts[1] + counts[2] + counts[3] != 0)
+            || (mods.contains(Mods::LIFE_GAUGE) && self.health <= 0.)
     }
 
     #[inline]
@@ -814,11 +844,15 @@ impl Judge {
             }
             if match judgement {
                 Judgement::Perfect => {
-                    res.with_model(line_tr * note.object.now(res), |res| res.emit_at_origin(note.rotation(line), res.res_pack.info.fx_perfect()));
+                    res.with_model(line_tr * note.object.now(res), |res| {
+                        res.emit_at_origin(note.rotation(line), res.res_pack.info.fx_perfect(), false)
+                    });
                     true
                 }
                 Judgement::Good => {
-                    res.with_model(line_tr * note.object.now(res), |res| res.emit_at_origin(note.rotation(line), res.res_pack.info.fx_good()));
+                    res.with_model(line_tr * note.object.now(res), |res| {
+                        res.emit_at_origin(note.rotation(line), res.res_pack.info.fx_good(), true)
+                    });
                     true
                 }
                 Judgement::Bad => {
@@ -925,7 +959,7 @@ impl Judge {
                 NoteKind::Click => {
                     self.commit(t, judge_type, line_id as _, id, 0.);
                     res.with_model(line.now_transform(res, &chart.lines) * note_transform, |res| {
-                        res.emit_at_origin(line.notes[id as usize].rotation(line), fx_color)
+                        res.emit_at_origin(line.notes[id as usize].rotation(line), fx_color, matches!(judge_type, Judgement::Good))
         
                     });
                 }
@@ -935,7 +969,7 @@ impl Judge {
                 _ => {
                     self.commit(t, Judgement::Perfect, line_id as _, id, 0.);
                     res.with_model(line.now_transform(res, &chart.lines) * note_transform, |res| {
-                        res.emit_at_origin(line.notes[id as usize].rotation(line), res.res_pack.info.fx_perfect())
+                        res.emit_at_origin(line.notes[id as usize].rotation(line), res.res_pack.info.fx_perfect(), false)
         
                     });
                 },
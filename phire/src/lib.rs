@@ -1,6 +1,9 @@
+pub mod audio;
 pub mod bin;
+pub mod chart_ops;
 pub mod config;
 pub mod core;
+pub mod diff;
 pub mod dir;
 pub mod ext;
 pub mod fs;
@@ -9,12 +12,17 @@ pub mod judge;
 pub mod l10n;
 pub mod parse;
 pub mod particle;
+pub mod respack;
+pub mod rks;
 pub mod scene;
 pub mod task;
 pub mod time;
 pub mod ui;
 pub mod gyro;
 
+#[cfg(not(target_arch = "wasm32"))]
+pub mod snapshot;
+
 #[cfg(feature = "log")]
 pub mod log;
 
@@ -2,12 +2,17 @@ crate::tl_file!("parser" ptl);
 
 use super::RPE_TWEEN_MAP;
 use crate::{
-    core::{Anim, BpmList, ChartExtra, ClampedTween, Effect, Keyframe, StaticTween, Triple, Tweenable, Uniform, Video, EPS},
-    ext::ScaleType,
+    core::{
+        Anim, BpmList, ChartEmitter, ChartExtra, ClampedTween, Effect, EffectLayer, EffectPass, EffectTexture, IntUniform, Keyframe, LayerEffect,
+        MusicInput, MusicUniform, StaticTween, Triple, Tweenable, Uniform, Video, VideoAlpha, EPS,
+    },
+    ext::{SafeTexture, ScaleType},
     fs::FileSystem,
+    particle::{AtlasConfig, BlendMode, ColorCurve, EmissionShape, EmitterConfig},
+    scene::show_error,
 };
 use anyhow::{Context, Result};
-use macroquad::prelude::{Color, Vec2};
+use macroquad::prelude::{vec2, Color, Mat4, Vec2};
 use serde::Deserialize;
 use std::{collections::HashMap, rc::Rc};
 
@@ -105,20 +110,42 @@ impl From<BpmForm> for BpmList {
 #[derive(Deserialize)]
 #[serde(untagged)]
 enum Variable {
+    Bool(bool),
     Float(ExtAnim<f32>),
     Vec2(ExtAnim<(f32, f32)>),
     Color(ExtAnim<[u8; 4]>),
+    Mat4([f32; 16]),
+    // `@` followed by the name of a music input
+    Music(String),
+}
+
+#[derive(Deserialize)]
+struct ExtPass {
+    shader: String,
+    #[serde(default)]
+    vars: HashMap<String, Variable>,
+    // sampler name -> chart image path, `@input` or `@previous`
+    #[serde(default)]
+    textures: HashMap<String, String>,
 }
 
 #[derive(Deserialize)]
 struct ExtEffect {
     start: Triple,
     end: Triple,
-    shader: String,
+    #[serde(flatten)]
+    pass: ExtPass,
+    // applied after the first pass, in order
     #[serde(default)]
-    vars: HashMap<String, Variable>,
+    passes: Vec<ExtPass>,
     #[serde(default)]
     global: bool,
+    // only applies to this line
+    #[serde(default)]
+    line: Option<usize>,
+    // only applies to lines with z index in the range, inclusive
+    #[serde(default)]
+    layer: Option<(i32, i32)>,
 }
 
 #[derive(Deserialize)]
@@ -132,6 +159,75 @@ struct ExtVideo {
     alpha: ExtAnim<f32>,
     #[serde(default)]
     dim: ExtAnim<f32>,
+    #[serde(default, rename = "alphaMode")]
+    alpha_mode: VideoAlpha,
+    #[serde(default)]
+    z: Option<i32>,
+}
+
+fn emitter_lifetime() -> f32 {
+    2.
+}
+
+fn emitter_size() -> f32 {
+    0.01
+}
+
+fn emitter_max_particles() -> usize {
+    1000
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+enum ExtEmissionShape {
+    Point,
+    Rect(f32, f32),
+    Circle(f32),
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ExtEmitter {
+    start: Triple,
+    end: Triple,
+    #[serde(default)]
+    position: ExtAnim<(f32, f32)>,
+    rate: ExtAnim<f32>,
+    #[serde(default)]
+    line: Option<usize>,
+    #[serde(default)]
+    texture: Option<String>,
+    // rows and columns of the texture, animated over the lifetime of a particle
+    #[serde(default)]
+    atlas: Option<(u16, u16)>,
+    #[serde(default = "emitter_max_particles")]
+    max_particles: usize,
+    #[serde(default = "emitter_lifetime")]
+    lifetime: f32,
+    #[serde(default)]
+    lifetime_randomness: f32,
+    #[serde(default = "emitter_size")]
+    size: f32,
+    #[serde(default)]
+    size_randomness: f32,
+    #[serde(default)]
+    shape: Option<ExtEmissionShape>,
+    #[serde(default)]
+    direction: Option<(f32, f32)>,
+    // in degrees
+    #[serde(default)]
+    spread: f32,
+    #[serde(default)]
+    speed: f32,
+    #[serde(default)]
+    speed_randomness: f32,
+    #[serde(default)]
+    gravity: (f32, f32),
+    // at the start, the middle and the end of a particle's lifetime
+    #[serde(default)]
+    colors: Option<[[u8; 4]; 3]>,
+    #[serde(default)]
+    additive: bool,
 }
 
 #[derive(Deserialize)]
@@ -142,39 +238,165 @@ struct Extra {
     effects: Vec<ExtEffect>,
     #[serde(default)]
     videos: Vec<ExtVideo>,
+    #[serde(default)]
+    emitters: Vec<ExtEmitter>,
 }
 
-async fn parse_effect(r: &mut BpmList, rpe: ExtEffect, fs: &mut dyn FileSystem) -> Result<Effect> {
-    let range = r.time(&rpe.start)..r.time(&rpe.end);
-    let vars = rpe
+async fn load_texture(path: String, fs: &mut dyn FileSystem, texture_cache: &mut HashMap<String, SafeTexture>) -> Result<SafeTexture> {
+    if let Some(texture) = texture_cache.get(&path) {
+        return Ok(texture.clone());
+    }
+    let texture = SafeTexture::from(
+        image::load_from_memory(
+            &fs.load_file(&path)
+                .await
+                .with_context(|| ptl!("effect-texture-load-failed", "path" => path.clone()))?,
+        )
+        .with_context(|| ptl!("effect-texture-load-failed", "path" => path.clone()))?,
+    );
+    texture_cache.insert(path, texture.clone());
+    Ok(texture)
+}
+
+// returns the source, along with its path if loaded from the chart folder
+async fn load_shader(shader: &str, fs: &mut dyn FileSystem) -> Result<(String, Option<String>)> {
+    Ok(if let Some(path) = shader.strip_prefix('/') {
+        if let Ok(file) = fs.load_file(path).await {
+            (String::from_utf8(file).with_context(|| ptl!("shader-load-failed", "path" => path))?, Some(path.to_owned()))
+        } else if let Some(rpe) = Effect::get_rpe_preset(path.strip_suffix(".glsl").unwrap_or_default()) {
+            (rpe.to_owned(), None)
+        } else {
+            return Err(ptl!(err "shader-load-failed", "path" => shader));
+        }
+    } else {
+        (
+            Effect::get_preset(shader)
+                .ok_or_else(|| ptl!(err "shader-not-found", "shader" => shader))?
+                .to_owned(),
+            None,
+        )
+    })
+}
+
+async fn parse_pass(
+    r: &mut BpmList,
+    pass: ExtPass,
+    fs: &mut dyn FileSystem,
+    texture_cache: &mut HashMap<String, SafeTexture>,
+) -> Result<EffectPass> {
+    let (shader, path) = load_shader(&pass.shader, fs).await?;
+    let declared = Effect::declared_uniforms(&shader);
+    let mut vars = pass
         .vars
         .into_iter()
         .map(|(name, var)| -> Result<Box<dyn Uniform>> {
+            // JSON numbers are floats, so look at the declaration for int and bool uniforms
+            let integral = declared.get(&name).map_or(false, |it| it == "int" || it == "bool");
             Ok(match var {
+                Variable::Bool(value) => Box::new((name, value as i32)),
+                Variable::Float(events) if integral => Box::new(IntUniform(name, events.into::<f32>(r, None))),
                 Variable::Float(events) => Box::new((name, events.into::<f32>(r, None))),
                 Variable::Vec2(events) => Box::new((name, events.into::<Vec2>(r, None))),
                 Variable::Color(events) => Box::new((name, events.into::<Color>(r, None))),
+                Variable::Mat4(values) => Box::new((name, Mat4::from_cols_array(&values))),
+                Variable::Music(input) => Box::new(MusicUniform::new(
+                    name,
+                    input
+                        .strip_prefix('@')
+                        .and_then(MusicInput::from_name)
+                        .ok_or_else(|| ptl!(err "unknown-music-input", "input" => input.clone()))?,
+                )),
             })
         })
-        .collect::<Result<_>>()?;
-    let string;
-    Effect::new(
-        range,
-        if let Some(path) = rpe.shader.strip_prefix('/') {
-            if let Ok(file) = fs.load_file(path).await {
-                string = String::from_utf8(file).with_context(|| ptl!("shader-load-failed", "path" => path))?;
-                &string
-            } else if let Some(rpe) = Effect::get_rpe_preset(path.strip_suffix(".glsl").unwrap_or_default()){
-                rpe
-            } else {
-                return Err(ptl!(err "shader-load-failed", "path" => rpe.shader));
+        .collect::<Result<Vec<_>>>()?;
+    // uniforms like `musicRms` follow the music without being listed in `vars`
+    for (name, ty) in declared {
+        if let Some(input) = MusicInput::from_uniform_name(&name) {
+            if input.glsl_type() == ty && !vars.iter().any(|it| it.uniform_pair().0 == name) {
+                vars.push(Box::new(MusicUniform::new(name, input)));
             }
-        } else {
-            Effect::get_preset(&rpe.shader).ok_or_else(|| ptl!(err "shader-not-found", "shader" => rpe.shader))?
+        }
+    }
+    let mut textures = Vec::new();
+    for (name, path) in pass.textures {
+        let texture = match path.as_str() {
+            "@input" => EffectTexture::Input,
+            "@previous" => EffectTexture::Previous,
+            _ => EffectTexture::Image(load_texture(path, fs, texture_cache).await?),
+        };
+        textures.push((name, texture));
+    }
+    // a shader that does not compile is reported, but doesn't stop the chart from loading
+    let (mut res, err) = EffectPass::new_or_pass_through(&shader, vars, textures)?;
+    if let Some(err) = err {
+        show_error(err.context(ptl!("shader-compile-failed", "path" => pass.shader)));
+    }
+    res.path = path;
+    Ok(res)
+}
+
+async fn parse_effect(
+    r: &mut BpmList,
+    rpe: ExtEffect,
+    fs: &mut dyn FileSystem,
+    texture_cache: &mut HashMap<String, SafeTexture>,
+) -> Result<Effect> {
+    let range = r.time(&rpe.start)..r.time(&rpe.end);
+    let mut passes = Vec::new();
+    for pass in std::iter::once(rpe.pass).chain(rpe.passes) {
+        passes.push(parse_pass(r, pass, fs, texture_cache).await?);
+    }
+    Ok(Effect::with_passes(range, passes, rpe.global))
+}
+
+async fn parse_emitter(
+    r: &mut BpmList,
+    ext: ExtEmitter,
+    fs: &mut dyn FileSystem,
+    texture_cache: &mut HashMap<String, SafeTexture>,
+) -> Result<ChartEmitter> {
+    let texture = match ext.texture {
+        Some(path) => Some(load_texture(path, fs, texture_cache).await?),
+        None => None,
+    };
+    let (direction, spread) = match ext.direction {
+        Some((x, y)) => (vec2(x, y).normalize_or_zero(), ext.spread.to_radians()),
+        // in all directions
+        None => (vec2(0., 1.), 2. * std::f32::consts::PI),
+    };
+    let config = EmitterConfig {
+        max_particles: ext.max_particles,
+        emission_shape: match ext.shape.unwrap_or(ExtEmissionShape::Point) {
+            ExtEmissionShape::Point => EmissionShape::Point,
+            ExtEmissionShape::Rect(width, height) => EmissionShape::Rect { width, height },
+            ExtEmissionShape::Circle(radius) => EmissionShape::Sphere { radius },
         },
-        vars,
-        rpe.global,
-    )
+        lifetime: ext.lifetime,
+        lifetime_randomness: ext.lifetime_randomness,
+        size: ext.size,
+        size_randomness: ext.size_randomness,
+        initial_direction: direction,
+        initial_direction_spread: spread,
+        initial_velocity: ext.speed,
+        initial_velocity_randomness: ext.speed_randomness,
+        gravity: vec2(ext.gravity.0, ext.gravity.1),
+        colors_curve: ext.colors.map_or_else(ColorCurve::default, |[start, mid, end]| ColorCurve {
+            start: start.into(),
+            mid: mid.into(),
+            end: end.into(),
+        }),
+        blend_mode: if ext.additive { BlendMode::Additive } else { BlendMode::Alpha },
+        atlas: ext.atlas.map(|(n, m)| AtlasConfig::new(n, m, ..)),
+        ..Default::default()
+    };
+    Ok(ChartEmitter::new(
+        r.time(&ext.start)..r.time(&ext.end),
+        ext.position.into(r, None),
+        ext.rate.into(r, None),
+        ext.line,
+        texture,
+        config,
+    ))
 }
 
 pub async fn parse_extra(source: &str, fs: &mut dyn FileSystem) -> Result<ChartExtra> {
@@ -182,12 +404,24 @@ pub async fn parse_extra(source: &str, fs: &mut dyn FileSystem) -> Result<ChartE
     let mut r: BpmList = ext.bpm.into();
     let mut effects = Vec::new();
     let mut global_effects = Vec::new();
+    let mut layer_effects = Vec::new();
+    let mut texture_cache = HashMap::new();
     for (id, effect) in ext.effects.into_iter().enumerate() {
-        (if effect.global { &mut global_effects } else { &mut effects }).push(
-            parse_effect(&mut r, effect, fs)
-                .await
-                .with_context(|| ptl!("effect-location", "id" => id))?,
-        );
+        let layer = match (effect.global, effect.line, effect.layer) {
+            (_, None, None) => None,
+            (false, Some(line), None) => Some(EffectLayer::Line(line)),
+            (false, None, Some((low, high))) => Some(EffectLayer::ZIndex(low, high)),
+            _ => return Err(ptl!(err "effect-target-conflict")).with_context(|| ptl!("effect-location", "id" => id)),
+        };
+        let global = effect.global;
+        let effect = parse_effect(&mut r, effect, fs, &mut texture_cache)
+            .await
+            .with_context(|| ptl!("effect-location", "id" => id))?;
+        if let Some(layer) = layer {
+            layer_effects.push(LayerEffect::new(layer, effect));
+        } else {
+            (if global { &mut global_effects } else { &mut effects }).push(effect);
+        }
     }
     let mut videos = Vec::new();
     for video in ext.videos {
@@ -200,13 +434,25 @@ pub async fn parse_extra(source: &str, fs: &mut dyn FileSystem) -> Result<ChartE
                 video.scale,
                 video.alpha.into(&mut r, Some(1.)),
                 video.dim.into(&mut r, Some(0.)),
+                video.alpha_mode,
+                video.z,
             )
             .with_context(|| ptl!("video-load-failed", "path" => video.path))?,
         );
     }
+    let mut emitters = Vec::new();
+    for (id, emitter) in ext.emitters.into_iter().enumerate() {
+        emitters.push(
+            parse_emitter(&mut r, emitter, fs, &mut texture_cache)
+                .await
+                .with_context(|| ptl!("emitter-location", "id" => id))?,
+        );
+    }
     Ok(ChartExtra {
         effects,
         global_effects,
+        layer_effects,
         videos,
+        emitters,
     })
 }
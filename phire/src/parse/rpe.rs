@@ -3,21 +3,19 @@ crate::tl_file!("parser" ptl);
 use super::{process_lines, RPE_TWEEN_MAP};
 use crate::{
     core::{
-        Anim, AnimFloat, AnimVector, BezierTween, BpmList, Chart, ChartExtra, ChartSettings, ClampedTween, CtrlObject, GifFrames, HitSoundMap,
-        JudgeLine, JudgeLineCache, JudgeLineKind, Keyframe, Note, NoteKind, Object, StaticTween, Triple, TweenFunction, Tweenable, UIElement, EPS,
-        HEIGHT_RATIO,
+        Anim, AnimFloat, AnimVector, AnimatedTexture, BezierTween, BpmList, Chart, ChartExtra, ChartSettings, ClampedTween, CtrlObject,
+        HitSoundMap, JudgeLine, JudgeLineCache, JudgeLineKind, Keyframe, Note, NoteKind, Object, StaticTween, Triple, TweenFunction, Tweenable,
+        UIElement, EPS, HEIGHT_RATIO,
     },
-    ext::{NotNanExt, SafeTexture},
+    ext::{NotNanExt, EMPTY_TEXTURE},
     fs::FileSystem,
     judge::{HitSound, JudgeStatus}
 };
 use anyhow::{Context, Result};
-use image::{codecs::gif, AnimationDecoder, DynamicImage, ImageError};
 use macroquad::prelude::{Color, WHITE};
 use sasa::AudioClip;
 use serde::{Deserialize, Serialize};
-use std::{cell::RefCell, collections::HashMap, future::IntoFuture, rc::Rc, str::FromStr, time::Duration};
-use tracing::debug;
+use std::{cell::RefCell, collections::HashMap, rc::Rc, str::FromStr};
 
 pub const RPE_WIDTH: f32 = 1350.;
 pub const RPE_HEIGHT: f32 = 900.;
@@ -284,7 +282,7 @@ fn parse_speed_events(r: &mut BpmList, rpe: &[RPEEventLayer], max_time: f32) ->
     Ok(AnimFloat::new(kfs))
 }
 
-fn parse_gif_events<V: Clone + Into<f32>>(r: &mut BpmList, rpe: &[RPEEvent<V>], bezier_map: &BezierMap, gif: &GifFrames) -> Result<Anim<f32>> {
+fn parse_gif_events<V: Clone + Into<f32>>(r: &mut BpmList, rpe: &[RPEEvent<V>], bezier_map: &BezierMap, gif: &AnimatedTexture) -> Result<Anim<f32>> {
     let mut kfs = Vec::new();
     kfs.push(Keyframe::new(0.0, 0.0, 2));
     let mut next_rep_time: u128 = 0;
@@ -428,7 +426,6 @@ async fn parse_judge_line(
     bezier_map: &BezierMap,
     hitsounds: &mut HitSoundMap,
 ) -> Result<JudgeLine> {
-    let mut line_texture_map: HashMap<String, SafeTexture> = Default::default();
     let event_layers: Vec<_> = rpe.event_layers.into_iter().flatten().collect();
     let r = &mut BpmList::new(bpm_list.into_iter().map(|it| (it.start_time.beats(), it.bpm / rpe.bpm_factor)).collect());
 
@@ -527,65 +524,20 @@ async fn parse_judge_line(
             } else {
                 JudgeLineKind::Normal
             }
-        } else if let Some(extended) = rpe.extended.as_ref() {
-            if let Some(events) = extended.gif_events.as_ref() {
-                let data = fs
-                    .load_file(&rpe.texture)
-                    .await
-                    .with_context(|| ptl!("gif-load-failed", "path" => rpe.texture.clone()))?;
-                let frames = GifFrames::new(
-                    tokio::spawn(async move {
-                        let decoder = gif::GifDecoder::new(&data[..])?;
-                        debug!("decoding gif");
-                        Ok::<std::vec::Vec<_>, ImageError>(decoder.into_frames().collect())
-                    })
-                    .into_future()
-                    .await??
-                    .into_iter()
-                    .map(|frame| -> (u128, SafeTexture) {
-                        let frame = frame.unwrap();
-                        let delay: Duration = frame.delay().into();
-                        (delay.as_millis(), SafeTexture::from(DynamicImage::ImageRgba8(frame.into_buffer())))
-                    })
-                    .collect(),
-                );
-                debug!("gif decoded");
-                let events = parse_gif_events(r, events, bezier_map, &frames).with_context(|| ptl!("gif-events-parse-failed"))?;
-                JudgeLineKind::TextureGif(events, frames, rpe.texture.clone())
-            } else {
-                if let Some(texture) = line_texture_map.get(&rpe.texture) {
-                    JudgeLineKind::Texture(texture.clone(), rpe.texture.clone())
-                } else {
-                    let texture = SafeTexture::from(image::load_from_memory(
-                        &fs.load_file(&rpe.texture)
-                            .await
-                            .with_context(|| ptl!("illustration-load-failed", "path" => rpe.texture.clone()))?,
-                    )?)
-                    .with_mipmap();
-                    line_texture_map.insert(rpe.texture.clone(), texture.clone());
-                    JudgeLineKind::Texture(
-                        texture,
-                        rpe.texture.clone(),
-                    )
-                }
-            }
+        } else if let Some(events) = rpe.extended.as_ref().and_then(|e| e.gif_events.as_ref()) {
+            let data = fs
+                .load_file(&rpe.texture)
+                .await
+                .with_context(|| ptl!("gif-load-failed", "path" => rpe.texture.clone()))?;
+            let frames = AnimatedTexture::decode(data)
+                .await
+                .with_context(|| ptl!("gif-load-failed", "path" => rpe.texture.clone()))?;
+            let events = parse_gif_events(r, events, bezier_map, &frames).with_context(|| ptl!("gif-events-parse-failed"))?;
+            JudgeLineKind::TextureGif(events, frames, rpe.texture.clone())
         } else {
-            if let Some(texture) = line_texture_map.get(&rpe.texture) {
-                JudgeLineKind::Texture(texture.clone(), rpe.texture.clone())
-            } else {
-                let texture = SafeTexture::from(image::load_from_memory(
-                    &fs.load_file(&rpe.texture)
-                        .await
-                        .with_context(|| ptl!("illustration-load-failed", "path" => rpe.texture.clone()))?,
-                )?)
-                .with_mipmap();
-                line_texture_map.insert(rpe.texture.clone(), texture.clone());
-                JudgeLineKind::Texture(
-                    texture,
-                    rpe.texture.clone(),
-                )
-            }
-    },
+            // loaded by `Chart::load_textures`, so that parsing doesn't need a GL context
+            JudgeLineKind::Texture(EMPTY_TEXTURE.clone(), rpe.texture.clone())
+        },
         color: if let Some(events) = rpe.extended.as_ref().and_then(|e| e.color_events.as_ref()) {
             parse_events(r, events, Some(WHITE), bezier_map).with_context(|| ptl!("color-events-parse-failed"))?
         } else {
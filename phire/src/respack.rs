@@ -0,0 +1,340 @@
+//! Validation of resource packs and contact sheets of their textures, so pack authors can check their work without launching the game.
+
+use crate::{core::ResPackInfo, fs::FileSystem, ui::FontArc};
+use anyhow::{Context, Result};
+use image::{
+    imageops::{self, FilterType},
+    Rgba, RgbaImage,
+};
+use sasa::AudioClip;
+use std::{collections::HashMap, fmt};
+
+const SOUNDS: [&str; 3] = ["click", "drag", "flick"];
+const SOUND_EXTENSIONS: [&str; 3] = ["ogg", "wav", "mp3"];
+const ENDINGS: [&str; 8] = ["_ap", "_fc", "_v", "_s", "_a", "_b", "_c", ""];
+const ENDING_EXTENSIONS: [&str; 2] = ["ogg", "mp3"];
+
+/// Width in pixels of a note in the contact sheet.
+const CELL: u32 = 128;
+const PAD: u32 = 16;
+const BACKGROUND: Rgba<u8> = Rgba([32, 32, 40, 255]);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+#[derive(Clone, Debug)]
+pub struct Issue {
+    pub severity: Severity,
+    pub message: String,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct PackReport {
+    /// Name and author, if `info.yml` could be read
+    pub title: Option<String>,
+    pub issues: Vec<Issue>,
+}
+
+impl PackReport {
+    fn error(&mut self, message: impl Into<String>) {
+        self.issues.push(Issue {
+            severity: Severity::Error,
+            message: message.into(),
+        });
+    }
+
+    fn warn(&mut self, message: impl Into<String>) {
+        self.issues.push(Issue {
+            severity: Severity::Warning,
+            message: message.into(),
+        });
+    }
+
+    /// Whether the game would refuse to load the pack
+    pub fn has_errors(&self) -> bool {
+        self.issues.iter().any(|it| it.severity == Severity::Error)
+    }
+}
+
+impl fmt::Display for PackReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(title) = &self.title {
+            writeln!(f, "{title}")?;
+        }
+        if self.issues.is_empty() {
+            return writeln!(f, "no issues");
+        }
+        for issue in &self.issues {
+            let severity = match issue.severity {
+                Severity::Warning => "warning",
+                Severity::Error => "error",
+            };
+            writeln!(f, "{severity}: {}", issue.message)?;
+        }
+        let errors = self.issues.iter().filter(|it| it.severity == Severity::Error).count();
+        writeln!(f, "{errors} error(s), {} warning(s)", self.issues.len() - errors)
+    }
+}
+
+async fn load_pack_info(fs: &mut dyn FileSystem) -> Result<ResPackInfo> {
+    let bytes = fs.load_file("info.yml").await.context("Missing info.yml")?;
+    serde_yaml::from_str(&String::from_utf8(bytes).context("info.yml is not UTF-8")?).context("Invalid info.yml")
+}
+
+async fn load_image(fs: &mut dyn FileSystem, path: &str) -> Result<RgbaImage> {
+    let bytes = fs.load_file(path).await.with_context(|| format!("Missing {path}"))?;
+    Ok(image::load_from_memory(&bytes).with_context(|| format!("Invalid {path}"))?.into_rgba8())
+}
+
+// textures loaded by `ResourcePack::load`, along with whether they are required
+fn texture_paths(info: &ResPackInfo) -> Vec<(&'static str, bool)> {
+    let mut paths = vec![
+        ("click.png", true),
+        ("drag.png", true),
+        ("flick.png", true),
+        ("hold.png", true),
+        ("click_mh.png", true),
+        ("drag_mh.png", true),
+        ("flick_mh.png", true),
+        ("hold_mh.png", true),
+        ("hit_fx.png", true),
+        ("line.png", false),
+    ];
+    if info.hit_fx_good.is_some() {
+        paths.push(("hit_fx_good.png", true));
+    }
+    paths
+}
+
+/// Checks everything the game needs to load the pack, and some mistakes it would silently accept.
+pub async fn validate(fs: &mut dyn FileSystem) -> PackReport {
+    let mut report = PackReport::default();
+    let info = match load_pack_info(fs).await {
+        Ok(info) => info,
+        Err(err) => {
+            report.error(format!("{err:#}"));
+            return report;
+        }
+    };
+    report.title = Some(format!("{} by {}", info.name, info.author));
+
+    let mut textures = Vec::new();
+    for (path, required) in texture_paths(&info) {
+        if !required && !fs.exists(path).await.unwrap_or(false) {
+            continue;
+        }
+        match load_image(fs, path).await {
+            Ok(image) => textures.push((path, image)),
+            Err(err) => report.error(format!("{err:#}")),
+        }
+    }
+    let texture = |path: &str| textures.iter().find(|it| it.0 == path).map(|it| &it.1);
+
+    for (path, atlas) in [("hold.png", info.hold_atlas), ("hold_mh.png", info.hold_atlas_mh)] {
+        if let Some(image) = texture(path) {
+            if atlas.0 + atlas.1 >= image.height() {
+                report.error(format!(
+                    "{path}: the tail ({}px) and the head ({}px) leave no room for the body in {}px",
+                    atlas.0,
+                    atlas.1,
+                    image.height()
+                ));
+            }
+        }
+    }
+    let animation = info.note_animation;
+    for (name, frames) in [("click", animation.click), ("drag", animation.drag), ("flick", animation.flick)] {
+        for path in [format!("{name}.png"), format!("{name}_mh.png")] {
+            if let Some(image) = texture(path.as_str()) {
+                if frames == 0 || image.width() % frames != 0 {
+                    report.error(format!("{path}: width of {}px is not a multiple of its {frames} frame(s)", image.width()));
+                }
+            }
+        }
+    }
+    for (path, atlas) in [("hit_fx.png", Some(info.hit_fx)), ("hit_fx_good.png", info.hit_fx_good)] {
+        let (Some(image), Some((columns, rows))) = (texture(path), atlas) else { continue };
+        if columns == 0 || rows == 0 {
+            report.error(format!("{path}: atlas of {columns}x{rows} frames is empty"));
+        } else if image.width() % columns != 0 || image.height() % rows != 0 {
+            report.warn(format!(
+                "{path}: {}x{}px is not divisible into {columns}x{rows} frames, so frames are cut unevenly",
+                image.width(),
+                image.height()
+            ));
+        }
+    }
+    if info.hit_fx_duration <= 0. {
+        report.warn(format!("hitFxDuration of {} is not positive, so hit effects never show", info.hit_fx_duration));
+    }
+
+    for (field, color) in [
+        ("colorPerfectFx", info.color_perfect_fx),
+        ("colorGoodFx", info.color_good_fx),
+        ("colorPerfectLine", info.color_perfect_line),
+        ("colorGoodLine", info.color_good_line),
+    ] {
+        let components = [color.0, color.1, color.2, color.3];
+        if components.iter().any(|it| !(0. ..=1.).contains(it)) {
+            report.warn(format!("{field}: components of {color:?} should be between 0 and 1"));
+        } else if color.3 == 0. {
+            report.warn(format!("{field}: alpha is 0, so it is invisible"));
+        }
+    }
+
+    for name in SOUNDS {
+        check_sound(fs, &mut report, name, &SOUND_EXTENSIONS).await;
+    }
+    let mut has_ending = false;
+    for suffix in ENDINGS {
+        has_ending |= check_sound(fs, &mut report, &format!("ending{suffix}"), &ENDING_EXTENSIONS).await;
+    }
+    if !has_ending {
+        report.warn("no ending music, the default one is used");
+    }
+
+    if let Ok(bytes) = fs.load_file("font.ttf").await {
+        if let Err(err) = FontArc::try_from_vec(bytes) {
+            report.error(format!("Invalid font.ttf: {err}"));
+        }
+    }
+
+    report
+}
+
+// returns whether the sound exists; missing sounds, except for endings, fall back to the default ones
+async fn check_sound(fs: &mut dyn FileSystem, report: &mut PackReport, name: &str, extensions: &[&str]) -> bool {
+    for ext in extensions {
+        let path = format!("{name}.{ext}");
+        let Ok(bytes) = fs.load_file(&path).await else { continue };
+        if let Err(err) = AudioClip::new(bytes) {
+            report.error(format!("{path} can not be decoded: {err:#}"));
+        }
+        return true;
+    }
+    if !name.starts_with("ending") {
+        report.warn(format!("no {name} sound, the default one is used"));
+    }
+    false
+}
+
+fn scaled(image: &RgbaImage, width: u32) -> RgbaImage {
+    let height = (image.height() as f32 * width as f32 / image.width() as f32).round().max(1.) as u32;
+    imageops::resize(image, width.max(1), height, FilterType::Triangle)
+}
+
+fn crop(image: &RgbaImage, x: u32, y: u32, width: u32, height: u32) -> Option<RgbaImage> {
+    (width > 0 && height > 0).then(|| imageops::crop_imm(image, x, y, width, height).to_image())
+}
+
+fn tinted(mut image: RgbaImage, (r, g, b, a): (f32, f32, f32, f32)) -> RgbaImage {
+    for Rgba(pixel) in image.pixels_mut() {
+        for (c, factor) in pixel.iter_mut().zip([r, g, b, a]) {
+            *c = (*c as f32 * factor.clamp(0., 1.)).round() as u8;
+        }
+    }
+    image
+}
+
+// first frames of click, drag and flick, then the head, the body and the tail of hold, all as wide as notes in the game
+fn note_cells(images: [&RgbaImage; 4], info: &ResPackInfo, atlas: (u32, u32), note_width: u32) -> Vec<RgbaImage> {
+    let [click, drag, flick, hold] = images;
+    let animation = info.note_animation;
+    let mut cells = Vec::new();
+    for (image, frames) in [(click, animation.click), (drag, animation.drag), (flick, animation.flick)] {
+        let frame_width = image.width() / frames.max(1);
+        if let Some(frame) = crop(image, 0, 0, frame_width, image.height()) {
+            cells.push(scaled(&frame, note_width));
+        }
+    }
+    let (width, height) = hold.dimensions();
+    let body = height.saturating_sub(atlas.0 + atlas.1);
+    for part in [
+        crop(hold, 0, height.saturating_sub(atlas.1), width, atlas.1.min(height)),
+        crop(hold, 0, atlas.0.min(height), width, body),
+        crop(hold, 0, 0, width, atlas.0.min(height)),
+    ]
+    .into_iter()
+    .flatten()
+    {
+        cells.push(scaled(&part, note_width));
+    }
+    cells
+}
+
+fn hit_fx_cells(image: &RgbaImage, (columns, rows): (u32, u32), color: Option<(f32, f32, f32, f32)>) -> Vec<RgbaImage> {
+    if columns == 0 || rows == 0 {
+        return Vec::new();
+    }
+    let (width, height) = (image.width() / columns, image.height() / rows);
+    let mut cells = Vec::new();
+    for row in 0..rows {
+        for column in 0..columns {
+            if let Some(frame) = crop(image, column * width, row * height, width, height) {
+                let frame = scaled(&frame, CELL);
+                cells.push(if let Some(color) = color { tinted(frame, color) } else { frame });
+            }
+        }
+    }
+    cells
+}
+
+/// Renders notes, holds (head, body and tail), their multi-hit variants and hit effect animations side by side.
+///
+/// Notes are scaled as the game would relative to each other. Everything is composed on the CPU, so no GL context is needed.
+pub async fn contact_sheet(fs: &mut dyn FileSystem) -> Result<RgbaImage> {
+    let info = load_pack_info(fs).await?;
+    let mut images = HashMap::new();
+    for (path, required) in texture_paths(&info) {
+        if required || fs.exists(path).await.unwrap_or(false) {
+            images.insert(path, load_image(fs, path).await?);
+        }
+    }
+    // all of these are required, and thus loaded above
+    let names = ["click", "drag", "flick", "hold", "click_mh", "drag_mh", "flick_mh", "hold_mh", "hit_fx"];
+    let [click, drag, flick, hold, click_mh, drag_mh, flick_mh, hold_mh, hit_fx] = names.map(|name| &images[format!("{name}.png").as_str()]);
+    let line = images.get("line.png");
+    let hit_fx_good = images.get("hit_fx_good.png");
+
+    let click_width = click.width() / info.note_animation.click.max(1);
+    let click_mh_width = click_mh.width() / info.note_animation.click.max(1);
+    // multi-hit notes are scaled by the width of click
+    let mh_width = (CELL as f32 * click_mh_width as f32 / click_width.max(1) as f32).round() as u32;
+
+    let tint = |color| info.hit_fx_tinted.then_some(color);
+    let mut rows = vec![
+        note_cells([click, drag, flick, hold], &info, info.hold_atlas, CELL),
+        note_cells([click_mh, drag_mh, flick_mh, hold_mh], &info, info.hold_atlas_mh, mh_width),
+        hit_fx_cells(hit_fx, info.hit_fx, tint(info.color_perfect_fx)),
+        match (hit_fx_good, info.hit_fx_good) {
+            (Some(good), Some(atlas)) => hit_fx_cells(good, atlas, tint(info.color_good_fx)),
+            _ => hit_fx_cells(hit_fx, info.hit_fx, tint(info.color_good_fx)),
+        },
+    ];
+    if let Some(line) = line {
+        rows.push(vec![scaled(line, CELL * 6)]);
+    }
+
+    let width = rows
+        .iter()
+        .map(|cells| cells.iter().map(|it| it.width() + PAD).sum::<u32>() + PAD)
+        .max()
+        .unwrap_or(PAD);
+    let heights: Vec<u32> = rows.iter().map(|cells| cells.iter().map(|it| it.height()).max().unwrap_or(0)).collect();
+    let height = heights.iter().map(|it| it + PAD).sum::<u32>() + PAD;
+    let mut sheet = RgbaImage::from_pixel(width, height, BACKGROUND);
+    let mut y = PAD;
+    for (cells, row_height) in rows.iter().zip(heights) {
+        let mut x = PAD;
+        for cell in cells {
+            // vertically centered in the row
+            imageops::overlay(&mut sheet, cell, x as i64, (y + (row_height - cell.height()) / 2) as i64);
+            x += cell.width() + PAD;
+        }
+        y += row_height + PAD;
+    }
+    Ok(sheet)
+}
@@ -0,0 +1,94 @@
+/// Number of best plays counted towards the RKS, besides the phi slot.
+pub const BEST_N: usize = 19;
+
+/// Accuracy below which a play gives no rating.
+const MIN_ACCURACY: f32 = 0.7;
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RksRecord {
+    pub difficulty: f32,
+    pub accuracy: f32,
+    pub all_perfect: bool,
+}
+
+impl RksRecord {
+    pub fn rating(&self) -> f32 {
+        chart_rating(self.accuracy, self.difficulty)
+    }
+
+    /// Keeps the better of the two records of the same chart.
+    pub fn merge(&self, other: &RksRecord) -> RksRecord {
+        RksRecord {
+            difficulty: self.difficulty,
+            accuracy: self.accuracy.max(other.accuracy),
+            all_perfect: self.all_perfect || other.all_perfect,
+        }
+    }
+}
+
+/// Single-play rating for a chart of the given difficulty, `accuracy` ranging from 0 to 1.
+pub fn chart_rating(accuracy: f32, difficulty: f32) -> f32 {
+    if accuracy < MIN_ACCURACY {
+        return 0.;
+    }
+    let x = (accuracy * 100. - 55.) / 45.;
+    x * x * difficulty.max(0.)
+}
+
+/// Records contributing to the RKS, as indices into the slice passed to [`RksBreakdown::new`].
+#[derive(Clone, Debug, Default)]
+pub struct RksBreakdown {
+    pub rks: f32,
+    /// The best all-perfect play, counted once more on top of the best N
+    pub phi: Option<(usize, f32)>,
+    pub best: Vec<(usize, f32)>,
+}
+
+impl RksBreakdown {
+    pub fn new(records: &[RksRecord]) -> Self {
+        let mut best: Vec<(usize, f32)> = records.iter().map(RksRecord::rating).enumerate().collect();
+        best.sort_by(|x, y| y.1.total_cmp(&x.1));
+        let phi = best.iter().find(|(index, _)| records[*index].all_perfect).copied();
+        best.truncate(BEST_N);
+        let sum = best.iter().map(|it| it.1).sum::<f32>() + phi.map_or(0., |it| it.1);
+        Self {
+            rks: sum / (BEST_N + 1) as f32,
+            phi,
+            best,
+        }
+    }
+}
+
+pub fn rks(records: &[RksRecord]) -> f32 {
+    RksBreakdown::new(records).rks
+}
+
+/// Local records an offline RKS projection is based on.
+#[derive(Clone, Debug, Default)]
+pub struct RksContext {
+    /// Best records of all other charts
+    pub others: Vec<RksRecord>,
+    /// Previous best record of the chart being played
+    pub current: Option<RksRecord>,
+    pub difficulty: f32,
+}
+
+impl RksContext {
+    pub fn rks(&self) -> f32 {
+        let mut records = self.others.clone();
+        records.extend(self.current);
+        rks(&records)
+    }
+
+    /// RKS after a play with the given result.
+    pub fn project(&self, accuracy: f32, all_perfect: bool) -> f32 {
+        let new = RksRecord {
+            difficulty: self.difficulty,
+            accuracy,
+            all_perfect,
+        };
+        let mut records = self.others.clone();
+        records.push(self.current.map_or(new, |it| it.merge(&new)));
+        rks(&records)
+    }
+}
@@ -1,5 +1,8 @@
 crate::tl_file!("scene" ttl);
 
+mod course;
+pub use course::{CourseChart, CourseInfo, CourseResult, CourseScene, CourseStage, StageLoader};
+
 mod ending;
 pub use ending::{EndingScene, RecordUpdateState};
 
@@ -9,6 +12,11 @@ pub use game::{GameMode, GameScene, SimpleRecord};
 mod loading;
 pub use loading::{BasicPlayer, LoadingScene, UpdateFn, UploadFn};
 
+#[cfg(not(target_arch = "wasm32"))]
+mod remote;
+#[cfg(not(target_arch = "wasm32"))]
+pub use remote::{RemoteCommand, RemoteScene, DEFAULT_REMOTE_PORT};
+
 use crate::{
     ext::{draw_image, screen_aspect, LocalTask, SafeTexture, ScaleType},
     judge::Judge,
@@ -325,6 +333,10 @@ pub trait Scene {
     }
     fn update(&mut self, tm: &mut TimeManager) -> Result<()>;
     fn render(&mut self, tm: &mut TimeManager, ui: &mut Ui) -> Result<()>;
+    /// Replaces the text painter of the UI while rendering this scene
+    fn text_painter(&mut self) -> Option<&mut TextPainter> {
+        None
+    }
     fn next_scene(&mut self, _tm: &mut TimeManager) -> NextScene {
         NextScene::None
     }
@@ -487,6 +499,24 @@ impl Main {
         if self.paused {
             return Ok(());
         }
+        let swapped = self.swap_painter(painter);
+        let result = self.render_with(painter);
+        if swapped {
+            self.swap_painter(painter);
+        }
+        result
+    }
+
+    fn swap_painter(&mut self, painter: &mut TextPainter) -> bool {
+        if let Some(own) = self.scenes.last_mut().unwrap().text_painter() {
+            std::mem::swap(painter, own);
+            true
+        } else {
+            false
+        }
+    }
+
+    fn render_with(&mut self, painter: &mut TextPainter) -> Result<()> {
         let mut ui = Ui::new(painter, self.viewport);
         ui.set_touches(self.touches.take().unwrap());
         ui.scope(|ui| self.scenes.last_mut().unwrap().render(&mut self.tm, ui))?;
@@ -535,12 +565,16 @@ impl Main {
     }
 }
 
+const BACKGROUND_DIM: f32 = 0.5;
+
+/// Draws `tex` over the whole screen, dimming it if `dim` is set. Backgrounds blurred by [`LoadingScene`] already have the dim
+/// baked in.
 fn draw_background(tex: Texture2D, dim: bool) {
     let asp = screen_aspect();
     let top = 1. / asp;
     draw_image(tex, Rect::new(-1., -top, 2., top * 2.), ScaleType::CropCenter);
     if dim {
-        draw_rectangle(-1., -top, 2., top * 2., Color::new(0., 0., 0., 0.5));
+        draw_rectangle(-1., -top, 2., top * 2., Color::new(0., 0., 0., BACKGROUND_DIM));
     }
 }
 
@@ -0,0 +1,285 @@
+use super::{draw_background, show_error, GameScene, NextScene, Scene, BACKGROUND_DIM};
+use crate::{
+    config::{ChallengeModeColor, Config},
+    core::Resource,
+    ext::{draw_parallelogram, draw_text_aligned, poll_future, semi_white, BlurredTexture, LocalTask, SafeTexture, ScaleType},
+    judge::PlayResult,
+    time::TimeManager,
+    ui::{RectButton, Ui},
+};
+use anyhow::{bail, Context, Result};
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::any::Any;
+
+/// A preset course, a list of local chart paths played back to back.
+#[derive(Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct CourseInfo {
+    pub name: String,
+    pub charts: Vec<String>,
+}
+
+impl CourseInfo {
+    /// Parses a course file, a YAML map holding the `name` of the course and the local paths of its `charts`.
+    pub fn from_yaml(text: &str) -> Result<Self> {
+        let course: Self = serde_yaml::from_str(text).context("Invalid course file")?;
+        if course.charts.is_empty() {
+            bail!("The course has no charts");
+        }
+        Ok(course)
+    }
+}
+
+pub struct CourseChart {
+    pub name: String,
+    pub level: String,
+    pub difficulty: f32,
+}
+
+/// Result of a single stage, returned by [`GameScene`] when playing in [`GameMode::Course`](super::GameMode::Course).
+pub struct CourseStage {
+    pub result: PlayResult,
+    pub failed: bool,
+}
+
+/// Summary returned to the launching scene once the course is over.
+#[derive(Clone)]
+pub struct CourseResult {
+    pub score: u32,
+    pub rank: u32,
+    pub color: ChallengeModeColor,
+    pub cleared: bool,
+}
+
+/// Creates the scene task (usually a `LoadingScene`) for the stage at the given index.
+pub type StageLoader = Box<dyn FnMut(usize) -> Result<LocalTask<Result<NextScene>>>>;
+
+pub struct CourseScene {
+    name: String,
+    charts: Vec<CourseChart>,
+    loader: StageLoader,
+    config: Config,
+    background: SafeTexture,
+    challenge_icons: [SafeTexture; 6],
+
+    stages: Vec<CourseStage>,
+    load_task: LocalTask<Result<NextScene>>,
+    playing: bool,
+    finished: bool,
+    next_scene: Option<NextScene>,
+    target: Option<RenderTarget>,
+
+    btn_proceed: RectButton,
+}
+
+impl CourseScene {
+    /// The background is blurred and dimmed from `illustration` the same way [`LoadingScene`](super::LoadingScene) does.
+    pub async fn new(name: String, charts: Vec<CourseChart>, config: &Config, illustration: SafeTexture, loader: StageLoader) -> Result<Self> {
+        // the dim is baked into the background, see `draw_background`
+        let dim = if config.render_bg_dim { BACKGROUND_DIM } else { 0. };
+        let background = BlurredTexture::new(illustration).into_texture(config.bg_blurriness, dim);
+        Ok(Self {
+            name,
+            charts,
+            loader,
+            config: config.clone(),
+            background,
+            challenge_icons: Resource::load_challenge_icons().await?,
+
+            stages: Vec::new(),
+            load_task: None,
+            playing: false,
+            finished: false,
+            next_scene: None,
+            target: None,
+
+            btn_proceed: RectButton::new(),
+        })
+    }
+
+    fn cleared(&self) -> bool {
+        self.stages.len() == self.charts.len() && self.stages.iter().all(|it| !it.failed)
+    }
+
+    fn score(&self) -> u32 {
+        self.stages.iter().map(|it| it.result.score).sum()
+    }
+
+    /// Challenge rank, the sum of the integral levels of all charts in the course.
+    fn rank(&self) -> u32 {
+        self.charts.iter().map(|it| it.difficulty.max(0.) as u32).sum()
+    }
+
+    fn color(&self) -> ChallengeModeColor {
+        if !self.cleared() {
+            return ChallengeModeColor::White;
+        }
+        let results = self.stages.iter().map(|it| &it.result);
+        if results.clone().all(|it| it.score >= 1_000_000) {
+            ChallengeModeColor::Rainbow
+        } else if results.clone().all(|it| it.max_combo == it.num_of_notes) {
+            ChallengeModeColor::Golden
+        } else {
+            match self.score() / self.stages.len() as u32 {
+                x if x >= 960000 => ChallengeModeColor::Red,
+                x if x >= 920000 => ChallengeModeColor::Blue,
+                _ => ChallengeModeColor::Green,
+            }
+        }
+    }
+
+    pub fn result(&self) -> CourseResult {
+        CourseResult {
+            score: self.score(),
+            rank: self.rank(),
+            color: self.color(),
+            cleared: self.cleared(),
+        }
+    }
+}
+
+impl Scene for CourseScene {
+    fn enter(&mut self, _tm: &mut TimeManager, target: Option<RenderTarget>) -> Result<()> {
+        self.target = target;
+        if self.playing {
+            // the player quit from the pause menu
+            self.playing = false;
+            self.finished = true;
+        }
+        Ok(())
+    }
+
+    fn on_result(&mut self, _tm: &mut TimeManager, result: Box<dyn Any>) -> Result<()> {
+        self.playing = false;
+        let result = match result.downcast::<CourseStage>() {
+            Ok(stage) => {
+                self.finished |= stage.failed;
+                self.stages.push(*stage);
+                return Ok(());
+            }
+            Err(result) => result,
+        };
+        if let Ok(error) = result.downcast::<anyhow::Error>() {
+            show_error(*error);
+        }
+        self.finished = true;
+        Ok(())
+    }
+
+    fn touch(&mut self, _tm: &mut TimeManager, touch: &Touch) -> Result<bool> {
+        if self.finished && self.btn_proceed.touch(touch) {
+            self.next_scene = Some(NextScene::PopWithResult(Box::new(self.result())));
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    fn update(&mut self, _tm: &mut TimeManager) -> Result<()> {
+        if self.finished || self.playing || self.next_scene.is_some() {
+            return Ok(());
+        }
+        if self.stages.len() == self.charts.len() {
+            self.finished = true;
+            return Ok(());
+        }
+        if self.load_task.is_none() {
+            match (self.loader)(self.stages.len()) {
+                Ok(task) => self.load_task = task,
+                Err(err) => {
+                    show_error(err);
+                    self.finished = true;
+                    return Ok(());
+                }
+            }
+        }
+        if let Some(task) = &mut self.load_task {
+            if let Some(res) = poll_future(task.as_mut()) {
+                self.load_task = None;
+                match res {
+                    Ok(scene) => {
+                        self.playing = true;
+                        self.next_scene = Some(scene);
+                    }
+                    Err(err) => {
+                        show_error(err);
+                        self.finished = true;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn render(&mut self, tm: &mut TimeManager, ui: &mut Ui) -> Result<()> {
+        let (text_title, text_stage, text_total, text_failed, text_proceed) = if self.config.chinese {
+            ("课题模式", "第{}首", "总分", "失败", "继续")
+        } else {
+            ("Course", "Stage {}", "Total", "FAILED", "Proceed")
+        };
+        let mut cam = ui.camera();
+        let top = -1. / cam.zoom.y;
+        cam.render_target = self.target;
+        set_camera(&cam);
+        if self.config.render_bg {
+            draw_background(*self.background, false);
+        }
+        let t = tm.now() as f32;
+
+        let title = if self.name.is_empty() { text_title } else { &self.name[..] };
+        draw_text_aligned(ui, title, -0.85, -top + 0.08, (0., 0.), 0.8, WHITE);
+
+        let h = 0.12;
+        let mut y = -top + 0.25;
+        for (index, chart) in self.charts.iter().enumerate() {
+            let current = !self.finished && index == self.stages.len();
+            let r = Rect::new(-0.85, y, 1.25, h);
+            draw_parallelogram(r, None, Color::new(0., 0., 0., if current { 0.7 } else { 0.5 }), false);
+            draw_text_aligned(ui, &text_stage.replace("{}", &(index + 1).to_string()), r.x + 0.05, r.center().y, (0., 0.5), 0.35, semi_white(0.7));
+            draw_text_aligned(ui, &chart.name, r.x + 0.25, r.center().y, (0., 0.5), 0.5, WHITE);
+            draw_text_aligned(ui, &chart.level, r.right() - 0.05, r.center().y, (1., 0.5), 0.4, WHITE);
+
+            let r = Rect::new(r.right() + 0.03, y, 0.4, h);
+            draw_parallelogram(r, None, Color::new(0., 0., 0., 0.5), false);
+            let text = match self.stages.get(index) {
+                Some(stage) if stage.failed => text_failed.to_owned(),
+                Some(stage) => format!("{:07}", stage.result.score),
+                None if current => ["·", "··", "···"][(t * 3.) as usize % 3].to_owned(),
+                None => "-".to_owned(),
+            };
+            draw_text_aligned(ui, &text, r.center().x, r.center().y, (0.5, 0.5), 0.5, WHITE);
+            y += h + 0.03;
+        }
+
+        let r = Rect::new(-0.85, y + 0.05, 1.68, h * 1.2);
+        draw_parallelogram(r, None, Color::new(0., 0., 0., 0.7), false);
+        draw_text_aligned(ui, text_total, r.x + 0.05, r.center().y, (0., 0.5), 0.45, WHITE);
+        draw_text_aligned(ui, &format!("{:07}", self.score()), r.right() - 0.3, r.center().y, (1., 0.5), 0.7, WHITE);
+
+        if self.finished {
+            let tex = &self.challenge_icons[self.color() as usize];
+            let ct = (r.right() - 0.14, r.center().y);
+            let (w, h) = (0.09 * tex.width() / 78., 0.04 * tex.height() / 38.);
+            let br = Rect::new(ct.0 - w / 2., ct.1 - h / 2., w, h);
+            ui.fill_rect(br, (**tex, br, ScaleType::Fit, WHITE));
+            let rank = if self.config.roman {
+                GameScene::int_to_roman(self.rank())
+            } else if self.config.chinese {
+                GameScene::int_to_chinese(self.rank())
+            } else {
+                self.rank().to_string()
+            };
+            draw_text_aligned(ui, &rank, br.center().x, br.center().y, (0.5, 1.), 0.46, WHITE);
+
+            let r = Rect::new(0.55, top - 0.18, 0.3, 0.1);
+            draw_parallelogram(r, None, if self.btn_proceed.touching() { semi_white(0.6) } else { WHITE }, true);
+            draw_text_aligned(ui, text_proceed, r.center().x, r.center().y, (0.5, 0.5), 0.45, BLACK);
+            self.btn_proceed.set(ui, r);
+        }
+        Ok(())
+    }
+
+    fn next_scene(&mut self, _tm: &mut TimeManager) -> NextScene {
+        self.next_scene.take().unwrap_or_default()
+    }
+}
@@ -2,7 +2,7 @@ crate::tl_file!("ending");
 
 use super::{draw_background, game::{SimpleRecord, GameScene}, loading::UploadFn, NextScene, Scene};
 use crate::{
-    config::Config,
+    config::{Config, Mods},
     ext::{
         create_audio_manger, draw_illustration, draw_parallelogram, draw_parallelogram_ex, draw_text_aligned, draw_text_aligned_fix, SafeTexture, ScaleType,
         PARALLELOGRAM_SLOPE,
@@ -44,9 +44,11 @@ pub struct EndingScene {
     result: PlayResult,
     player_name: String,
     player_rks: Option<f32>,
+    rks_gain: Option<f32>,
     challenge_texture: SafeTexture,
     challenge_rank: u32,
     autoplay: bool,
+    failed: bool,
     speed: f32,
     next: u8, // 0 -> none, 1 -> pop, 2 -> exit
     update_state: Option<RecordUpdateState>,
@@ -80,8 +82,18 @@ impl EndingScene {
         player_rks: Option<f32>,
         record_data: Option<Vec<u8>>,
         record: Option<SimpleRecord>,
+        failed: bool,
     ) -> Result<Self> {
-        let index = icon_index(result.score, result.num_of_notes == result.max_combo);
+        // projected offline RKS, only for plays that would be recorded
+        let rks_projection = config
+            .rks_context
+            .as_ref()
+            .filter(|_| config.offline_mode && !failed && !config.autoplay() && config.speed >= 1.0 - 1e-3)
+            .map(|ctx| {
+                let rks = ctx.project(result.accuracy as f32, result.score >= 1_000_000);
+                (rks, rks - ctx.rks())
+            });
+        let index = if failed { 7 } else { icon_index(result.score, result.num_of_notes == result.max_combo) };
         let mut audio = create_audio_manger(config)?;
         let bgm = audio.create_music(
             endings[index].clone(),
@@ -105,7 +117,7 @@ impl EndingScene {
             audio,
             bgm,
             bgm_already_played: false,
-            update_state: if upload_task.is_some() {
+            update_state: if upload_task.is_some() || failed {
                 None
             } else {
                 Some(RecordUpdateState {
@@ -120,10 +132,12 @@ impl EndingScene {
             info,
             result,
             player_name: config.player_name.clone(),
-            player_rks,
+            player_rks: rks_projection.map(|it| it.0).or(player_rks),
+            rks_gain: rks_projection.map(|it| it.1),
             challenge_texture,
             challenge_rank: config.challenge_rank,
             autoplay: config.autoplay(),
+            failed,
             speed: config.speed,
             next: 0,
 
@@ -249,6 +263,7 @@ impl Scene for EndingScene {
             text_max_combo,
             text_accuracy,
             text_autoplay,
+            text_failed,
             text_new_best,
             text_perfect,
             text_good,
@@ -261,6 +276,7 @@ impl Scene for EndingScene {
                 "最大连击数",
                 "准确率",
                 "自动游玩",
+                "挑战失败",
                 "新纪录",
                 "完美",
                 "良好",
@@ -274,6 +290,7 @@ impl Scene for EndingScene {
                 "Max Combo",
                 "Accuracy",
                 "AUTOPLAY",
+                "FAILED",
                 "NEW BEST",
                 "Perfect",
                 "Good",
@@ -294,7 +311,7 @@ impl Scene for EndingScene {
         cam.render_target = self.target;
         set_camera(&cam);
         if self.config.render_bg {
-            draw_background(*self.background, self.config.render_bg_dim);
+            draw_background(*self.background, false);
         }
 
         fn ran(t: f32, l: f32, r: f32) -> f32 {
@@ -352,15 +369,27 @@ impl Scene for EndingScene {
             } else {
                 format!("{:.2}x", self.speed)
             };
-            let full_screen_judge = if self.config.full_scrrn_judge() {
-                format!("FULL SCREEN JUDGE")
-            } else {
-                format!("")
-            };
+            let mods = [
+                (Mods::FULL_SCREEN_JUDGE, "FULL SCREEN JUDGE"),
+                (Mods::SUDDEN_DEATH, "SUDDEN DEATH"),
+                (Mods::PERFECT_ONLY, "PERFECT ONLY"),
+                (Mods::LIFE_GAUGE, "LIFE GAUGE"),
+            ]
+            .into_iter()
+            .filter(|(m, _)| self.config.has_mod(*m))
+            .map(|(_, name)| name.to_owned())
+            .chain(
+                // with the seed, the same shuffle can be played again
+                self.config.has_mod(Mods::RANDOM).then(|| format!("RANDOM #{}", self.config.random_seed)),
+            )
+            .collect::<Vec<_>>()
+            .join(" ");
             let text = if self.autoplay {
                 format!("{text_autoplay} {spd}")
+            } else if self.failed {
+                format!("{text_failed} {mods} {spd}")
             } else if !self.rated {
-                format!("{full_screen_judge} {spd}")
+                format!("{mods} {spd}")
             } else if let Some(state) = &self.update_state {
                 format!(
                     "{spd}  {}",
@@ -523,6 +552,9 @@ impl Scene for EndingScene {
             Color::new(0., 0., 0., alpha),
             0.10
         );
+        if let Some(gain) = self.rks_gain.filter(|it| *it >= 0.005) {
+            draw_text_aligned(ui, &format!("+{gain:.2}"), sub.right(), main.bottom() + 0.008, (1., 0.), 0.3, color);
+        }
         let r = draw_illustration(*self.player, 1. - 0.21, main.center().y, 0.12 / (0.076 * 7.), 0.12 / (0.076 * 7.), color, true);
         let mut text = ui.text(&self.player_name).pos(r.x - 0.015, r.center().y - 0.002).anchor(1., 0.5).size(0.54).color(color);
         let text_rect = text.measure();
@@ -5,33 +5,40 @@ crate::tl_file!("game");
 use chinese_number::{ChineseCase, ChineseCountMethod, ChineseVariant, NumberToChinese, ChineseToNumber};
 use regex::Regex;
 use super::{
+    course::CourseStage,
     draw_background,
     ending::RecordUpdateState,
     loading::{BasicPlayer, UpdateFn, UploadFn},
     request_input, return_input, show_message, take_input, EndingScene, NextScene, Scene,
 };
 use crate::{
+    audio::MusicFeatures,
     bin::{BinaryReader, BinaryWriter},
     config::{Config, Mods},
     core::{copy_fbo, BadNote, Chart, ChartExtra, Effect, Matrix, Point, Resource, UIElement, Vector, BUFFER_SIZE},
-    ext::{ease_in_out_quartic, get_latency, parse_time, push_frame_time, screen_aspect, semi_white, validate_combo, RectExt, SafeTexture},
-    fs::FileSystem, gyro::{Gyro, GYRO, GYROSCOPE_DATA},
+    ext::{
+        ease_in_out_quartic, get_latency, parse_time, poll_future, push_frame_time, screen_aspect, semi_white, validate_combo, LocalTask, RectExt,
+        SafeTexture,
+    },
+    fs::{FileSystem, FolderWatcher}, gyro::{Gyro, GYRO, GYROSCOPE_DATA},
     info::{ChartFormat, ChartInfo},
     judge::Judge, parse::{parse_extra, parse_pec, parse_phigros, parse_rpe},
     particle::EmitterConfig,
     task::Task,
     time::TimeManager,
-    ui::{RectButton, Ui}
+    ui::{RectButton, TextPainter, Ui}
 };
 use anyhow::{bail, Context, Result};
 use concat_string::concat_string;
 use lyon::path::Path;
 use macroquad::{prelude::*, window::InternalGlContext};
+use rand_pcg::{rand_core::SeedableRng, Pcg32};
 use sasa::{Music, MusicParams};
 use serde::{Deserialize, Serialize};
 use std::{
     any::Any,
     cell::RefCell,
+    collections::HashMap,
     fs::File,
     io::{Cursor, ErrorKind},
     ops::{DerefMut, Range},
@@ -39,7 +46,7 @@ use std::{
     process::{Command, Stdio},
     rc::Rc,
     sync::{Arc, Mutex},
-    time::Duration,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 use tracing::{debug, warn};
 
@@ -52,7 +59,9 @@ use inner::*;
 
 const WAIT_TIME: f32 = 0.5;
 const AFTER_TIME: f32 = 0.7;
+const FAIL_TIME: f32 = 1.5;
 const PAUSE_BACKGROUND_ALPHA: f32 = 0.6;
+const HOT_RELOAD_INTERVAL: f64 = 0.5;
 
 #[derive(Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -60,6 +69,8 @@ pub struct SimpleRecord {
     pub score: u32,
     pub accuracy: f32,
     pub full_combo: bool,
+    #[serde(default)]
+    pub mods: Mods,
 }
 
 impl SimpleRecord {
@@ -67,6 +78,7 @@ impl SimpleRecord {
         let mut changed = false;
         if other.score > self.score {
             self.score = other.score;
+            self.mods = other.mods;
             changed = true;
         }
         if other.accuracy > self.accuracy {
@@ -105,6 +117,7 @@ pub enum GameMode {
     Exercise,
     NoRetry,
     View,
+    Course,
 }
 
 #[derive(Clone)]
@@ -113,6 +126,7 @@ enum State {
     BeforeMusic,
     Playing,
     Ending,
+    Failed,
 }
 
 pub struct PauseRewind {
@@ -121,11 +135,26 @@ pub struct PauseRewind {
     dim: bool,
 }
 
+/// Reloads the chart whenever the folder it's loaded from changes, or only the effect shaders if nothing else changed.
+struct HotReload {
+    fs: Box<dyn FileSystem>,
+    watcher: FolderWatcher,
+    last_check: f64,
+    // changes are only picked up once the folder stays unchanged for one interval, so that half-written files are skipped
+    pending: bool,
+    // files changed since the last reload, relative to the folder
+    changes: Vec<PathBuf>,
+    task: LocalTask<Result<(Chart, Vec<u8>, ChartFormat)>>,
+    error: Option<String>,
+}
+
 pub struct GameScene {
     should_exit: bool,
     next_scene: Option<NextScene>,
 
     pub mode: GameMode,
+    // when unset, finishing only reports the result to the host instead of building an [`EndingScene`]
+    pub(crate) show_ending: bool,
     pub res: Resource,
     pub chart: Chart,
     pub judge: Judge,
@@ -141,17 +170,23 @@ pub struct GameScene {
     exercise_btns: (RectButton, RectButton),
 
     pub music: Music,
+    music_scale: f32,
 
     state: State,
     pub last_update_time: f64,
     pause_rewind: PauseRewind,
     pause_first_time: f32,
+    fail_time: f32,
 
     pub bad_notes: Vec<BadNote>,
 
     upload_fn: Option<UploadFn>,
     update_fn: Option<UpdateFn>,
 
+    hot_reload: Option<HotReload>,
+    // the font of the resource pack, if any
+    text_painter: Option<TextPainter>,
+
     pub touch_points: Vec<(f32, f32)>,
 }
 
@@ -178,20 +213,15 @@ macro_rules! reset {
 macro_rules! reset_music_speed {
     ($self:ident, $res:expr, $tm:ident) => {{
         debug!("recreate music");
-        $self.music = $res.audio.create_music(
-            $res.music.clone(),
-            MusicParams {
-                amplifier: $res.config.volume_music as _,
-                playback_rate: $res.config.speed as _,
-                ..Default::default()
-            },
-        ).expect("failed to create music");
+        let (music, scale) = Self::new_music($res).expect("failed to create music");
+        $self.music = music;
+        $self.music_scale = scale;
         $tm.pause();
         $self.music.pause();
         let now = $tm.now();
         $tm.speed = $res.config.speed as _;
         $tm.seek_to(now);
-        $self.music.seek_to(now as f32);
+        $self.music.seek_to(now as f32 / $self.music_scale);
     }};
 }
 
@@ -315,7 +345,8 @@ impl GameScene {
     }
     
 
-    pub async fn load_chart(fs: &mut dyn FileSystem, info: &ChartInfo, config: &Config) -> Result<(Chart, Vec<u8>, ChartFormat)> {
+    /// Parses the chart without loading any of its textures, so that this works without a GL context.
+    pub async fn parse_chart(fs: &mut dyn FileSystem, info: &ChartInfo, config: &Config) -> Result<(Chart, Vec<u8>, ChartFormat)> {
         let extra = if config.render_extra {
             if let Some(extra) = fs.load_file("extra.json").await.ok().map(String::from_utf8).transpose()? {
                 parse_extra(&extra, fs).await.context("Failed to parse extra")?
@@ -352,8 +383,20 @@ impl GameScene {
                 r.read()
             }
         }?;
-        chart.load_textures(fs).await?;
+        chart.check_extra().context("Failed to parse extra")?;
         chart.settings.hold_partial_cover = info.hold_partial_cover;
+        if config.has_mod(Mods::RANDOM) {
+            let mut rng = Pcg32::seed_from_u64(config.random_seed);
+            for line in &mut chart.lines {
+                line.randomize(&mut rng);
+            }
+        }
+        Ok((chart, bytes, format))
+    }
+
+    pub async fn load_chart(fs: &mut dyn FileSystem, info: &ChartInfo, config: &Config) -> Result<(Chart, Vec<u8>, ChartFormat)> {
+        let (mut chart, bytes, format) = Self::parse_chart(fs, info, config).await?;
+        chart.load_textures(fs).await?;
         Ok((chart, bytes, format))
     }
 
@@ -376,14 +419,26 @@ impl GameScene {
             }
             _ => {}
         }
-        let (mut chart, chart_bytes, chart_format) = Self::load_chart(fs.deref_mut(), &info, &config).await?;
-        let effects = std::mem::take(&mut chart.extra.global_effects);
-        if config.fxaa {
-            chart
-                .extra
-                .effects
-                .push(Effect::new(0.0..f32::INFINITY, include_str!("fxaa.glsl"), Vec::new(), false).unwrap());
+        if config.has_mod(Mods::RANDOM) && config.random_seed == 0 {
+            // kept in the config, so hot reloads shuffle the same way and the ending screen can show it
+            config.random_seed = SystemTime::now().duration_since(UNIX_EPOCH).map_or(1, |it| it.as_nanos() as u64).max(1);
         }
+        let (mut chart, chart_bytes, chart_format) = Self::load_chart(fs.deref_mut(), &info, &config).await?;
+        let effects = Self::take_effects(&mut chart, &config);
+        let hot_reload = if matches!(mode, GameMode::View | GameMode::Exercise) {
+            let mut fs = fs.clone_box();
+            FolderWatcher::new(fs.deref_mut(), Duration::from_secs_f64(HOT_RELOAD_INTERVAL)).map(|watcher| HotReload {
+                fs,
+                watcher,
+                last_check: 0.,
+                pending: false,
+                changes: Vec::new(),
+                task: None,
+                error: None,
+            })
+        } else {
+            None
+        };
 
         let judge = Judge::new(&chart);
 
@@ -396,11 +451,12 @@ impl GameScene {
             player.as_ref().and_then(|it| it.avatar.clone()),
             background,
             illustration,
-            chart.extra.effects.is_empty() && effects.is_empty(),
+            chart.extra.effects.is_empty() && chart.extra.layer_effects.is_empty() && effects.is_empty(),
         )
         .await
         .context("Failed to load resources")?;
-        let exercise_range = (chart.offset + info_offset + res.config.offset)..res.track_length;
+        let text_painter = res.res_pack.font.clone().map(TextPainter::new);
+        let exercise_range = (chart.offset + info_offset + res.config.offset + res.config.start_time)..res.track_length;
         
         // Prepare extra sfx from chart.hitsounds
         chart.hitsounds.drain().for_each(|(name, clip)| {
@@ -409,12 +465,13 @@ impl GameScene {
             }
         });
 
-        let music = Self::new_music(&mut res)?;
+        let (music, music_scale) = Self::new_music(&mut res)?;
         Ok(Self {
             should_exit: false,
             next_scene: None,
 
             mode,
+            show_ending: true,
             res,
             chart,
             judge,
@@ -430,6 +487,7 @@ impl GameScene {
             exercise_btns: (RectButton::new(), RectButton::new()),
 
             music,
+            music_scale,
 
             state: State::Starting,
             last_update_time: 0.,
@@ -439,25 +497,241 @@ impl GameScene {
                 dim: false
             },
             pause_first_time: f32::NEG_INFINITY,
+            fail_time: f32::NAN,
 
             bad_notes: Vec::new(),
 
             upload_fn,
             update_fn,
 
+            hot_reload,
+            text_painter,
+
             touch_points: Vec::new(),
         })
     }
 
-    fn new_music(res: &mut Resource) -> Result<Music> {
-        res.audio.create_music(
-            res.music.clone(),
+    /// Resumes playing from the current time, for external controllers.
+    pub fn play(&mut self, tm: &mut TimeManager) -> Result<()> {
+        if !tm.paused() {
+            return Ok(());
+        }
+        if (tm.speed - self.res.config.speed as f64).abs() > 1e-3 {
+            reset_music_speed!(self, &mut self.res, tm);
+        }
+        let now = tm.now();
+        tm.speed = self.res.config.speed as _;
+        tm.resume();
+        tm.seek_to(now);
+        if matches!(self.state, State::Playing) {
+            self.music.seek_to(now as f32 / self.music_scale)?;
+            self.music.play()?;
+        }
+        Ok(())
+    }
+
+    /// Jumps to the given time, keeping the paused state. Judgements are reset.
+    pub fn seek(&mut self, tm: &mut TimeManager, time: f32) -> Result<()> {
+        let time = time.clamp(0., self.res.track_length);
+        if !matches!(self.state, State::Playing) {
+            // skip the starting animation, and come back from the ending
+            self.res.alpha = 1.;
+            self.state = State::Playing;
+        }
+        self.judge.reset();
+        self.chart.reset();
+        self.bad_notes.clear();
+        tm.seek_to(time as f64);
+        self.music.seek_to(time / self.music_scale)?;
+        if tm.paused() {
+            self.music.pause()?;
+        } else {
+            self.music.play()?;
+        }
+        Ok(())
+    }
+
+    pub fn set_speed(&mut self, tm: &mut TimeManager, speed: f32) -> Result<()> {
+        let paused = tm.paused();
+        self.res.config.speed = speed;
+        // leaves both the music and the time manager paused
+        reset_music_speed!(self, &mut self.res, tm);
+        if !paused {
+            self.play(tm)?;
+        }
+        Ok(())
+    }
+
+    // takes out global effects, and appends FXAA to chart effects if enabled
+    fn take_effects(chart: &mut Chart, config: &Config) -> Vec<Effect> {
+        let effects = std::mem::take(&mut chart.extra.global_effects);
+        if config.fxaa {
+            chart
+                .extra
+                .effects
+                .push(Effect::new(0.0..f32::INFINITY, include_str!("fxaa.glsl"), Vec::new(), false).unwrap());
+        }
+        effects
+    }
+
+    fn update_hot_reload(&mut self, tm: &mut TimeManager) {
+        let Some(hot) = &mut self.hot_reload else { return };
+        if let Some(task) = &mut hot.task {
+            if let Some(res) = poll_future(task.as_mut()) {
+                hot.task = None;
+                match res {
+                    // effect targets are only set up when loading, so effects can't be enabled for a chart loaded without any
+                    Ok((chart, ..)) if self.res.no_effect && chart.extra.has_effects() => {
+                        hot.error = Some(tl!("reload-effects-added").to_string());
+                    }
+                    Ok((chart, chart_bytes, _)) => {
+                        hot.error = None;
+                        self.swap_chart(chart, chart_bytes);
+                    }
+                    Err(err) => {
+                        warn!("failed to reload chart: {err:?}");
+                        hot.error = Some(format!("{err:?}"));
+                    }
+                }
+            }
+            return;
+        }
+        let now = tm.real_time();
+        if now - hot.last_check < HOT_RELOAD_INTERVAL {
+            return;
+        }
+        hot.last_check = now;
+        let changes = hot.watcher.changes();
+        if !changes.is_empty() {
+            hot.pending = true;
+            hot.changes.extend(changes);
+        } else if hot.pending {
+            hot.pending = false;
+            let changes = std::mem::take(&mut hot.changes);
+            let root = hot.watcher.root().to_owned();
+            // shaders used by effects are reloaded in place, anything else reloads the whole chart
+            let shaders: HashMap<PathBuf, String> = Self::all_effects(&mut self.chart, &mut self.effects)
+                .flat_map(|it| it.shader_paths().map(|path| (PathBuf::from(path), path.to_owned())).collect::<Vec<_>>())
+                .collect();
+            if changes.iter().all(|it| shaders.contains_key(it)) {
+                let mut errors = Vec::new();
+                for path in &changes {
+                    let source = match std::fs::read_to_string(root.join(path)) {
+                        Ok(source) => source,
+                        Err(err) => {
+                            errors.push(format!("{}: {err}", path.display()));
+                            continue;
+                        }
+                    };
+                    // every effect gets the new shader even if others fail, and those sharing it fail the same way
+                    let mut messages: Vec<_> = Self::all_effects(&mut self.chart, &mut self.effects)
+                        .filter_map(|effect| effect.reload_shader(&shaders[path], &source).err())
+                        .map(|err| err.to_string())
+                        .collect();
+                    messages.dedup();
+                    errors.extend(messages.into_iter().map(|err| format!("{}:\n{err}", path.display())));
+                }
+                hot.error = if errors.is_empty() { None } else { Some(errors.join("\n")) };
+                return;
+            }
+            let mut fs = hot.fs.clone_box();
+            let info = self.res.info.clone();
+            let config = self.res.config.clone();
+            hot.task = Some(Box::pin(async move { Self::load_chart(fs.deref_mut(), &info, &config).await }));
+        }
+    }
+
+    fn all_effects<'a>(chart: &'a mut Chart, effects: &'a mut [Effect]) -> impl Iterator<Item = &'a mut Effect> {
+        chart
+            .extra
+            .effects
+            .iter_mut()
+            .chain(chart.extra.layer_effects.iter_mut().map(|it| &mut it.effect))
+            .chain(effects.iter_mut())
+    }
+
+    // the new chart picks up at the current time, since it's driven by `res.time`
+    fn swap_chart(&mut self, mut chart: Chart, chart_bytes: Vec<u8>) {
+        self.effects = Self::take_effects(&mut chart, &self.res.config);
+        chart.hitsounds.drain().for_each(|(name, clip)| {
+            if let Ok(clip) = self.res.audio.create_sfx(clip, Some(BUFFER_SIZE)) {
+                self.res.extra_sfxs.insert(name, clip);
+            }
+        });
+        self.judge = Judge::new(&chart);
+        self.chart = chart;
+        self.chart.reset();
+        // notes already passed would all be missed otherwise
+        self.judge.skip_before(&mut self.chart, self.res.time);
+        self.chart_bytes = chart_bytes;
+        self.bad_notes.clear();
+    }
+
+    // returns the music and the ratio between chart time and music position
+    fn new_music(res: &mut Resource) -> Result<(Music, f32)> {
+        let (clip, playback_rate) = res.music_for_speed();
+        let music = res.audio.create_music(
+            clip,
             MusicParams {
-                amplifier: res.config.volume_music as _,
-                playback_rate: res.config.speed as _,
+                amplifier: (res.config.volume_music * res.music_gain) as _,
+                playback_rate: playback_rate as _,
                 ..Default::default()
             },
-        )
+        )?;
+        Ok((music, res.config.speed / playback_rate))
+    }
+
+    fn finish(&mut self, failed: bool) -> Result<()> {
+        if !self.show_ending {
+            self.next_scene = Some(NextScene::PopWithResult(Box::new(self.judge.result())));
+            return Ok(());
+        }
+        let mut record_data = None;
+        // TODO strengthen the protection
+        #[cfg(feature = "closed")]
+        if let Some(upload_fn) = &self.upload_fn {
+            if !failed && !self.res.config.offline_mode && !self.res.config.autoplay() && self.res.config.speed >= 1.0 - 1e-3 {
+                if let Some(player) = &self.player {
+                    if let Some(chart) = &self.res.info.id {
+                        record_data = Some(encode_record(self, player.id, *chart));
+                    }
+                }
+            }
+        }
+        let result = self.judge.result();
+        let record = if failed || self.res.config.autoplay() || self.res.config.speed < 1.0 - 1e-3 {
+            None
+        } else {
+            Some(SimpleRecord {
+                score: result.score as _,
+                accuracy: result.accuracy as _,
+                full_combo: result.max_combo == result.num_of_notes,
+                mods: self.res.config.mods,
+            })
+        };
+        self.next_scene = match self.mode {
+            GameMode::Normal | GameMode::Exercise | GameMode::NoRetry | GameMode::View => Some(NextScene::Overlay(Box::new(EndingScene::new(
+                self.res.background.clone(),
+                self.res.illustration.clone(),
+                self.res.player.clone(),
+                self.res.icons.clone(),
+                self.res.icon_retry.clone(),
+                self.res.icon_proceed.clone(),
+                self.res.info.clone(),
+                result,
+                self.res.challenge_icons[self.res.config.challenge_color.clone() as usize].clone(),
+                &self.res.config,
+                self.res.res_pack.endings.clone(),
+                self.upload_fn.as_ref().map(Arc::clone),
+                if self.res.config.offline_mode { Some(self.res.config.player_rks) } else { self.player.as_ref().map(|it| it.rks) },
+                record_data,
+                record,
+                failed,
+            )?))),
+            GameMode::TweakOffset => Some(NextScene::PopWithResult(Box::new(None::<f32>))),
+            GameMode::Course => Some(NextScene::PopWithResult(Box::new(CourseStage { result, failed }))),
+        };
+        Ok(())
     }
 
     fn touch_scale(&self) -> f32 {
@@ -480,6 +754,7 @@ impl GameScene {
                 let t = time - self.res.track_length - WAIT_TIME;
                 1. - (t / (AFTER_TIME + 0.3)).clamp(0., 1.).powi(2)
             }
+            State::Failed => 1. - ((time - self.fail_time) / FAIL_TIME).clamp(0., 1.).powi(2),
         };
         let c = Color::new(1., 1., 1., self.res.alpha);
         let res = &mut self.res;
@@ -571,6 +846,15 @@ impl GameScene {
                 ;
             });
         }
+        if res.config.has_mod(Mods::LIFE_GAUGE) {
+            let r = Rect::new(pause_center.x - pause_w * 1.5, pause_center.y + pause_h / 2. + 0.03, 0.25, 0.012);
+            let health = self.judge.health;
+            ui.fill_rect(r, Color::new(1., 1., 1., 0.3 * c.a));
+            ui.fill_rect(
+                Rect { w: r.w * health, ..r },
+                if health > 0.3 { Color::new(1., 1., 1., c.a) } else { Color::new(1., 0.3, 0.3, c.a) },
+            );
+        }
         let unit_h = ui.text("0").size(scale_ratio).measure().h;
         let combo_y = top + eps * 1.55 - (1. - p) * 0.4;
         if self.judge.combo() >= 3 && res.config.render_ui_combo {
@@ -694,6 +978,30 @@ impl GameScene {
 
     fn overlay_ui(&mut self, ui: &mut Ui, tm: &mut TimeManager) -> Result<()> {
         let c = semi_white(self.res.alpha);
+        if matches!(self.state, State::Failed) {
+            let p = ((tm.now() as f32 - self.fail_time) / FAIL_TIME).clamp(0., 1.);
+            let h = 1. / self.res.aspect_ratio;
+            draw_rectangle(-1., -h, 2., h * 2., Color::new(0.6, 0., 0., 0.4 * (1. - p)));
+            ui.text(tl!("failed"))
+                .pos(0., 0.)
+                .anchor(0.5, 0.5)
+                .size(1.2)
+                .color(Color::new(1., 1., 1., (p * 4.).min(1.) * (1. - p)))
+                .draw();
+        }
+        if let Some(error) = self.hot_reload.as_ref().and_then(|it| it.error.as_ref()) {
+            let h = 1. / self.res.aspect_ratio;
+            let text = tl!("reload-failed", "error" => error.as_str());
+            let r = ui.text(&text).pos(-0.95, -h + 0.04).size(0.35).max_width(1.9).multiline().measure();
+            ui.fill_rect(r.feather(0.02), Color::new(0., 0., 0., 0.7));
+            ui.text(text)
+                .pos(-0.95, -h + 0.04)
+                .size(0.35)
+                .max_width(1.9)
+                .multiline()
+                .color(Color::new(1., 0.6, 0.6, 1.))
+                .draw();
+        }
         let res = &mut self.res;
         for pos in &self.touch_points {
             ui.fill_circle(pos.0, pos.1, 0.04, Color { a: 0.4, ..BLUE });
@@ -702,7 +1010,7 @@ impl GameScene {
             let o = if matches!(self.mode, GameMode::Exercise | GameMode::TweakOffset) { -0.3 } else { 0. };
             let s = 0.06;
             let w = 0.05;
-            let no_retry = self.mode == GameMode::NoRetry;
+            let no_retry = matches!(self.mode, GameMode::NoRetry | GameMode::Course);
             draw_texture_ex(
                 *res.icon_back,
                 -s * 3. - w,
@@ -753,7 +1061,7 @@ impl GameScene {
                 if no_retry && clicked == Some(0) {
                     clicked = None;
                 }
-                let mut pos = self.music.position();
+                let mut pos = self.music.position() * self.music_scale;
                 if clicked.map_or(false, |it| it != -1) && (tm.speed - res.config.speed as f64).abs() > 1e-3 {
                     reset_music_speed!(self, res, tm);
                 }
@@ -773,7 +1081,7 @@ impl GameScene {
                     Some(1) => {
                         if self.mode == GameMode::Exercise && tm.now() > self.exercise_range.end as f64 && self.exercise_range.end - 0.1 < res.track_length {
                             tm.seek_to(self.exercise_range.start as f64);
-                            self.music.seek_to(self.exercise_range.start)?;
+                            self.music.seek_to(self.exercise_range.start / self.music_scale)?;
                             pos = self.exercise_range.start;
                         }
                         self.music.play()?;
@@ -781,7 +1089,7 @@ impl GameScene {
                         tm.speed = res.config.speed as _;
                         tm.resume();
                         tm.seek_to(now - 1.);
-                        self.music.seek_to(now as f32 - 1.);
+                        self.music.seek_to((now as f32 - 1.) / self.music_scale);
                         self.pause_rewind = PauseRewind {
                             time: Some(tm.now()),
                             duration: Some(1.0),
@@ -862,7 +1170,7 @@ impl GameScene {
                         };
                         if *ctrl == 0 {
                             tm.seek_to(p as f64);
-                            self.music.seek_to(p)?;
+                            self.music.seek_to(p / self.music_scale)?;
                         } else {
                             *(if *ctrl == -1 {
                                 &mut self.exercise_range.start
@@ -932,7 +1240,7 @@ impl GameScene {
         res.config.interactive && matches!(state, State::Playing)
     }
 
-    fn offset(&self) -> f32 {
+    pub(crate) fn offset(&self) -> f32 {
         self.chart.offset + self.res.config.offset + self.info_offset
     }
 
@@ -1009,7 +1317,7 @@ impl GameScene {
             ui.dy(ui.top - height * 0.75);
             ui.slider(tl!("speed"), 0.1..2.0, 0.05, &mut self.res.config.speed, Some(0.36));
             if (tm.speed - self.res.config.speed as f64).abs() > 1e-3 {
-                reset_music_speed!(self, self.res, tm);
+                reset_music_speed!(self, &mut self.res, tm);
                 tm.resume();
                 self.music.play();
             }
@@ -1021,7 +1329,7 @@ impl Scene for GameScene {
     fn enter(&mut self, tm: &mut TimeManager, target: Option<RenderTarget>) -> Result<()> {
         #[cfg(target_arch = "wasm32")]
         on_game_start();
-        self.music = Self::new_music(&mut self.res)?;
+        (self.music, self.music_scale) = Self::new_music(&mut self.res)?;
         self.res.camera.render_target = target;
         tm.speed = self.res.config.speed as _;
         tm.adjust_time = self.res.config.adjust_time;
@@ -1053,14 +1361,25 @@ impl Scene for GameScene {
 
     fn update(&mut self, tm: &mut TimeManager) -> Result<()> {
         self.res.audio.recover_if_needed()?;
+        self.update_hot_reload(tm);
         if matches!(self.state, State::Playing) {
-            tm.update(self.music.position() as f64);
+            if self.res.poll_music_stretch() {
+                // swaps in the clip stretched in the background, carrying on from where the old one was
+                let paused = tm.paused();
+                reset_music_speed!(self, &mut self.res, tm);
+                if !paused {
+                    tm.resume();
+                    self.music.play()?;
+                }
+            }
+            tm.update((self.music.position() * self.music_scale) as f64);
         }
         if self.mode == GameMode::Exercise && tm.now() > self.exercise_range.end as f64 && self.exercise_range.end < self.res.track_length - 0.1 && !tm.paused() {
             let state = self.state.clone();
             reset!(self, self.res, tm);
             self.state = state;
             tm.seek_to(self.exercise_range.start as f64);
+            self.judge.skip_before(&mut self.chart, self.exercise_range.start - self.offset());
             tm.pause();
             self.music.pause()?;
         }
@@ -1075,6 +1394,8 @@ impl Scene for GameScene {
                     self.state = State::BeforeMusic;
                     tm.reset();
                     tm.seek_to(self.exercise_range.start as f64);
+                    // notes before the start would otherwise all be missed
+                    self.judge.skip_before(&mut self.chart, self.exercise_range.start - self.offset());
                     self.last_update_time = tm.real_time();
                     if self.first_in && self.mode == GameMode::Exercise {
                         //tm.pause();
@@ -1103,7 +1424,7 @@ impl Scene for GameScene {
             }
             State::BeforeMusic => {
                 if time >= 0.0 {
-                    self.music.seek_to(time)?;
+                    self.music.seek_to(time / self.music_scale)?;
                     self.music.play()?;
                     self.state = State::Playing;
                 }
@@ -1118,52 +1439,19 @@ impl Scene for GameScene {
             State::Ending => {
                 let t = time - self.res.track_length - WAIT_TIME;
                 if t >= AFTER_TIME + 0.3 {
-                    let mut record_data = None;
-                    // TODO strengthen the protection
-                    #[cfg(feature = "closed")]
-                    if let Some(upload_fn) = &self.upload_fn {
-                        if !self.res.config.offline_mode && !self.res.config.autoplay() && self.res.config.speed >= 1.0 - 1e-3 {
-                            if let Some(player) = &self.player {
-                                if let Some(chart) = &self.res.info.id {
-                                    record_data = Some(encode_record(self, player.id, *chart));
-                                }
-                            }
-                        }
-                    }
-                    let result = self.judge.result();
-                    let record = if self.res.config.autoplay() || self.res.config.speed < 1.0 - 1e-3 {
-                        None
-                    } else {
-                        Some(SimpleRecord {
-                            score: result.score as _,
-                            accuracy: result.accuracy as _,
-                            full_combo: result.max_combo == result.num_of_notes,
-                        })
-                    };
-                    self.next_scene = match self.mode {
-                        GameMode::Normal | GameMode::Exercise | GameMode::NoRetry | GameMode::View => Some(NextScene::Overlay(Box::new(EndingScene::new(
-                            self.res.background.clone(),
-                            self.res.illustration.clone(),
-                            self.res.player.clone(),
-                            self.res.icons.clone(),
-                            self.res.icon_retry.clone(),
-                            self.res.icon_proceed.clone(),
-                            self.res.info.clone(),
-                            self.judge.result(),
-                            self.res.challenge_icons[self.res.config.challenge_color.clone() as usize].clone(),
-                            &self.res.config,
-                            self.res.res_pack.endings.clone(),
-                            self.upload_fn.as_ref().map(Arc::clone),
-                            self.player.as_ref().map(|it| it.rks),
-                            record_data,
-                            record,
-                        )?))),
-                        GameMode::TweakOffset => Some(NextScene::PopWithResult(Box::new(None::<f32>))),
-                    };
+                    self.finish(false)?;
                 }
                 self.res.alpha = 1. - (t / AFTER_TIME).clamp(0., 1.).powi(2);
                 self.res.track_length
             }
+            State::Failed => {
+                let t = time - self.fail_time;
+                if t >= FAIL_TIME {
+                    self.finish(true)?;
+                }
+                self.res.alpha = 1. - (t / FAIL_TIME).clamp(0., 1.).powi(2);
+                self.fail_time
+            }
         };
 
         let time = if self.mode == GameMode::TweakOffset {
@@ -1174,7 +1462,11 @@ impl Scene for GameScene {
             (time - self.offset()).max(0.)
         };
         self.res.time = time;
-        if !tm.paused() && (self.res.config.autoplay() || self.pause_rewind.time.is_none()) && self.mode != GameMode::View {
+        if !tm.paused()
+            && (self.res.config.autoplay() || self.pause_rewind.time.is_none())
+            && self.mode != GameMode::View
+            && !matches!(self.state, State::Failed)
+        {
             self.gl.quad_gl.viewport(self.res.camera.viewport);
 
             let angle = GYRO.lock().unwrap().get_angle(&self.res.config);
@@ -1182,6 +1474,15 @@ impl Scene for GameScene {
             self.judge.update(&mut self.res, &mut self.chart, &mut self.bad_notes, -angle);
             self.gl.quad_gl.viewport(None);
         }
+        if matches!(self.state, State::Playing)
+            && matches!(self.mode, GameMode::Normal | GameMode::NoRetry | GameMode::Course)
+            && self.res.config.can_fail()
+            && self.judge.failed(self.res.config.mods)
+        {
+            self.music.pause()?;
+            self.fail_time = tm.now() as f32;
+            self.state = State::Failed;
+        }
         if let Some(update) = &mut self.update_fn {
             update(self.res.time, &mut self.res, &mut self.judge);
         }
@@ -1196,6 +1497,11 @@ impl Scene for GameScene {
             WHITE
         };
         self.res.judge_line_color.a *= self.res.alpha;
+        if !self.res.no_effect && Self::all_effects(&mut self.chart, &mut self.effects).any(|it| it.uses_music()) {
+            let beat = self.chart.bpm_list.get_mut().beat(self.res.time);
+            self.res.music_features =
+                MusicFeatures::analyze(self.res.music.frames(), self.res.music.sample_rate(), self.res.time + self.offset(), beat);
+        }
         self.chart.update(&mut self.res);
         let res = &mut self.res;
         if !tm.paused() {
@@ -1212,7 +1518,7 @@ impl Scene for GameScene {
                     if (tm.speed - res.config.speed as f64).abs() > 1e-3 {
                         reset_music_speed!(self, res, tm);
                     }
-                    self.music.seek_to(now as f32)?;
+                    self.music.seek_to(now as f32 / self.music_scale)?;
                     self.music.play()?;
                     tm.seek_to(now);
                     tm.resume();
@@ -1238,14 +1544,14 @@ impl Scene for GameScene {
         if Self::interactive(res, &self.state) {
             if is_key_pressed(KeyCode::Left) {
                 res.time -= 2.;
-                let dst = (self.music.position() - 2.).max(0.);
-                self.music.seek_to(dst)?;
+                let dst = (self.music.position() * self.music_scale - 2.).max(0.);
+                self.music.seek_to(dst / self.music_scale)?;
                 tm.seek_to(dst as f64);
             }
             if is_key_pressed(KeyCode::Right) {
                 res.time += 5.;
-                let dst = (self.music.position() + 5.).min(res.track_length);
-                self.music.seek_to(dst)?;
+                let dst = (self.music.position() * self.music_scale + 5.).min(res.track_length);
+                self.music.seek_to(dst / self.music_scale)?;
                 tm.seek_to(dst as f64);
 
                 self.pause_rewind = PauseRewind {
@@ -1313,6 +1619,10 @@ impl Scene for GameScene {
         Ok(false)
     }
 
+    fn text_painter(&mut self) -> Option<&mut TextPainter> {
+        self.text_painter.as_mut()
+    }
+
     fn render(&mut self, tm: &mut TimeManager, ui: &mut Ui) -> Result<()> {
         let res = &mut self.res;
 
@@ -1330,6 +1640,7 @@ impl Scene for GameScene {
                 let t = time - res.track_length;
                 1. - (t / Self::BEFORE_DURATION).clamp(0., 1.)
             }
+            State::Failed => 1. - ((time - self.fail_time) / Self::BEFORE_DURATION).clamp(0., 1.),
         };
         let ratio = if res.config.chart_ratio == 1. || res.config.disable_loading {
             res.config.chart_ratio
@@ -1372,7 +1683,7 @@ impl Scene for GameScene {
         });
         if res.config.render_bg {
             clear_background(BLACK);
-            draw_background(*res.background, res.config.render_bg_dim);
+            draw_background(*res.background, false);
         }
 
         if res.config.render_bg_dim && res.config.chart_ratio >= 1. {
@@ -1402,14 +1713,15 @@ impl Scene for GameScene {
         }
 
         let angle = GYRO.lock().unwrap().get_angle(&res.config);
-        set_camera( &Camera2D {
+        let chart_camera = Camera2D {
             zoom: chart_zoom,
             viewport: chart_viewport,
             rotation: angle.to_degrees(),
             ..Default::default()
-        });
+        };
+        set_camera(&chart_camera);
         self.gl.quad_gl.render_pass(chart_onto.map(|it| it.render_pass));
-        self.chart.render(ui, res);
+        self.chart.render(ui, res, &chart_camera);
 
         self.gl.quad_gl.render_pass(
             res.chart_target
@@ -1430,7 +1742,7 @@ impl Scene for GameScene {
                 zoom: vec2(1., asp2_chart),
                 ..Default::default()
             });
-            for effect in &self.chart.extra.effects {
+            for effect in &mut self.chart.extra.effects {
                 effect.render(res);
             }
         }
@@ -1450,7 +1762,7 @@ impl Scene for GameScene {
                 zoom: vec2(1., asp2_window),
                 ..Default::default()
             });
-            for effect in &self.effects {
+            for effect in &mut self.effects {
                 effect.render(&mut self.res);
             }
         }
@@ -1535,7 +1847,7 @@ impl Scene for GameScene {
             tm.speed = 1.0;
             tm.adjust_time = false;
             match self.mode {
-                GameMode::Normal | GameMode::Exercise | GameMode::NoRetry | GameMode::View => NextScene::Pop,
+                GameMode::Normal | GameMode::Exercise | GameMode::NoRetry | GameMode::View | GameMode::Course => NextScene::Pop,
                 GameMode::TweakOffset => NextScene::PopWithResult(Box::new(None::<f32>)),
             }
         } else if let Some(next_scene) = self.next_scene.take() {
@@ -1,8 +1,11 @@
-use super::{draw_background, ending::RecordUpdateState, game::GameMode, GameScene, NextScene, Scene};
+use super::{draw_background, ending::RecordUpdateState, game::GameMode, GameScene, NextScene, Scene, BACKGROUND_DIM};
 use crate::{
     config::Config,
     core::Resource,
-    ext::{draw_illustration, draw_parallelogram, draw_text_aligned, draw_text_aligned_fix, poll_future, LocalTask, SafeTexture, BLACK_TEXTURE},
+    ext::{
+        draw_illustration, draw_parallelogram, draw_text_aligned, draw_text_aligned_fix, poll_future, BlurredTexture, LocalTask, SafeTexture,
+        BLACK_TEXTURE,
+    },
     fs::FileSystem,
     info::ChartInfo,
     judge::Judge,
@@ -54,43 +57,22 @@ impl LoadingScene {
         upload_fn: Option<UploadFn>,
         update_fn: Option<UpdateFn>,
     ) -> Result<Self> {
-        async fn load(fs: &mut Box<dyn FileSystem>, config: &Config, path: &str) -> Result<(Texture2D, Texture2D)> {
+        async fn load(fs: &mut Box<dyn FileSystem>, config: &Config, path: &str) -> Result<(SafeTexture, SafeTexture)> {
             let image = image::load_from_memory(&fs.load_file(path).await?).context("Failed to decode image")?;
-            let (w, h) = (image.width(), image.height());
-            let size = w as usize * h as usize;
-
-            let mut blurred_rgb = image.to_rgb8();
-            let mut vec = unsafe { Vec::from_raw_parts(std::mem::transmute(blurred_rgb.as_mut_ptr()), size, size) };
-            fastblur::gaussian_blur(&mut vec, w as _, h as _, config.bg_blurriness);
-            std::mem::forget(vec);
-            let mut blurred = Vec::with_capacity(size * 4);
-            for input in blurred_rgb.chunks_exact(3) {
-                //blurred.extend_from_slice(input);
-                blurred.push(input[0]);
-                blurred.push(input[1]);
-                blurred.push(input[2]);
-                blurred.push(255);
-            }
-            Ok((
-                Texture2D::from_rgba8(w as _, h as _, &image.into_rgba8()),
-                Texture2D::from_image(&Image {
-                    width: w as _,
-                    height: h as _,
-                    bytes: blurred,
-                }),
-            ))
+            let illustration: SafeTexture = image.into();
+            // the dim is baked into the background, see `draw_background`
+            let dim = if config.render_bg_dim { BACKGROUND_DIM } else { 0. };
+            let background = BlurredTexture::new(illustration.clone()).into_texture(config.bg_blurriness, dim);
+            Ok((illustration, background))
         }
 
-        let background = match load(&mut fs, config, &info.illustration).await {
-            Ok((ill, bg)) => Some((ill, bg)),
+        let (illustration, background) = match load(&mut fs, config, &info.illustration).await {
+            Ok(textures) => textures,
             Err(err) => {
                 warn!("failed to load background: {err:?}");
-                None
+                (BLACK_TEXTURE.clone(), BLACK_TEXTURE.clone())
             }
         };
-        let (illustration, background): (SafeTexture, SafeTexture) = background
-            .map(|(ill, back)| (ill.into(), back.into()))
-            .unwrap_or_else(|| (BLACK_TEXTURE.clone(), BLACK_TEXTURE.clone()));
         if info.tip.is_none() {
             let tips_file = load_file(format!("tips.txt").as_str()).await?;
             let tips = String::from_utf8_lossy(&tips_file)
@@ -159,7 +141,7 @@ impl Scene for LoadingScene {
             ..Default::default()
         });
         if self.config.render_bg {
-            draw_background(*self.background, self.config.render_bg_dim);
+            draw_background(*self.background, false);
         }
         let dx = if now > self.finish_time {
             let p = ((now - self.finish_time) / TRANSITION_TIME).min(1.);
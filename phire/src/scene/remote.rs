@@ -0,0 +1,275 @@
+//! Remote control for external chart editors.
+//!
+//! The server listens on localhost and speaks line-delimited JSON: every line sent is a [`RemoteCommand`], and is answered by exactly one line,
+//! `{"ok": true, ...}` on success or `{"ok": false, "error": "..."}` otherwise.
+
+use super::{GameMode, GameScene, LoadingScene, NextScene, Scene};
+use crate::{
+    config::{Config, Mods},
+    ext::{poll_future, semi_white, LocalTask},
+    fs::{fs_from_file, load_info, FileSystem, ZipFileSystem},
+    time::TimeManager,
+    ui::Ui,
+};
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use macroquad::prelude::*;
+use serde::{Deserialize, Deserializer};
+use serde_json::{json, Value};
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    path::Path,
+    sync::mpsc,
+    thread,
+};
+use tracing::{info, warn};
+
+pub const DEFAULT_REMOTE_PORT: u16 = 14514;
+
+#[derive(Deserialize)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+pub enum RemoteCommand {
+    /// Loads a chart, either from a folder or zip file at `path`, or from the base64-encoded zip in `zip`
+    Load {
+        path: Option<String>,
+        zip: Option<String>,
+    },
+    Play,
+    Pause,
+    /// Seeks to the given time in seconds
+    Seek {
+        time: f32,
+    },
+    Speed {
+        speed: f32,
+    },
+    /// Sets mods by their bits as an integer, see [`Mods`]; unknown bits are dropped. Those changing the chart itself (e.g. `RANDOM`)
+    /// take effect on the next load.
+    Mods {
+        #[serde(deserialize_with = "mods_from_bits")]
+        mods: Mods,
+    },
+    Status,
+}
+
+fn mods_from_bits<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Mods, D::Error> {
+    Ok(Mods::from_bits_truncate(i32::deserialize(deserializer)?))
+}
+
+type Request = (RemoteCommand, mpsc::Sender<Value>);
+
+fn serve(stream: TcpStream, tx: mpsc::Sender<Request>) -> Result<()> {
+    let mut writer = stream.try_clone()?;
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let resp = match serde_json::from_str::<RemoteCommand>(&line) {
+            Ok(cmd) => {
+                let (resp_tx, resp_rx) = mpsc::channel();
+                tx.send((cmd, resp_tx))?;
+                resp_rx.recv()?
+            }
+            Err(err) => json!({ "ok": false, "error": format!("invalid command: {err}") }),
+        };
+        writeln!(writer, "{resp}")?;
+    }
+    Ok(())
+}
+
+/// Hosts a [`GameScene`] in [`GameMode::View`] driven by remote commands.
+pub struct RemoteScene {
+    port: u16,
+    config: Config,
+    rx: mpsc::Receiver<Request>,
+
+    game: Option<GameScene>,
+    load_task: LocalTask<Result<GameScene>>,
+    // responder of the command that started the loading
+    load_resp: Option<mpsc::Sender<Value>>,
+    target: Option<RenderTarget>,
+}
+
+impl RemoteScene {
+    pub fn new(port: u16, config: Config) -> Result<Self> {
+        let listener = TcpListener::bind(("127.0.0.1", port))?;
+        info!("remote control listening on 127.0.0.1:{port}");
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => {
+                        let tx = tx.clone();
+                        thread::spawn(move || {
+                            if let Err(err) = serve(stream, tx) {
+                                warn!("remote connection closed: {err:?}");
+                            }
+                        });
+                    }
+                    Err(err) => warn!("failed to accept remote connection: {err:?}"),
+                }
+            }
+        });
+        Ok(Self {
+            port,
+            config,
+            rx,
+
+            game: None,
+            load_task: None,
+            load_resp: None,
+            target: None,
+        })
+    }
+
+    fn load(&mut self, path: Option<String>, zip: Option<String>) -> Result<()> {
+        let mut fs: Box<dyn FileSystem> = match (path, zip) {
+            (Some(path), None) => fs_from_file(Path::new(&path))?,
+            (None, Some(zip)) => Box::new(ZipFileSystem::new(STANDARD.decode(zip)?)?),
+            _ => return Err(anyhow!("expected exactly one of `path` and `zip`")),
+        };
+        let config = self.config.clone();
+        self.load_task = Some(Box::pin(async move {
+            let info = load_info(fs.as_mut()).await?;
+            let mut loading = LoadingScene::new(GameMode::View, info, &config, fs, None, None, None).await?;
+            loading.load_task.take().unwrap().await
+        }));
+        Ok(())
+    }
+
+    fn status(&self, tm: &TimeManager) -> Value {
+        let Some(game) = &self.game else {
+            return json!({ "ok": true, "loaded": false, "loading": self.load_task.is_some() });
+        };
+        // notes are not judged in view mode, so there is no judge state to report
+        json!({
+            "ok": true,
+            "loaded": true,
+            "time": game.res.time,
+            "length": game.res.track_length,
+            "paused": tm.paused(),
+            "speed": game.res.config.speed,
+            "mods": game.res.config.mods.bits(),
+        })
+    }
+
+    fn handle(&mut self, tm: &mut TimeManager, cmd: RemoteCommand, resp: mpsc::Sender<Value>) -> Result<()> {
+        if let RemoteCommand::Load { path, zip } = cmd {
+            self.load(path, zip)?;
+            // answered once loaded
+            if let Some(old) = self.load_resp.replace(resp) {
+                let _ = old.send(json!({ "ok": false, "error": "superseded by another load" }));
+            }
+            return Ok(());
+        }
+        if let RemoteCommand::Status = cmd {
+            let _ = resp.send(self.status(tm));
+            return Ok(());
+        }
+        let game = self.game.as_mut().ok_or_else(|| anyhow!("no chart loaded"))?;
+        match cmd {
+            RemoteCommand::Play => game.play(tm)?,
+            RemoteCommand::Pause => Scene::pause(game, tm)?,
+            RemoteCommand::Seek { time } => game.seek(tm, time)?,
+            RemoteCommand::Speed { speed } => {
+                if !(0.1..=2.).contains(&speed) {
+                    return Err(anyhow!("speed out of range"));
+                }
+                game.set_speed(tm, speed)?;
+            }
+            RemoteCommand::Mods { mods } => {
+                game.res.config.mods = mods;
+                self.config.mods = mods;
+            }
+            RemoteCommand::Load { .. } | RemoteCommand::Status => unreachable!(),
+        }
+        let _ = resp.send(json!({ "ok": true }));
+        Ok(())
+    }
+}
+
+impl Scene for RemoteScene {
+    fn enter(&mut self, tm: &mut TimeManager, target: Option<RenderTarget>) -> Result<()> {
+        self.target = target;
+        if let Some(game) = &mut self.game {
+            game.enter(tm, target)?;
+        }
+        Ok(())
+    }
+
+    fn touch(&mut self, tm: &mut TimeManager, touch: &Touch) -> Result<bool> {
+        match &mut self.game {
+            Some(game) => game.touch(tm, touch),
+            None => Ok(false),
+        }
+    }
+
+    fn update(&mut self, tm: &mut TimeManager) -> Result<()> {
+        while let Ok((cmd, resp)) = self.rx.try_recv() {
+            if let Err(err) = self.handle(tm, cmd, resp.clone()) {
+                let _ = resp.send(json!({ "ok": false, "error": format!("{err:?}") }));
+            }
+        }
+        if let Some(task) = &mut self.load_task {
+            if let Some(res) = poll_future(task.as_mut()) {
+                self.load_task = None;
+                let resp = match res {
+                    Ok(mut game) => {
+                        game.show_ending = false;
+                        game.enter(tm, self.target)?;
+                        self.game = Some(game);
+                        json!({ "ok": true })
+                    }
+                    Err(err) => json!({ "ok": false, "error": format!("{err:?}") }),
+                };
+                if let Some(sender) = self.load_resp.take() {
+                    let _ = sender.send(resp);
+                }
+            }
+        }
+        if let Some(game) = &mut self.game {
+            game.update(tm)?;
+        }
+        Ok(())
+    }
+
+    fn render(&mut self, tm: &mut TimeManager, ui: &mut Ui) -> Result<()> {
+        if let Some(game) = &mut self.game {
+            return game.render(tm, ui);
+        }
+        let mut cam = ui.camera();
+        cam.render_target = self.target;
+        set_camera(&cam);
+        clear_background(BLACK);
+        let text = if self.load_task.is_some() {
+            "Loading...".to_owned()
+        } else {
+            format!("Waiting for commands on 127.0.0.1:{}", self.port)
+        };
+        ui.text(text).pos(0., 0.).anchor(0.5, 0.5).size(0.6).color(semi_white(0.7)).draw();
+        Ok(())
+    }
+
+    fn next_scene(&mut self, tm: &mut TimeManager) -> NextScene {
+        let Some(game) = &mut self.game else {
+            return NextScene::None;
+        };
+        match game.next_scene(tm) {
+            NextScene::None => {}
+            NextScene::Pop => {
+                // quitting from the pause menu unloads the chart
+                self.game = None;
+            }
+            _ => {
+                // the chart has ended, stays there instead of showing the result
+                let end = game.res.track_length;
+                if let Err(err) = Scene::pause(game, tm).and_then(|_| game.seek(tm, end)) {
+                    warn!("failed to stop at the end: {err:?}");
+                }
+            }
+        }
+        NextScene::None
+    }
+}
@@ -0,0 +1,60 @@
+//! Offscreen rendering of single frames, e.g. for chart thumbnails.
+
+use crate::{
+    config::Config,
+    fs::{load_info, FileSystem},
+    scene::{GameMode, LoadingScene, Scene},
+    time::TimeManager,
+    ui::{FontArc, TextPainter, Ui},
+};
+use anyhow::Result;
+use image::RgbaImage;
+use macroquad::prelude::*;
+use std::{cell::Cell, rc::Rc};
+
+/// Renders the chart at the given chart time (in seconds, offsets excluded) into an offscreen target of `size`.
+///
+/// A GL context is still required, and with it a window and a display, but nothing gets drawn to the window. On machines without
+/// a display, run under a virtual one such as `xvfb-run`.
+pub async fn snapshot(mut fs: Box<dyn FileSystem>, config: &Config, time: f32, size: (u32, u32)) -> Result<RgbaImage> {
+    // chart loading relies on tokio, which the caller may not have set up
+    let rt = match tokio::runtime::Handle::try_current() {
+        Ok(_) => None,
+        Err(_) => Some(tokio::runtime::Builder::new_multi_thread().worker_threads(2).enable_all().build()?),
+    };
+    let _guard = rt.as_ref().map(|it| it.enter());
+
+    let font = FontArc::try_from_vec(load_file("font.ttf").await?)?;
+    let mut painter = TextPainter::new(font);
+
+    let mut config = config.clone();
+    config.disable_audio = true;
+    let info = load_info(fs.as_mut()).await?;
+    let mut loading = LoadingScene::new(GameMode::View, info, &config, fs, None, None, None).await?;
+    let mut game = loading.load_task.take().unwrap().await?;
+
+    let (w, h) = size;
+    let target = render_target(w, h);
+    target.texture.set_filter(FilterMode::Linear);
+
+    let clock = Rc::new(Cell::new(0.));
+    let mut tm = TimeManager::manual(Box::new({
+        let clock = Rc::clone(&clock);
+        move || clock.get()
+    }));
+    game.enter(&mut tm, Some(target))?;
+    Scene::pause(&mut game, &mut tm)?;
+    game.seek(&mut tm, time + game.offset())?;
+    game.update(&mut tm)?;
+
+    let mut ui = Ui::new(&mut painter, Some((0, 0, w as i32, h as i32)));
+    game.render(&mut tm, &mut ui)?;
+    drop(ui);
+    unsafe { get_internal_gl() }.flush();
+
+    let data = target.texture.get_texture_data();
+    let mut image = RgbaImage::from_raw(w, h, data.bytes).unwrap();
+    // GL textures are stored bottom-up
+    image::imageops::flip_vertical_in_place(&mut image);
+    Ok(image)
+}
@@ -164,3 +164,16 @@ impl IntoShading for (Texture2D, Rect, ScaleType, Color) {
         }
     }
 }
+
+// the texture, its source rect in UV coordinates, the destination rect and the tint
+impl IntoShading for (Texture2D, Rect, Rect, Color) {
+    type Target = TextureShading;
+
+    fn into_shading(self) -> Self::Target {
+        let (tex, source, rect, color) = self;
+        TextureShading {
+            texture: (tex, source, rect),
+            color,
+        }
+    }
+}
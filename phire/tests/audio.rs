@@ -0,0 +1,46 @@
+//! Tests for the music analysis driving audio-reactive effects.
+
+use phire::audio::{MusicFeatures, SPECTRUM_BANDS};
+use sasa::Frame;
+use std::f32::consts::PI;
+
+const SAMPLE_RATE: u32 = 44100;
+
+fn sine(frequency: f32, amplitude: f32, seconds: f32) -> Vec<Frame> {
+    (0..(seconds * SAMPLE_RATE as f32) as usize)
+        .map(|i| {
+            let x = amplitude * (2. * PI * frequency * i as f32 / SAMPLE_RATE as f32).sin();
+            let mut frame = Frame::default();
+            frame.0 = x;
+            frame.1 = x;
+            frame
+        })
+        .collect()
+}
+
+#[test]
+fn sine_features() {
+    // exactly on the 32nd frequency bin, which falls in the fifth band
+    let frequency = 32. * SAMPLE_RATE as f32 / 1024.;
+    let frames = sine(frequency, 0.5, 1.);
+    let features = MusicFeatures::analyze(&frames, SAMPLE_RATE, 0.5, 3.25);
+    assert!((features.rms - 0.5 / 2f32.sqrt()).abs() < 0.01, "rms {}", features.rms);
+    assert_eq!(features.beat, 3.25);
+    for (band, value) in features.spectrum.iter().enumerate() {
+        if band == 4 {
+            assert!((value - 0.5).abs() < 0.05, "band {band}: {value}");
+        } else {
+            assert!(*value < 0.05, "band {band}: {value}");
+        }
+    }
+}
+
+#[test]
+fn silence_outside_music() {
+    let frames = sine(440., 1., 0.5);
+    for time in [-1., 2.] {
+        let features = MusicFeatures::analyze(&frames, SAMPLE_RATE, time, 0.);
+        assert_eq!(features.rms, 0.);
+        assert_eq!(features.spectrum, [0.; SPECTRUM_BANDS]);
+    }
+}
@@ -0,0 +1,108 @@
+//! Tests for the chart transformations in `phire::chart_ops`.
+
+use phire::{
+    chart_ops::{change_rate, extract, mirror, shift},
+    core::{AnimFloat, Chart, ChartExtra},
+    parse::parse_pec,
+};
+
+const EPS: f32 = 1e-4;
+
+// notes at 1s, 2s (two of them) and 5s, as the BPM halves at 2s
+fn pec() -> Chart {
+    parse_pec(include_str!("parse/pec.pec"), ChartExtra::default()).unwrap()
+}
+
+fn assert_close(actual: f32, expected: f32) {
+    assert!((actual - expected).abs() < EPS, "expected {expected}, got {actual}");
+}
+
+fn note_times(chart: &Chart) -> Vec<f32> {
+    let mut times: Vec<_> = chart.lines.iter().flat_map(|it| it.notes.iter().map(|note| note.time)).collect();
+    times.sort_by(f32::total_cmp);
+    times
+}
+
+fn assert_times(chart: &Chart, expected: &[f32]) {
+    let times = note_times(chart);
+    assert_eq!(times.len(), expected.len(), "expected {expected:?}, got {times:?}");
+    for (actual, expected) in times.into_iter().zip(expected) {
+        assert_close(actual, *expected);
+    }
+}
+
+fn value_at(anim: &mut AnimFloat, time: f32) -> f32 {
+    anim.set_time(time);
+    anim.now()
+}
+
+#[test]
+fn rate() {
+    let mut chart = pec();
+    let offset = chart.offset;
+    change_rate(&mut chart, 2.).unwrap();
+    assert_times(&chart, &[0.5, 1., 1., 2.5]);
+    assert_close(chart.offset, offset / 2.);
+    // beats stay where they were
+    let bpm = chart.bpm_list.get_mut();
+    assert_close(bpm.time_beats(4.), 1.);
+    assert_close(bpm.time_beats(7.), 2.5);
+    let translation = &mut chart.lines[0].object.translation.0;
+    let (before, after) = (value_at(&mut pec().lines[0].object.translation.0, 1.), value_at(translation, 0.5));
+    assert_close(after, before);
+}
+
+#[test]
+fn invalid_rate() {
+    let mut chart = pec();
+    for rate in [0., -1., f32::NAN, f32::INFINITY] {
+        assert!(change_rate(&mut chart, rate).is_err(), "rate {rate}");
+    }
+    assert_times(&chart, &note_times(&pec()));
+}
+
+#[test]
+fn shift_later() {
+    let mut chart = pec();
+    shift(&mut chart, 1.5).unwrap();
+    assert_times(&chart, &[2.5, 3.5, 3.5, 6.5]);
+    assert_close(chart.bpm_list.get_mut().time_beats(4.), 3.5);
+}
+
+#[test]
+fn shift_before_start() {
+    let mut chart = pec();
+    assert!(shift(&mut chart, -1.5).is_err());
+    assert_times(&chart, &note_times(&pec()));
+    shift(&mut chart, -1.).unwrap();
+    assert_times(&chart, &[0., 1., 1., 4.]);
+}
+
+#[test]
+fn extract_section() {
+    let mut chart = pec();
+    extract(&mut chart, 1.5..4.).unwrap();
+    assert_times(&chart, &[0.5, 0.5]);
+    // keyframes around the bounds are kept, so lines are where they were at the start of the range
+    let expected = value_at(&mut pec().lines[0].object.translation.0, 1.5);
+    assert_close(value_at(&mut chart.lines[0].object.translation.0, 0.), expected);
+    // only the BPM changes within the range are left, counting beats from its start
+    assert_eq!(chart.bpm_list.get_mut().changes(), vec![(0., 120.), (0.5, 60.)]);
+    assert_close(chart.bpm_list.get_mut().time_beats(1.), 0.5);
+
+    assert!(extract(&mut pec(), 3.0..3.0).is_err());
+}
+
+#[test]
+fn mirror_notes() {
+    let mut chart = pec();
+    mirror(&mut chart);
+    let original = pec();
+    for (line, original) in chart.lines.iter().zip(&original.lines) {
+        let mut xs: Vec<_> = line.notes.iter().map(|it| it.object.translation.0.now()).collect();
+        let mut expected: Vec<_> = original.notes.iter().map(|it| -it.object.translation.0.now()).collect();
+        xs.sort_by(f32::total_cmp);
+        expected.sort_by(f32::total_cmp);
+        assert_eq!(xs, expected);
+    }
+}
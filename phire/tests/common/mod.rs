@@ -0,0 +1,51 @@
+//! Helpers shared by the integration tests.
+
+#![allow(dead_code)]
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use image::{ImageOutputFormat, RgbaImage};
+use phire::fs::FileSystem;
+use std::{any::Any, collections::HashMap, future::Future, io::Cursor};
+
+/// A file system holding all of its files in memory, keyed by path.
+#[derive(Clone, Default)]
+pub struct MemoryFileSystem(pub HashMap<String, Vec<u8>>);
+
+#[async_trait]
+impl FileSystem for MemoryFileSystem {
+    async fn load_file(&mut self, path: &str) -> Result<Vec<u8>> {
+        self.0.get(path).cloned().with_context(|| format!("no such file: {path}"))
+    }
+
+    async fn exists(&mut self, path: &str) -> Result<bool> {
+        Ok(self.0.contains_key(path))
+    }
+
+    fn list_root(&self) -> Result<Vec<String>> {
+        Ok(self.0.keys().cloned().collect())
+    }
+
+    fn clone_box(&self) -> Box<dyn FileSystem> {
+        Box::new(self.clone())
+    }
+
+    fn as_any(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+pub fn block_on<T>(future: impl Future<Output = T>) -> T {
+    tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(future)
+}
+
+pub fn encode(image: &RgbaImage, format: ImageOutputFormat) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    image.write_to(&mut Cursor::new(&mut bytes), format).unwrap();
+    bytes
+}
+
+/// A transparent PNG of the given size.
+pub fn png(width: u32, height: u32) -> Vec<u8> {
+    encode(&RgbaImage::new(width, height), ImageOutputFormat::Png)
+}
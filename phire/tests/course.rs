@@ -0,0 +1,15 @@
+//! Tests for reading course files.
+
+use phire::scene::CourseInfo;
+
+#[test]
+fn course_file() {
+    let course = CourseInfo::from_yaml("name: Warmup\ncharts:\n  - custom/a#0\n  - custom/b#1\n").unwrap();
+    assert_eq!(course.name, "Warmup");
+    assert_eq!(course.charts, ["custom/a#0", "custom/b#1"]);
+
+    // the name is optional, but a course needs charts
+    assert!(CourseInfo::from_yaml("charts: [custom/a#0]").unwrap().name.is_empty());
+    assert!(CourseInfo::from_yaml("name: Empty").is_err());
+    assert!(CourseInfo::from_yaml("- custom/a#0").is_err());
+}
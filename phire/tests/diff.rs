@@ -0,0 +1,114 @@
+//! Tests for the chart comparison in `phire::diff`.
+
+use phire::{
+    core::{Chart, ChartExtra, Keyframe},
+    diff::{ChartDiff, DEFAULT_TOLERANCE, MAX_RETIME_SHIFT},
+    info::ChartInfo,
+    parse::parse_pec,
+};
+
+// notes at 1s and 2s on the first line, and at 2s and 5s on the second
+fn pec() -> Chart {
+    parse_pec(include_str!("parse/pec.pec"), ChartExtra::default()).unwrap()
+}
+
+fn diff(new: &Chart) -> ChartDiff {
+    ChartDiff::new(&pec(), new, DEFAULT_TOLERANCE)
+}
+
+#[test]
+fn identical() {
+    let diff = diff(&pec());
+    assert!(diff.is_empty());
+    assert_eq!(diff.to_string().lines().count(), 1);
+}
+
+#[test]
+fn retimed_note() {
+    let mut chart = pec();
+    let note = chart.lines[1].notes.iter_mut().max_by(|a, b| a.time.total_cmp(&b.time)).unwrap();
+    let time = note.time;
+    note.time += 0.5;
+    let diff = diff(&chart);
+    assert_eq!(diff.lines.len(), 1);
+    let line = &diff.lines[0];
+    assert_eq!(line.line, 1);
+    assert!(line.added.is_empty() && line.removed.is_empty());
+    assert_eq!(line.retimed.len(), 1);
+    assert_eq!((line.retimed[0].note.time, line.retimed[0].new_time), (time, time + 0.5));
+}
+
+#[test]
+fn distant_note_not_retimed() {
+    let mut chart = pec();
+    let note = chart.lines[1].notes.iter_mut().max_by(|a, b| a.time.total_cmp(&b.time)).unwrap();
+    let time = note.time;
+    note.time += MAX_RETIME_SHIFT + 1.;
+    let diff = diff(&chart);
+    let line = &diff.lines[0];
+    assert!(line.retimed.is_empty());
+    assert_eq!(line.removed.len(), 1);
+    assert_eq!(line.removed[0].time, time);
+    assert_eq!(line.added.len(), 1);
+    assert_eq!(line.added[0].time, time + MAX_RETIME_SHIFT + 1.);
+}
+
+#[test]
+fn added_and_removed_notes() {
+    let mut chart = pec();
+    let note = chart.lines[0].notes.remove(0);
+    let removed = diff(&chart);
+    assert_eq!(removed.lines[0].removed.len(), 1);
+    assert_eq!(removed.lines[0].removed[0].time, note.time);
+    assert!(removed.lines[0].added.is_empty() && removed.lines[0].retimed.is_empty());
+
+    let added = ChartDiff::new(&chart, &pec(), DEFAULT_TOLERANCE);
+    assert_eq!(added.lines[0].added.len(), 1);
+    assert_eq!(added.lines[0].added[0].time, note.time);
+    assert!(added.lines[0].removed.is_empty() && added.lines[0].retimed.is_empty());
+}
+
+#[test]
+fn within_tolerance() {
+    let mut chart = pec();
+    chart.lines[0].notes[0].time += DEFAULT_TOLERANCE / 2.;
+    chart.offset += DEFAULT_TOLERANCE / 2.;
+    assert!(diff(&chart).is_empty());
+}
+
+#[test]
+fn changed_events() {
+    let mut chart = pec();
+    chart.lines[0].object.alpha.keyframes.push(Keyframe::new(3., 0., 0));
+    let diff = diff(&chart);
+    let events = &diff.lines[0].events;
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].event, "alpha");
+    assert_eq!(events[0].new_keyframes, events[0].old_keyframes + 1);
+    assert_eq!(events[0].first_difference, 3.);
+}
+
+#[test]
+fn changed_info() {
+    let old = ChartInfo::default();
+    let new = ChartInfo {
+        name: "Renamed".to_owned(),
+        ..ChartInfo::default()
+    };
+    let diff = diff(&pec()).with_info(&old, &new).unwrap();
+    assert_eq!(diff.info.len(), 1);
+    assert_eq!(diff.info[0].field, "name");
+    assert_eq!(diff.info[0].new, "Renamed");
+    assert!(!diff.is_empty());
+}
+
+#[test]
+fn json() {
+    let mut chart = pec();
+    chart.lines[1].notes.clear();
+    let value = serde_json::to_value(diff(&chart)).unwrap();
+    assert_eq!(value["lines"][0]["line"], 1);
+    assert_eq!(value["lines"][0]["removed"].as_array().unwrap().len(), 2);
+    // unchanged parts are left out
+    assert!(value.get("offset").is_none());
+}
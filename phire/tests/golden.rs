@@ -0,0 +1,202 @@
+//! Golden-image tests for the renderer.
+//!
+//! Every case renders a fixture chart from `tests/golden/fixtures` at a fixed chart time and compares it against
+//! `tests/golden/expected/<case>.png`. A missing golden fails the case. Goldens are written by running with
+//! `UPDATE_GOLDEN=1` on a machine with a display (which also rewrites all of them after an intended change), and are to
+//! be committed along with the case or change that needs them. On mismatch or a missing golden, the actual frame (and a
+//! diff image) are saved under the cargo target directory.
+//!
+//! A GL context is required. On headless machines, run under a virtual display with software rendering, e.g.
+//! `LIBGL_ALWAYS_SOFTWARE=1 xvfb-run cargo test -p phire --test golden`. Without a display the test is skipped, unless
+//! `CI` is set, in which case it fails so that a misconfigured runner doesn't silently pass.
+
+mod common;
+
+use anyhow::{bail, Result};
+use common::{encode, MemoryFileSystem};
+use image::{codecs::gif::GifEncoder, Delay, Frame, ImageOutputFormat, Rgba, RgbaImage};
+use macroquad::Window;
+use phire::{build_conf, config::Config, core::Effect, snapshot::snapshot};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+const SIZE: (u32, u32) = (480, 270);
+
+// a pixel differs if any channel is off by more than this
+const CHANNEL_TOLERANCE: u8 = 8;
+// and the frame differs if more than this portion of pixels differ
+const PIXEL_TOLERANCE: f64 = 0.002;
+
+struct Case {
+    name: String,
+    fixture: &'static str,
+    time: f32,
+    extra: Option<String>,
+}
+
+fn cases() -> Vec<Case> {
+    let mut cases = Vec::new();
+    for (fixture, times) in [("rpe", &[0.5, 2.0, 2.5, 4.0][..]), ("pec", &[1.0, 2.5]), ("pgr", &[1.0, 4.5])] {
+        for &time in times {
+            cases.push(Case {
+                name: format!("{fixture}-{time:.1}"),
+                fixture,
+                time,
+                extra: None,
+            });
+        }
+    }
+    let presets = Effect::preset_names()
+        .map(|name| (name.to_owned(), name.to_owned()))
+        .chain(Effect::rpe_preset_names().map(|name| (name.to_owned(), format!("/{name}.glsl"))));
+    for (name, shader) in presets {
+        cases.push(Case {
+            name: format!("shader-{name}"),
+            fixture: "rpe",
+            time: 2.5,
+            extra: Some(format!(r#"{{"bpm":120,"effects":[{{"start":[0,0,1],"end":[100,0,1],"shader":"{shader}"}}]}}"#)),
+        });
+    }
+    cases
+}
+
+fn root() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden")
+}
+
+fn silent_wav(secs: u32) -> Vec<u8> {
+    const RATE: u32 = 8000;
+    let len = RATE * secs;
+    let mut bytes = Vec::new();
+    bytes.extend(b"RIFF");
+    bytes.extend((36 + len).to_le_bytes());
+    bytes.extend(b"WAVEfmt ");
+    bytes.extend(16u32.to_le_bytes());
+    bytes.extend(1u16.to_le_bytes()); // PCM
+    bytes.extend(1u16.to_le_bytes()); // mono
+    bytes.extend(RATE.to_le_bytes());
+    bytes.extend(RATE.to_le_bytes());
+    bytes.extend(1u16.to_le_bytes());
+    bytes.extend(8u16.to_le_bytes());
+    bytes.extend(b"data");
+    bytes.extend(len.to_le_bytes());
+    bytes.resize(bytes.len() + len as usize, 128);
+    bytes
+}
+
+// resources shared by fixtures, generated so that they don't have to be checked in
+fn generated_files() -> Result<HashMap<String, Vec<u8>>> {
+    let illustration = RgbaImage::from_fn(160, 90, |x, y| Rgba([(x * 255 / 160) as u8, (y * 255 / 90) as u8, 160, 255]));
+    let texture = RgbaImage::from_fn(64, 16, |x, y| if (x / 8 + y / 8) % 2 == 0 { Rgba([255, 255, 255, 255]) } else { Rgba([255, 80, 80, 160]) });
+    let mut gif = Vec::new();
+    {
+        let mut encoder = GifEncoder::new(&mut gif);
+        for color in [[80, 255, 80, 255], [80, 80, 255, 255], [255, 255, 80, 255]] {
+            let frame = RgbaImage::from_pixel(32, 32, Rgba(color));
+            encoder.encode_frame(Frame::from_parts(frame, 0, 0, Delay::from_numer_denom_ms(500, 1)))?;
+        }
+    }
+    Ok(HashMap::from([
+        ("music.wav".to_owned(), silent_wav(10)),
+        ("illustration.png".to_owned(), encode(&illustration, ImageOutputFormat::Png)),
+        ("texture.png".to_owned(), encode(&texture, ImageOutputFormat::Png)),
+        ("line.gif".to_owned(), gif),
+    ]))
+}
+
+fn fixture_fs(fixture: &str, generated: &HashMap<String, Vec<u8>>, extra: Option<&str>) -> Result<MemoryFileSystem> {
+    let mut files = generated.clone();
+    for entry in std::fs::read_dir(root().join("fixtures").join(fixture))? {
+        let entry = entry?;
+        files.insert(entry.file_name().to_string_lossy().into_owned(), std::fs::read(entry.path())?);
+    }
+    if let Some(extra) = extra {
+        files.insert("extra.json".to_owned(), extra.as_bytes().to_vec());
+    }
+    Ok(MemoryFileSystem(files))
+}
+
+/// Returns the portion of differing pixels, along with an image highlighting them over the dimmed expected frame.
+fn compare(expected: &RgbaImage, actual: &RgbaImage) -> (f64, RgbaImage) {
+    let mut diff = RgbaImage::new(expected.width(), expected.height());
+    let mut count = 0;
+    for ((e, a), d) in expected.pixels().zip(actual.pixels()).zip(diff.pixels_mut()) {
+        if e.0.iter().zip(a.0.iter()).any(|(x, y)| x.abs_diff(*y) > CHANNEL_TOLERANCE) {
+            count += 1;
+            *d = Rgba([255, 0, 0, 255]);
+        } else {
+            let l = ((e[0] as u32 + e[1] as u32 + e[2] as u32) / 3 / 4) as u8;
+            *d = Rgba([l, l, l, 255]);
+        }
+    }
+    (count as f64 / (expected.width() * expected.height()) as f64, diff)
+}
+
+async fn run_case(case: &Case, generated: &HashMap<String, Vec<u8>>, update: bool) -> Result<()> {
+    let fs = fixture_fs(case.fixture, generated, case.extra.as_deref())?;
+    let actual = snapshot(Box::new(fs), &Config::default(), case.time, SIZE).await?;
+    let path = root().join("expected").join(format!("{}.png", case.name));
+    if update {
+        std::fs::create_dir_all(path.parent().unwrap())?;
+        actual.save(&path)?;
+        println!("golden {}: written", case.name);
+        return Ok(());
+    }
+    let out = Path::new(env!("CARGO_TARGET_TMPDIR")).join("golden");
+    std::fs::create_dir_all(&out)?;
+    if !path.exists() {
+        actual.save(out.join(format!("{}-actual.png", case.name)))?;
+        bail!("no golden at {}, run with UPDATE_GOLDEN=1 to write it, see {}", path.display(), out.display());
+    }
+    let expected = image::open(&path)?.into_rgba8();
+    if expected.dimensions() != actual.dimensions() {
+        bail!("size mismatch: expected {:?}, got {:?}", expected.dimensions(), actual.dimensions());
+    }
+    let (portion, diff) = compare(&expected, &actual);
+    if portion > PIXEL_TOLERANCE {
+        actual.save(out.join(format!("{}-actual.png", case.name)))?;
+        diff.save(out.join(format!("{}-diff.png", case.name)))?;
+        bail!("{:.2}% of pixels differ, see {}", portion * 100., out.display());
+    }
+    println!("golden {}: ok", case.name);
+    Ok(())
+}
+
+fn main() {
+    if cfg!(target_os = "linux") && std::env::var_os("DISPLAY").is_none() && std::env::var_os("WAYLAND_DISPLAY").is_none() {
+        if std::env::var_os("CI").is_some() {
+            eprintln!("golden: no display available, but CI requires these tests to run");
+            std::process::exit(1);
+        }
+        println!("golden: no display available, skipping");
+        return;
+    }
+    let update = std::env::var("UPDATE_GOLDEN").map_or(false, |it| it == "1");
+    let filter = std::env::args().skip(1).find(|it| !it.starts_with('-'));
+
+    phire::core::init_assets();
+    let mut conf = build_conf();
+    conf.window_title = "golden".to_owned();
+    conf.window_width = SIZE.0 as _;
+    conf.window_height = SIZE.1 as _;
+    Window::from_config(conf, async move {
+        let generated = generated_files().unwrap();
+        let mut failed = Vec::new();
+        for case in cases() {
+            if filter.as_ref().map_or(false, |it| !case.name.contains(it.as_str())) {
+                continue;
+            }
+            if let Err(err) = run_case(&case, &generated, update).await {
+                println!("golden {}: FAILED\n{err:?}", case.name);
+                failed.push(case.name);
+            }
+        }
+        if failed.is_empty() {
+            std::process::exit(0);
+        }
+        println!("\nfailed cases: {}", failed.join(", "));
+        std::process::exit(1);
+    });
+}
@@ -0,0 +1,35 @@
+//! Tests for loading chart infos from the supported info files.
+
+mod common;
+
+use common::{block_on, MemoryFileSystem};
+use phire::fs::{load_info, load_infos};
+
+fn package(name: &str, content: &str) -> MemoryFileSystem {
+    let mut fs = MemoryFileSystem::default();
+    fs.0.insert(name.to_owned(), content.as_bytes().to_vec());
+    fs
+}
+
+#[test]
+fn csv_rows() {
+    let mut fs = package(
+        "info.csv",
+        "Chart,Music,Image,Name,Level\nez.json,song.ogg,bg.png,Song,EZ Lv.3\nhd.json,song.ogg,bg.png,Song,HD Lv.9\n",
+    );
+    // the last row has always been the default, so it's listed first
+    let infos = block_on(load_infos(&mut fs)).unwrap();
+    assert_eq!(infos.iter().map(|it| it.chart.as_str()).collect::<Vec<_>>(), ["hd.json", "ez.json"]);
+    assert_eq!(infos[0].difficulty, 9.);
+    assert_eq!(block_on(load_info(&mut fs)).unwrap().chart, "hd.json");
+}
+
+#[test]
+fn yaml_list() {
+    let mut fs = package("info.yml", "- name: Song\n  chart: ez.json\n  level: EZ Lv.3\n- name: Song\n  chart: hd.json\n  level: HD Lv.9\n");
+    assert_eq!(block_on(load_infos(&mut fs)).unwrap().len(), 2);
+    assert_eq!(block_on(load_info(&mut fs)).unwrap().chart, "ez.json");
+
+    let mut fs = package("info.yml", "[]");
+    assert!(block_on(load_infos(&mut fs)).is_err());
+}
@@ -0,0 +1,263 @@
+//! Conformance tests for the chart parsers, driven by the fixtures in `tests/parse`.
+
+mod common;
+
+use common::{block_on, MemoryFileSystem};
+use phire::{
+    bin::{BinaryReader, BinaryWriter},
+    core::{AnimFloat, BezierTween, BpmList, Chart, ChartExtra, JudgeLine, Note, NoteKind, TweenFunction},
+    judge::HitSound,
+    parse::{parse_extra, parse_pec, parse_phigros, parse_rpe},
+};
+use std::io::Cursor;
+
+const EPS: f32 = 1e-4;
+
+fn rpe() -> Chart {
+    block_on(parse_rpe(include_str!("parse/rpe.json"), &mut MemoryFileSystem::default(), ChartExtra::default())).unwrap()
+}
+
+fn pec() -> Chart {
+    parse_pec(include_str!("parse/pec.pec"), ChartExtra::default()).unwrap()
+}
+
+fn pgr() -> Chart {
+    parse_phigros(include_str!("parse/pgr.json"), ChartExtra::default()).unwrap()
+}
+
+fn assert_close(actual: f32, expected: f32) {
+    assert!((actual - expected).abs() < EPS, "expected {expected}, got {actual}");
+}
+
+// notes are reordered for rendering, so compare them in time order
+fn notes(line: &JudgeLine) -> Vec<&Note> {
+    let mut notes: Vec<_> = line.notes.iter().collect();
+    notes.sort_by(|a, b| a.time.total_cmp(&b.time).then(a.object.translation.0.now().total_cmp(&b.object.translation.0.now())));
+    notes
+}
+
+fn kind_name(kind: &NoteKind) -> &'static str {
+    match kind {
+        NoteKind::Click => "click",
+        NoteKind::Hold { .. } => "hold",
+        NoteKind::Flick => "flick",
+        NoteKind::Drag => "drag",
+    }
+}
+
+fn hold_end(note: &Note) -> f32 {
+    match note.kind {
+        NoteKind::Hold { end_time, .. } => end_time,
+        _ => panic!("not a hold"),
+    }
+}
+
+fn value_at(anim: &mut AnimFloat, time: f32) -> f32 {
+    anim.set_time(time);
+    anim.now()
+}
+
+// reference cubic bezier through (0, 0), p1, p2 and (1, 1), solved for x by bisection
+fn bezier(p1: (f32, f32), p2: (f32, f32), x: f32) -> f32 {
+    let curve = |a: f32, b: f32, t: f32| 3. * (1. - t) * (1. - t) * t * a + 3. * (1. - t) * t * t * b + t * t * t;
+    let (mut lo, mut hi) = (0., 1.);
+    for _ in 0..64 {
+        let mid = (lo + hi) / 2.;
+        if curve(p1.0, p2.0, mid) < x {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+    curve(p1.1, p2.1, (lo + hi) / 2.)
+}
+
+#[test]
+fn bpm_list_conversions() {
+    let mut bpm = BpmList::new(vec![(0., 120.), (4., 60.)]);
+    assert_close(bpm.time_beats(2.), 1.);
+    assert_close(bpm.time_beats(4.), 2.);
+    assert_close(bpm.time_beats(6.), 4.);
+    // the cursor has to move back as well
+    assert_close(bpm.time_beats(1.), 0.5);
+    assert_close(bpm.beat(4.), 6.);
+    assert_close(bpm.beat(1.), 2.);
+    assert_close(bpm.now_bpm(3.), 60.);
+    assert_close(bpm.now_bpm(0.5), 120.);
+    for beats in [0., 0.5, 3.9, 4., 4.1, 10.] {
+        let time = bpm.time_beats(beats);
+        assert_close(bpm.beat(time), beats);
+    }
+}
+
+#[test]
+fn rpe_notes() {
+    let chart = rpe();
+    assert_close(chart.offset, 0.05);
+    assert_eq!(chart.lines.len(), 2);
+
+    let main = notes(&chart.lines[0]);
+    let kinds: Vec<_> = main.iter().map(|it| kind_name(&it.kind)).collect();
+    assert_eq!(kinds, ["click", "hold", "flick", "drag"]);
+    let times: Vec<_> = main.iter().map(|it| it.time).collect();
+    for (time, expected) in times.into_iter().zip([1., 2., 4., 5.]) {
+        assert_close(time, expected);
+    }
+    assert_close(hold_end(main[1]), 4.);
+    assert_close(main[1].object.translation.0.now(), -0.5);
+    assert_close(main[2].object.translation.0.now(), 0.5);
+    assert!(main[3].fake);
+    assert!(!main[0].fake);
+    assert!(matches!(main[2].hitsound, HitSound::Flick));
+
+    let second = notes(&chart.lines[1]);
+    assert_close(second[0].time, 2.);
+    assert_close(second[1].time, 6.);
+}
+
+#[test]
+fn rpe_events() {
+    let mut chart = rpe();
+    let object = &mut chart.lines[0].object;
+    // move X goes linearly from the left edge to the right one during the first 4 beats
+    assert_close(value_at(&mut object.translation.0, 0.), -1.);
+    assert_close(value_at(&mut object.translation.0, 1.), 0.);
+    assert_close(value_at(&mut object.translation.0, 2.), 1.);
+    // rotation is reversed, and spans beats 4 to 6 which are under 60 BPM
+    assert_close(value_at(&mut object.rotation, 2.), 0.);
+    assert_close(value_at(&mut object.rotation, 3.), -45.);
+    assert_close(value_at(&mut object.rotation, 4.), -90.);
+    assert_close(value_at(&mut chart.lines[1].object.translation.1, 0.), -0.5);
+}
+
+#[test]
+fn rpe_bezier() {
+    let mut chart = rpe();
+    let alpha = &mut chart.lines[0].object.alpha;
+    let tween = alpha.keyframes[0].tween.as_any().downcast_ref::<BezierTween>().expect("expected a bezier tween");
+    assert_eq!((tween.p1, tween.p2), ((0.1, 0.7), (0.4, 1.0)));
+    for x in [0.1, 0.25, 0.5, 0.75, 0.9] {
+        let expected = bezier((0.1, 0.7), (0.4, 1.0), x);
+        assert!((tween.y(x) - expected).abs() < 1e-3, "at {x}: expected {expected}, got {}", tween.y(x));
+        // alpha goes from 0 to 255 over 2 seconds, normalized to 0..1
+        assert!((value_at(alpha, x * 2.) - expected).abs() < 1e-3);
+    }
+}
+
+#[test]
+fn pec_notes_and_events() {
+    let mut chart = pec();
+    assert_close(chart.offset, 0.);
+
+    let main = notes(&chart.lines[0]);
+    assert_eq!(main.iter().map(|it| kind_name(&it.kind)).collect::<Vec<_>>(), ["click", "hold"]);
+    assert_close(main[0].time, 1.);
+    assert_close(main[0].speed, 1.5);
+    assert_close(main[1].time, 2.);
+    assert_close(hold_end(main[1]), 4.);
+    assert_close(main[1].object.translation.0.now(), 0.5);
+
+    let second = notes(&chart.lines[1]);
+    assert_eq!(second.iter().map(|it| kind_name(&it.kind)).collect::<Vec<_>>(), ["flick", "drag"]);
+    assert!(!second[0].above);
+    assert_close(second[0].object.translation.0.now(), -0.5);
+    assert_close(second[1].time, 5.);
+    assert!(second[1].fake);
+    assert_close(second[1].object.scale.0.now(), 1.2);
+
+    let object = &mut chart.lines[0].object;
+    assert_close(value_at(&mut object.translation.0, 0.), 0.);
+    assert_close(value_at(&mut object.translation.0, 1.), 0.5);
+    assert_close(value_at(&mut object.translation.1, 1.), 0.);
+    assert_close(value_at(&mut object.alpha, 1.), 1.);
+}
+
+#[test]
+fn pgr_notes_and_events() {
+    let mut chart = pgr();
+    assert_close(chart.offset, 0.1);
+    let line = &chart.lines[0];
+
+    let notes = notes(line);
+    assert_eq!(notes.iter().map(|it| kind_name(&it.kind)).collect::<Vec<_>>(), ["click", "flick", "hold", "drag"]);
+    let times: Vec<_> = notes.iter().map(|it| it.time).collect();
+    for (time, expected) in times.into_iter().zip([1., 2., 2., 4.]) {
+        assert_close(time, expected);
+    }
+    assert_close(hold_end(notes[2]), 3.);
+    assert!(notes[2].above);
+    assert!(!notes[1].above);
+    assert_close(notes[0].object.translation.0.now(), 9. / 80.);
+
+    let object = &mut chart.lines[0].object;
+    assert_close(value_at(&mut object.translation.0, 1.), 0.);
+    assert_close(value_at(&mut object.translation.0, 3.), 0.5);
+    assert_close(value_at(&mut object.translation.0, 5.), 1.);
+    assert_close(value_at(&mut object.translation.1, 3.), 0.);
+}
+
+#[test]
+fn multiple_hints() {
+    fn hinted(chart: &Chart) -> Vec<(usize, f32)> {
+        let mut res: Vec<_> = chart
+            .lines
+            .iter()
+            .enumerate()
+            .flat_map(|(id, line)| line.notes.iter().filter(|it| it.multiple_hint).map(move |it| (id, it.time)))
+            .collect();
+        res.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        res
+    }
+    // notes at the same time are hinted, across lines as well
+    assert_eq!(hinted(&rpe()), [(0, 2.), (1, 2.)]);
+    assert_eq!(hinted(&pec()), [(0, 2.), (1, 2.)]);
+    assert_eq!(hinted(&pgr()), [(0, 2.), (0, 2.)]);
+}
+
+fn to_pbc(chart: &Chart) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    BinaryWriter::new(&mut bytes).write(chart).unwrap();
+    bytes
+}
+
+#[test]
+fn pbc_round_trip() {
+    for (name, chart) in [("rpe", rpe()), ("pec", pec()), ("pgr", pgr())] {
+        let bytes = to_pbc(&chart);
+        let read: Chart = BinaryReader::new(Cursor::new(&bytes)).read().unwrap();
+        assert_eq!(to_pbc(&read), bytes, "{name}: PBC changed after a round trip");
+
+        assert_close(read.offset, chart.offset);
+        assert_eq!(read.lines.len(), chart.lines.len());
+        for (a, b) in chart.lines.iter().zip(read.lines.iter()) {
+            let (a, b) = (notes(a), notes(b));
+            assert_eq!(a.len(), b.len());
+            for (a, b) in a.into_iter().zip(b) {
+                assert_eq!(kind_name(&a.kind), kind_name(&b.kind));
+                // times are stored in milliseconds
+                assert!((a.time - b.time).abs() < 1e-3, "{name}: note at {} read back at {}", a.time, b.time);
+                assert_eq!((a.above, a.fake, a.multiple_hint), (b.above, b.fake, b.multiple_hint));
+            }
+        }
+    }
+}
+
+#[test]
+fn extra() {
+    let extra = block_on(parse_extra(r#"{"bpm": [{"time": [0, 0, 1], "bpm": 120}]}"#, &mut MemoryFileSystem::default())).unwrap();
+    assert!(extra.effects.is_empty() && extra.global_effects.is_empty() && extra.layer_effects.is_empty() && extra.videos.is_empty());
+    assert!(extra.emitters.is_empty());
+
+    let effect = |shader: &str| format!(r#"{{"bpm": 120, "effects": [{{"start": [0, 0, 1], "end": [4, 0, 1], "shader": "{shader}"}}]}}"#);
+    assert!(block_on(parse_extra(&effect("no-such-preset"), &mut MemoryFileSystem::default())).is_err());
+    assert!(block_on(parse_extra(&effect("/missing.glsl"), &mut MemoryFileSystem::default())).is_err());
+    assert!(block_on(parse_extra("{", &mut MemoryFileSystem::default())).is_err());
+
+    // an effect targets the whole screen, a line or a layer, but not several
+    let targeted = |target: &str| format!(r#"{{"bpm": 120, "effects": [{{"start": [0, 0, 1], "end": [4, 0, 1], "shader": "noise", {target}}}]}}"#);
+    assert!(block_on(parse_extra(&targeted(r#""line": 0, "layer": [0, 1]"#), &mut MemoryFileSystem::default())).is_err());
+    assert!(block_on(parse_extra(&targeted(r#""global": true, "line": 0"#), &mut MemoryFileSystem::default())).is_err());
+
+    let emitter = r#"{"bpm": 120, "emitters": [{"start": [0, 0, 1], "end": [4, 0, 1], "rate": 10, "texture": "/missing.png"}]}"#;
+    assert!(block_on(parse_extra(emitter, &mut MemoryFileSystem::default())).is_err());
+}
@@ -0,0 +1,17 @@
+//! Tests for the wire format of remote commands.
+
+use phire::{config::Mods, scene::RemoteCommand};
+
+fn parse(line: &str) -> RemoteCommand {
+    serde_json::from_str(line).unwrap()
+}
+
+#[test]
+fn mods_from_bits() {
+    let RemoteCommand::Mods { mods } = parse(r#"{"cmd": "mods", "mods": 5}"#) else { panic!("expected mods") };
+    assert_eq!(mods, Mods::AUTOPLAY | Mods::FADE_OUT);
+    // unknown bits are dropped
+    let RemoteCommand::Mods { mods } = parse(r#"{"cmd": "mods", "mods": 1048577}"#) else { panic!("expected mods") };
+    assert_eq!(mods, Mods::AUTOPLAY);
+    assert!(serde_json::from_str::<RemoteCommand>(r#"{"cmd": "mods", "mods": "AUTOPLAY"}"#).is_err());
+}
@@ -0,0 +1,101 @@
+//! Tests for the `info.yml` of resource packs and their validation.
+
+mod common;
+
+use common::{block_on, png, MemoryFileSystem};
+use phire::{
+    core::ResPackInfo,
+    respack::{contact_sheet, validate, Severity},
+};
+use std::collections::HashMap;
+
+fn pack(info: &str) -> MemoryFileSystem {
+    let mut files = HashMap::new();
+    files.insert("info.yml".to_owned(), info.as_bytes().to_vec());
+    for name in ["click", "drag", "flick"] {
+        files.insert(format!("{name}.png"), png(100, 10));
+        files.insert(format!("{name}_mh.png"), png(120, 20));
+    }
+    files.insert("hold.png".to_owned(), png(100, 200));
+    files.insert("hold_mh.png".to_owned(), png(120, 220));
+    files.insert("hit_fx.png".to_owned(), png(50, 60));
+    MemoryFileSystem(files)
+}
+
+const BASIC: &str = "
+name: Basic
+author: Someone
+hitFx: [5, 6]
+holdAtlas: [50, 50]
+holdAtlasMH: [0, 110]
+";
+
+#[test]
+fn defaults() {
+    let info: ResPackInfo = serde_yaml::from_str(BASIC).unwrap();
+    assert!(info.hit_fx_good.is_none());
+    assert_eq!(info.particles.count, 4);
+    assert!(info.particles.lifetime.is_none());
+    let animation = info.note_animation;
+    assert_eq!((animation.click, animation.drag, animation.flick), (1, 1, 1));
+    assert_eq!(animation.frame(animation.click, 12.3), macroquad::prelude::Rect::new(0., 0., 1., 1.));
+}
+
+#[test]
+fn extensions() {
+    let info: ResPackInfo = serde_yaml::from_str(&format!(
+        "{BASIC}
+hitFxGood: [4, 4]
+noteAnimation:
+  click: 4
+  fps: 2
+particles:
+  count: 8
+  spread: 90
+"
+    ))
+    .unwrap();
+    assert_eq!(info.hit_fx_good, Some((4, 4)));
+    assert_eq!((info.particles.count, info.particles.spread, info.particles.speed), (8, 90., 4.3));
+
+    let animation = info.note_animation;
+    assert_eq!((animation.click, animation.drag), (4, 1));
+    // two frames per second, looping over four frames
+    for (time, index) in [(0., 0.), (0.6, 1.), (1.9, 3.), (2.1, 0.), (-0.1, 3.)] {
+        let rect = animation.frame(animation.click, time);
+        assert_eq!((rect.x, rect.w), (index * 0.25, 0.25), "at {time}");
+    }
+}
+
+#[test]
+fn valid_pack() {
+    let mut fs = pack(BASIC);
+    let report = block_on(validate(&mut fs));
+    assert!(!report.has_errors(), "{report}");
+    assert_eq!(report.title.as_deref(), Some("Basic by Someone"));
+    // no sounds in the pack
+    assert!(report.issues.iter().any(|it| it.message.contains("click sound")));
+
+    let sheet = block_on(contact_sheet(&mut fs)).unwrap();
+    assert!(sheet.width() > 0 && sheet.height() > 0);
+}
+
+#[test]
+fn broken_pack() {
+    let mut fs = pack(&BASIC.replace("holdAtlas: [50, 50]", "holdAtlas: [150, 50]\ncolorGoodFx: [2, 0, 0, 1]"));
+    fs.0.remove("hit_fx.png");
+    let report = block_on(validate(&mut fs));
+    let errors: Vec<_> = report.issues.iter().filter(|it| it.severity == Severity::Error).map(|it| it.message.as_str()).collect();
+    assert_eq!(errors.len(), 2, "{report}");
+    assert!(errors.iter().any(|it| it.starts_with("hold.png")));
+    assert!(errors.iter().any(|it| it.contains("hit_fx.png")));
+    // the game clamps colors instead of refusing them
+    assert!(report
+        .issues
+        .iter()
+        .any(|it| it.severity == Severity::Warning && it.message.starts_with("colorGoodFx")));
+    assert!(block_on(contact_sheet(&mut fs)).is_err());
+
+    let report = block_on(validate(&mut MemoryFileSystem::default()));
+    assert!(report.has_errors() && report.title.is_none());
+}
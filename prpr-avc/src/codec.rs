@@ -3,6 +3,7 @@ use crate::{
 };
 use anyhow::{bail, Context, Result};
 use std::{
+    ffi::CString,
     ptr::null_mut,
     sync::{
         atomic::{AtomicI32, Ordering},
@@ -39,6 +40,18 @@ impl AVCodecRef {
             }
         }
     }
+
+    pub fn find_decoder_by_name(name: &str) -> Option<Self> {
+        let name = CString::new(name).ok()?;
+        unsafe {
+            let ptr = ffi::avcodec_find_decoder_by_name(name.as_ptr());
+            if ptr.is_null() {
+                None
+            } else {
+                Some(Self(ptr))
+            }
+        }
+    }
 }
 
 static EXPECTED_PIX_FMT_EDIT: Mutex<()> = Mutex::new(());
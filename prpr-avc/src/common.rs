@@ -75,8 +75,26 @@ impl Display for AVRational {
 #[derive(Debug, Clone, Copy)]
 pub struct AVPixelFormat(pub ffi::AVPixelFormat);
 impl AVPixelFormat {
-    pub const YUV420P: AVPixelFormat = AVPixelFormat(0);
-    pub const RGB24: AVPixelFormat = AVPixelFormat(2);
+    pub const YUV420P: AVPixelFormat = AVPixelFormat(ffi::AVPixelFormat_AV_PIX_FMT_YUV420P);
+    pub const RGB24: AVPixelFormat = AVPixelFormat(ffi::AVPixelFormat_AV_PIX_FMT_RGB24);
+    pub const YUVA420P: AVPixelFormat = AVPixelFormat(ffi::AVPixelFormat_AV_PIX_FMT_YUVA420P);
+
+    pub fn name(self) -> String {
+        unsafe {
+            let desc = ffi::av_pix_fmt_desc_get(self.0);
+            if desc.is_null() {
+                return format!("unknown ({})", self.0);
+            }
+            CStr::from_ptr((*desc).name).to_string_lossy().into_owned()
+        }
+    }
+
+    pub fn has_alpha(self) -> bool {
+        unsafe {
+            let desc = ffi::av_pix_fmt_desc_get(self.0);
+            !desc.is_null() && (*desc).flags & ffi::AV_PIX_FMT_FLAG_ALPHA != 0
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
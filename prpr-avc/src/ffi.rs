@@ -20,11 +20,13 @@ extern "C" {
     pub fn av_frame_alloc() -> *mut AVFrame;
     pub fn av_frame_free(frame: *mut *mut AVFrame);
     pub fn av_frame_get_buffer(frame: *mut AVFrame, align: ::std::os::raw::c_int) -> ::std::os::raw::c_int;
+    pub fn av_pix_fmt_desc_get(pix_fmt: AVPixelFormat) -> *const AVPixFmtDescriptor;
 }
 
 #[link(name = "avcodec", kind = "static")]
 extern "C" {
     pub fn avcodec_find_decoder(id: AVCodecID) -> *mut AVCodec;
+    pub fn avcodec_find_decoder_by_name(name: *const ::std::os::raw::c_char) -> *mut AVCodec;
     pub fn avcodec_alloc_context3(codec: *const AVCodec) -> *mut AVCodecContext;
     pub fn avcodec_free_context(avctx: *mut *mut AVCodecContext);
     pub fn avcodec_parameters_to_context(codec: *mut AVCodecContext, par: *const AVCodecParameters) -> ::std::os::raw::c_int;
@@ -79,7 +81,14 @@ pub type AVSampleFormat = ::std::os::raw::c_int;
 pub type AVCodec = c_void;
 pub type SwsContext = c_void;
 
+pub const AVCodecID_AV_CODEC_ID_VP8: AVCodecID = 139;
+pub const AVCodecID_AV_CODEC_ID_VP9: AVCodecID = 167;
+pub const AVPixelFormat_AV_PIX_FMT_YUV420P: AVPixelFormat = 0;
+pub const AVPixelFormat_AV_PIX_FMT_RGB24: AVPixelFormat = 2;
+pub const AVPixelFormat_AV_PIX_FMT_YUVA420P: AVPixelFormat = 33;
+
 pub const AV_ERROR_MAX_STRING_SIZE: u32 = 64;
+pub const AV_PIX_FMT_FLAG_ALPHA: u64 = 1 << 7;
 pub const SWS_BICUBIC: u32 = 4;
 
 #[repr(C)]
@@ -89,6 +98,17 @@ pub struct AVRational {
     pub den: ::std::os::raw::c_int,
 }
 
+// only the leading fields, as descriptors are never created on this side
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct AVPixFmtDescriptor {
+    pub name: *const ::std::os::raw::c_char,
+    pub nb_components: u8,
+    pub log2_chroma_w: u8,
+    pub log2_chroma_h: u8,
+    pub flags: u64,
+}
+
 #[repr(C)]
 #[derive(Debug, Copy, Clone)]
 pub struct AVIOInterruptCB {
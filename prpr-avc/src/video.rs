@@ -1,5 +1,5 @@
-use crate::{AVCodecContext, AVFormatContext, AVFrame, AVPacket, AVPixelFormat, AVRational, AVStreamRef, StreamFormat, SwsContext};
-use anyhow::{Context, Result};
+use crate::{ffi, AVCodecContext, AVCodecRef, AVFormatContext, AVFrame, AVPacket, AVPixelFormat, AVRational, AVStreamRef, StreamFormat, SwsContext};
+use anyhow::{bail, Context, Result};
 use std::{
     sync::{
         atomic::{AtomicBool, Ordering},
@@ -28,8 +28,27 @@ impl Video {
 
         let video_stream = format_ctx.streams().into_iter().find(|it| it.is_video()).context("no video")?;
 
-        let decoder = video_stream.find_decoder()?;
+        // ffmpeg's own VP8/VP9 decoders drop the alpha plane of WebM videos, only libvpx keeps it
+        let libvpx = match video_stream.codec_params().codec_id() {
+            _ if !pix_fmt.has_alpha() => None,
+            ffi::AVCodecID_AV_CODEC_ID_VP8 => Some(("libvpx", AVCodecRef::find_decoder_by_name("libvpx"))),
+            ffi::AVCodecID_AV_CODEC_ID_VP9 => Some(("libvpx-vp9", AVCodecRef::find_decoder_by_name("libvpx-vp9"))),
+            _ => None,
+        };
+        let uses_libvpx = libvpx.is_some();
+        let decoder = match libvpx {
+            Some((_, Some(decoder))) => decoder,
+            Some((name, None)) => bail!("alpha of VP8/VP9 videos needs the {name} decoder, which this build of ffmpeg lacks"),
+            None => video_stream.find_decoder()?,
+        };
         let mut codec_ctx = AVCodecContext::new(decoder, video_stream.codec_params(), Some(pix_fmt))?;
+        // otherwise the alpha plane would silently come out opaque
+        if pix_fmt.has_alpha() && !uses_libvpx {
+            let decoded = codec_ctx.stream_format().pix_fmt;
+            if !decoded.has_alpha() {
+                bail!("video has no alpha channel, as it's decoded to {}", decoded.name());
+            }
+        }
 
         let out_format = StreamFormat {
             pix_fmt,
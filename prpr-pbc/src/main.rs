@@ -1,25 +1,61 @@
 use anyhow::{anyhow, bail, Context, Result};
 use async_trait::async_trait;
+use macroquad::Window;
 use phire::{
     bin::{BinaryReader, BinaryWriter},
-    core::ChartExtra,
-    fs::FileSystem,
-    info::ChartFormat,
+    build_conf,
+    chart_ops::{change_rate, extract, mirror, shift},
+    config::Config,
+    core::{init_assets, Chart, ChartExtra},
+    diff::{ChartDiff, DEFAULT_TOLERANCE},
+    fs::{fs_from_file, load_info, FileSystem},
+    info::{ChartFormat, ChartInfo},
     parse::{parse_pec, parse_phigros, parse_rpe},
+    respack::{contact_sheet, validate},
+    scene::GameScene,
+    snapshot::snapshot,
 };
 use std::{
     any::Any,
     fs::File,
+    future::Future,
     io::{BufWriter, Cursor},
+    ops::Range,
+    path::Path,
 };
 
 const HELP: &'static str = "
 Usage: prpr-pbc [options] input output
+       prpr-pbc snapshot [options] chart time output
+       prpr-pbc diff [options] old new
+       prpr-pbc respack [options] pack
+
+Converts the chart to PBC, renders the chart at the given time (in seconds) to a PNG,
+lists what changed between two versions of a chart, or checks a resource pack folder or zip.
+
+Rendering needs a GL context, so snapshot briefly opens a window and requires a display.
+On headless machines, run it under a virtual display, e.g. xvfb-run prpr-pbc snapshot ...
 
 Options:
-    -h, --help  Display this message
+    -h, --help              Display this message
+        --extract <s>:<e>   Only keeps the part of the chart between the given times in seconds
+        --shift <seconds>   Moves everything in the chart later by the given time
+        --rate <rate>       Makes the chart play faster by the given rate, for music resampled accordingly
+        --mirror            Mirrors the chart horizontally
+        --size <WxH>        Snapshot size, defaults to 1280x720
+        --json              Print the diff as JSON
+        --tolerance <ms>    Time differences ignored by the diff, defaults to 1
+        --preview <png>     Also writes a contact sheet of the resource pack's notes and hit effects
 ";
 
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Command {
+    Convert,
+    Snapshot,
+    Diff,
+    Respack,
+}
+
 struct DummyFileSystem;
 #[async_trait]
 impl FileSystem for DummyFileSystem {
@@ -40,30 +76,207 @@ impl FileSystem for DummyFileSystem {
     }
 }
 
+// transformations applied when converting, in this order
+#[derive(Default)]
+struct Transforms {
+    extract: Option<Range<f32>>,
+    shift: Option<f32>,
+    rate: Option<f32>,
+    mirror: bool,
+}
+
+impl Transforms {
+    fn apply(self, chart: &mut Chart) -> Result<()> {
+        if let Some(range) = self.extract {
+            extract(chart, range).context("Failed to extract")?;
+        }
+        if let Some(delta) = self.shift {
+            shift(chart, delta).context("Failed to shift")?;
+        }
+        if let Some(rate) = self.rate {
+            change_rate(chart, rate).context("Failed to change rate")?;
+        }
+        if self.mirror {
+            mirror(chart);
+        }
+        Ok(())
+    }
+}
+
+fn parse_range(s: &str) -> Result<Range<f32>> {
+    let (start, end) = s.split_once(':').ok_or_else(|| anyhow!("Invalid time range: {s}"))?;
+    Ok(start.parse()?..end.parse()?)
+}
+
+fn parse_size(s: &str) -> Result<(u32, u32)> {
+    let (w, h) = s.split_once('x').ok_or_else(|| anyhow!("Invalid size: {s}"))?;
+    let size = (w.parse()?, h.parse()?);
+    if size.0 == 0 || size.1 == 0 {
+        bail!("Invalid size: {s}");
+    }
+    Ok(size)
+}
+
+// rendering needs a GL context, so snapshots are taken inside a window, which needs a display
+fn run_in_window(size: (u32, u32), task: impl Future<Output = Result<()>> + 'static) -> Result<()> {
+    init_assets();
+    let mut conf = build_conf();
+    conf.window_title = "prpr-pbc".to_owned();
+    conf.window_width = size.0 as _;
+    conf.window_height = size.1 as _;
+    Window::from_config(conf, async move {
+        let code = match task.await {
+            Ok(()) => 0,
+            Err(err) => {
+                eprintln!("{err:?}");
+                1
+            }
+        };
+        std::process::exit(code);
+    });
+    Ok(())
+}
+
+fn run_snapshot(chart: String, time: f32, output: String, size: (u32, u32)) -> Result<()> {
+    let fs = fs_from_file(Path::new(&chart))?;
+    run_in_window(size, async move {
+        let image = snapshot(fs, &Config::default(), time, size).await.context("Failed to take snapshot")?;
+        image.save(&output)?;
+        Ok(())
+    })
+}
+
+async fn load_package(path: &str) -> Result<(ChartInfo, Chart)> {
+    let mut fs = fs_from_file(Path::new(path))?;
+    let info = load_info(fs.as_mut()).await?;
+    let config = Config {
+        render_extra: false,
+        ..Default::default()
+    };
+    let (chart, ..) = GameScene::parse_chart(fs.as_mut(), &info, &config).await?;
+    Ok((info, chart))
+}
+
+fn run_diff(old: String, new: String, json: bool, tolerance: f32) -> Result<()> {
+    let rt = tokio::runtime::Builder::new_multi_thread().worker_threads(2).enable_all().build()?;
+    let diff = rt.block_on(async {
+        let (old_info, old_chart) = load_package(&old).await.with_context(|| format!("Failed to load {old}"))?;
+        let (new_info, new_chart) = load_package(&new).await.with_context(|| format!("Failed to load {new}"))?;
+        ChartDiff::new(&old_chart, &new_chart, tolerance).with_info(&old_info, &new_info)
+    })?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&diff)?);
+    } else {
+        print!("{diff}");
+    }
+    Ok(())
+}
+
+fn run_respack(pack: String, preview: Option<String>) -> Result<()> {
+    let rt = tokio::runtime::Builder::new_multi_thread().worker_threads(2).enable_all().build()?;
+    let report = rt.block_on(async {
+        let mut fs = fs_from_file(Path::new(&pack))?;
+        let report = validate(fs.as_mut()).await;
+        if let Some(preview) = preview {
+            // the sheet only needs textures, so it may still be rendered when other checks failed
+            match contact_sheet(fs.as_mut()).await {
+                Ok(sheet) => sheet.save(&preview).context("Failed to save contact sheet")?,
+                Err(err) => eprintln!("Failed to render contact sheet: {err:#}"),
+            }
+        }
+        Ok::<_, anyhow::Error>(report)
+    })?;
+    print!("{report}");
+    if report.has_errors() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
 fn main() -> Result<()> {
-    let mut iter = std::env::args().skip(1);
-    let mut input = None;
-    let mut output = None;
+    let mut iter = std::env::args().skip(1).peekable();
+    let command = if iter.next_if_eq("snapshot").is_some() {
+        Command::Snapshot
+    } else if iter.next_if_eq("diff").is_some() {
+        Command::Diff
+    } else if iter.next_if_eq("respack").is_some() {
+        Command::Respack
+    } else {
+        Command::Convert
+    };
+    let mut size = (1280, 720);
+    let mut json = false;
+    let mut tolerance = DEFAULT_TOLERANCE;
+    let mut preview = None;
+    let mut transforms = Transforms::default();
+    let mut args = Vec::new();
     while let Some(arg) = iter.next() {
         match arg.as_str() {
             "-h" | "--help" => {
                 println!("{}", HELP.trim());
                 return Ok(());
             }
+            "--size" if command == Command::Snapshot => {
+                size = parse_size(&iter.next().ok_or_else(|| anyhow!("Missing value for --size"))?)?;
+            }
+            "--json" if command == Command::Diff => {
+                json = true;
+            }
+            "--tolerance" if command == Command::Diff => {
+                let ms: f32 = iter.next().ok_or_else(|| anyhow!("Missing value for --tolerance"))?.parse().context("Invalid tolerance")?;
+                tolerance = ms / 1000.;
+            }
+            "--extract" if command == Command::Convert => {
+                transforms.extract = Some(parse_range(&iter.next().ok_or_else(|| anyhow!("Missing value for --extract"))?)?);
+            }
+            "--shift" if command == Command::Convert => {
+                transforms.shift = Some(iter.next().ok_or_else(|| anyhow!("Missing value for --shift"))?.parse().context("Invalid time shift")?);
+            }
+            "--rate" if command == Command::Convert => {
+                transforms.rate = Some(iter.next().ok_or_else(|| anyhow!("Missing value for --rate"))?.parse().context("Invalid rate")?);
+            }
+            "--mirror" if command == Command::Convert => {
+                transforms.mirror = true;
+            }
+            "--preview" if command == Command::Respack => {
+                preview = Some(iter.next().ok_or_else(|| anyhow!("Missing value for --preview"))?);
+            }
             _ => {
-                if input.is_none() {
-                    input = Some(arg);
-                } else if output.is_none() {
-                    output = Some(arg);
-                } else {
+                let max_args = match command {
+                    Command::Snapshot => 3,
+                    Command::Respack => 1,
+                    _ => 2,
+                };
+                if args.len() == max_args {
                     bail!("Too many arguments");
                 }
+                args.push(arg);
             }
         }
     }
+    let mut args = args.into_iter();
+
+    match command {
+        Command::Snapshot => {
+            let chart = args.next().ok_or_else(|| anyhow!("Missing chart"))?;
+            let time = args.next().ok_or_else(|| anyhow!("Missing time"))?.parse().context("Invalid time")?;
+            let output = args.next().ok_or_else(|| anyhow!("Missing output"))?;
+            return run_snapshot(chart, time, output, size);
+        }
+        Command::Diff => {
+            let old = args.next().ok_or_else(|| anyhow!("Missing old chart"))?;
+            let new = args.next().ok_or_else(|| anyhow!("Missing new chart"))?;
+            return run_diff(old, new, json, tolerance);
+        }
+        Command::Respack => {
+            let pack = args.next().ok_or_else(|| anyhow!("Missing resource pack"))?;
+            return run_respack(pack, preview);
+        }
+        Command::Convert => {}
+    }
 
-    let input = input.ok_or_else(|| anyhow!("Missing input"))?;
-    let output = output.ok_or_else(|| anyhow!("Missing output"))?;
+    let input = args.next().ok_or_else(|| anyhow!("Missing input"))?;
+    let output = args.next().ok_or_else(|| anyhow!("Missing output"))?;
 
     let bytes = std::fs::read(input).context("Failed to read chart")?;
     let format = if let Ok(text) = String::from_utf8(bytes.clone()) {
@@ -91,6 +304,7 @@ fn main() -> Result<()> {
             r.read()
         }
     }?;
+    transforms.apply(&mut chart)?;
 
     let output = BufWriter::new(File::create(output)?);
     let mut w = BinaryWriter::new(output);